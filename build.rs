@@ -13,4 +13,15 @@ fn main() {
     bindings
         .write_to_file(out_path.join("v4l2loopback.rs"))
         .expect("Couldn't write bindings for v4l2 loopback types");
+
+    let videodev2 = bindgen::Builder::default()
+        .header_contents("videodev2.h", "#include <linux/videodev2.h>")
+        .allowlist_type("v4l2_.*")
+        .allowlist_var("V4L2_.*")
+        .generate()
+        .expect("Unable to generate videodev2 bindings");
+
+    videodev2
+        .write_to_file(out_path.join("videodev2.rs"))
+        .expect("Couldn't write bindings for v4l2 types");
 }
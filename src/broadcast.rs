@@ -0,0 +1,105 @@
+use std::{fs::File, io::Write};
+
+use crate::{video::open_video_device, Error, Result};
+
+/// Writes the same frames to several devices.
+///
+/// The devices are opened once when creating the broadcaster and stay opened until it is
+/// dropped, so they all have to be configured with the same format beforehand.
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, Broadcaster, DeviceConfig};
+///
+/// let first = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let second = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let broadcaster = Broadcaster::new(&[first, second]).expect("Error when opening the devices");
+/// let frame = vec![0; 640 * 480 * 2];
+/// for (device_num, res) in broadcaster.devices().zip(broadcaster.broadcast_frame(&frame)) {
+///     res.unwrap_or_else(|e| panic!("Error when writing to /dev/video{}: {}", device_num, e));
+/// }
+///
+/// drop(broadcaster);
+/// delete_device(first).expect("Error when removing device");
+/// delete_device(second).expect("Error when removing device");
+/// ```
+#[derive(Debug)]
+pub struct Broadcaster {
+    devices: Vec<(u32, File)>,
+}
+
+impl Broadcaster {
+    /// Open every device in `device_nums` for writing.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`DeviceNotFound`] if one of the devices doesn't exist.
+    /// - [`Other`] if one of the devices couldn't be opened.
+    ///
+    /// [`DeviceNotFound`]: Error::DeviceNotFound
+    /// [`Other`]: Error::Other
+    pub fn new(device_nums: &[u32]) -> Result<Self> {
+        let devices = device_nums
+            .iter()
+            .map(|num| Ok((*num, open_video_device(*num, true)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { devices })
+    }
+
+    /// Numbers of the devices, in the order given to [`Broadcaster::new`].
+    pub fn devices(&self) -> impl Iterator<Item = u32> + '_ {
+        self.devices.iter().map(|(num, _)| *num)
+    }
+
+    /// Write `frame` to every device.
+    ///
+    /// A device failing doesn't prevent writing to the next ones. The result of each write is
+    /// returned in the same order as [`Broadcaster::devices`], with [`Error::Other`] wrapping
+    /// the I/O error for failed writes.
+    pub fn broadcast_frame(&self, frame: &[u8]) -> Vec<Result<()>> {
+        self.devices
+            .iter()
+            .map(|(_, file)| {
+                let mut file: &File = file;
+                file.write_all(frame).map_err(|e| Error::Other(Box::new(e)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::{add_device, delete_device, video::open_video_device, Broadcaster, PixelFormat};
+
+    #[test]
+    fn broadcast_to_two_devices() {
+        let first = add_device(None, Default::default()).expect("Error when creating the device");
+        let second = add_device(None, Default::default()).expect("Error when creating the device");
+
+        // Default format of v4l2loopback devices
+        let size = PixelFormat::Yuyv.image_size(640, 480).unwrap();
+        let frame: Vec<u8> = (0..size).map(|i| i as u8).collect();
+
+        let broadcaster = Broadcaster::new(&[first, second]).expect("Error when opening devices");
+        for res in broadcaster.broadcast_frame(&frame) {
+            res.expect("Error when writing the frame");
+        }
+
+        for device_num in [first, second] {
+            let mut consumer = open_video_device(device_num, false).unwrap();
+            let mut read = vec![0; size];
+            consumer.read_exact(&mut read).unwrap();
+            assert_eq!(read, frame);
+        }
+
+        drop(broadcaster);
+        delete_device(first).expect("Error when removing device");
+        delete_device(second).expect("Error when removing device");
+    }
+}
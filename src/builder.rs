@@ -0,0 +1,193 @@
+use crate::{query_device, DebugFlags, DeviceConfig, Result};
+
+/// Builder of a [`DeviceConfig`], setting one field at a time.
+///
+/// Fields which are not set keep the values of [`DeviceConfig::default`], or the values of an
+/// existing device with [`DeviceConfigBuilder::from_existing`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, DeviceConfigBuilder};
+///
+/// let config = DeviceConfigBuilder::new()
+///     .label("Camera")
+///     .max_width(1920)
+///     .max_height(1080)
+///     .build();
+/// let device_num = add_device(None, config).expect("Error when creating the device");
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct DeviceConfigBuilder {
+    config: DeviceConfig,
+}
+
+impl DeviceConfigBuilder {
+    /// Start from the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from the configuration of the existing device `/dev/videoN`, so only the fields
+    /// to change have to be set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return any error from [`query_device`], like [`DeviceNotFound`] if
+    /// the device doesn't exist.
+    ///
+    /// [`DeviceNotFound`]: crate::Error::DeviceNotFound
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{add_device, delete_device, DeviceConfig, DeviceConfigBuilder};
+    ///
+    /// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+    ///
+    /// let config = DeviceConfigBuilder::from_existing(device_num)
+    ///     .expect("Error when querying the device")
+    ///     .label("Copy")
+    ///     .build();
+    /// let copy_num = add_device(None, config).expect("Error when creating the device");
+    ///
+    /// delete_device(copy_num).expect("Error when removing device");
+    /// delete_device(device_num).expect("Error when removing device");
+    /// ```
+    pub fn from_existing(device_num: u32) -> Result<Self> {
+        Ok(Self {
+            config: query_device(device_num)?,
+        })
+    }
+
+    /// Set [`DeviceConfig::label`].
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.config.label = label.into();
+        self
+    }
+
+    /// Set [`DeviceConfig::min_width`].
+    pub fn min_width(mut self, min_width: u32) -> Self {
+        self.config.min_width = min_width;
+        self
+    }
+
+    /// Set [`DeviceConfig::max_width`].
+    pub fn max_width(mut self, max_width: u32) -> Self {
+        self.config.max_width = max_width;
+        self
+    }
+
+    /// Set [`DeviceConfig::min_height`].
+    pub fn min_height(mut self, min_height: u32) -> Self {
+        self.config.min_height = min_height;
+        self
+    }
+
+    /// Set [`DeviceConfig::max_height`].
+    pub fn max_height(mut self, max_height: u32) -> Self {
+        self.config.max_height = max_height;
+        self
+    }
+
+    /// Set [`DeviceConfig::max_buffers`].
+    pub fn max_buffers(mut self, max_buffers: u32) -> Self {
+        self.config.max_buffers = max_buffers;
+        self
+    }
+
+    /// Set [`DeviceConfig::max_openers`].
+    pub fn max_openers(mut self, max_openers: u32) -> Self {
+        self.config.max_openers = max_openers;
+        self
+    }
+
+    /// Set [`DeviceConfig::announce_all_caps`].
+    pub fn announce_all_caps(mut self, announce_all_caps: bool) -> Self {
+        self.config.announce_all_caps = announce_all_caps;
+        self
+    }
+
+    /// Set [`DeviceConfig::debug`].
+    pub fn debug(mut self, debug: DebugFlags) -> Self {
+        self.config.debug = debug;
+        self
+    }
+
+    /// The configuration built so far.
+    pub fn build(self) -> DeviceConfig {
+        self.config
+    }
+}
+
+impl From<DeviceConfig> for DeviceConfigBuilder {
+    fn from(config: DeviceConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceConfigBuilder;
+    use crate::{add_device, delete_device, DebugFlags, DeviceConfig, Error};
+
+    #[test]
+    fn builder_fields() {
+        let config = DeviceConfigBuilder::new()
+            .label("Built")
+            .min_width(64)
+            .max_width(1920)
+            .min_height(48)
+            .max_height(1080)
+            .max_buffers(4)
+            .max_openers(2)
+            .announce_all_caps(true)
+            .debug(DebugFlags::IOCTL)
+            .build();
+        assert_eq!(
+            config,
+            DeviceConfig {
+                label: "Built".to_string(),
+                min_width: 64,
+                max_width: 1920,
+                min_height: 48,
+                max_height: 1080,
+                max_buffers: 4,
+                max_openers: 2,
+                announce_all_caps: true,
+                debug: DebugFlags::IOCTL,
+            }
+        );
+    }
+
+    #[test]
+    fn seeded_from_existing_device() {
+        assert!(matches!(
+            DeviceConfigBuilder::from_existing(u32::MAX),
+            Err(Error::DeviceNotFound(_))
+        ));
+
+        let original = DeviceConfig {
+            label: "Original".to_string(),
+            max_width: 1280,
+            max_height: 720,
+            max_buffers: 3,
+            ..Default::default()
+        };
+        let device_num = add_device(None, original).expect("Error when creating the device");
+
+        let config = DeviceConfigBuilder::from_existing(device_num)
+            .expect("Error when querying the device")
+            .label("Tweaked")
+            .build();
+        delete_device(device_num).expect("Error when removing device");
+
+        assert_eq!(config.label, "Tweaked");
+        assert_eq!(
+            (config.max_width, config.max_height, config.max_buffers),
+            (1280, 720, 3)
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use std::os::fd::AsRawFd;
+
+use crate::{
+    list_devices, query_device, sys::ioctl_read, video::open_video_device, videodev2, Errno, Error,
+    Result,
+};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Capabilities announced by a video device.
+    ///
+    /// Only the capabilities relevant to v4l2loopback devices are named, other bits are kept
+    /// as is.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    pub struct DeviceCaps: u32 {
+        /// The device can be used to capture video.
+        const VIDEO_CAPTURE = videodev2::V4L2_CAP_VIDEO_CAPTURE;
+        /// The device can be used to output video.
+        const VIDEO_OUTPUT = videodev2::V4L2_CAP_VIDEO_OUTPUT;
+        /// The device can capture video using the multi-planar API.
+        const VIDEO_CAPTURE_MPLANE = videodev2::V4L2_CAP_VIDEO_CAPTURE_MPLANE;
+        /// The device can output video using the multi-planar API.
+        const VIDEO_OUTPUT_MPLANE = videodev2::V4L2_CAP_VIDEO_OUTPUT_MPLANE;
+        /// The device is a memory to memory device.
+        const VIDEO_M2M = videodev2::V4L2_CAP_VIDEO_M2M;
+        /// The device supports the extended pixel format fields.
+        const EXT_PIX_FORMAT = videodev2::V4L2_CAP_EXT_PIX_FORMAT;
+        /// The device supports `read()` and `write()`.
+        const READWRITE = videodev2::V4L2_CAP_READWRITE;
+        /// The device supports the streaming I/O ioctls.
+        const STREAMING = videodev2::V4L2_CAP_STREAMING;
+        /// The `device_caps` field is filled.
+        const DEVICE_CAPS = videodev2::V4L2_CAP_DEVICE_CAPS;
+
+        const _ = !0;
+    }
+}
+
+/// Informations returned by `VIDIOC_QUERYCAP` for a video device.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Capabilities {
+    /// Name of the driver, `v4l2 loopback` for v4l2loopback devices.
+    pub driver: String,
+    /// Name of the device, which is the label for v4l2loopback devices.
+    pub card: String,
+    /// Location of the device in the system.
+    pub bus_info: String,
+    /// Version of the driver.
+    pub version: u32,
+    /// Capabilities of the physical device as a whole.
+    pub capabilities: DeviceCaps,
+    /// Capabilities of this particular device node.
+    pub device_caps: DeviceCaps,
+}
+
+impl Capabilities {
+    /// Whether the device is managed by v4l2loopback, based on its driver name.
+    pub fn is_loopback(&self) -> bool {
+        self.driver == "v4l2 loopback"
+    }
+}
+
+/// Read a NUL terminated string from a fixed size buffer.
+pub(crate) fn string_from_bytes(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+/// Query the capabilities of a video device.
+///
+/// This works with any video device, not only v4l2loopback ones.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, query_capabilities, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     label: "Test Device".to_string(),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// let caps = query_capabilities(device_num).expect("Error when querying capabilities");
+/// assert_eq!(caps.card, "Test Device");
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn query_capabilities(device_num: u32) -> Result<Capabilities> {
+    let file = open_video_device(device_num, false)?;
+
+    ioctl_read!(vidioc_querycap, b'V', 0, videodev2::v4l2_capability);
+
+    let mut caps: videodev2::v4l2_capability = unsafe { std::mem::zeroed() };
+    unsafe { vidioc_querycap(file.as_raw_fd(), &mut caps) }?;
+
+    Ok(Capabilities {
+        driver: string_from_bytes(&caps.driver),
+        card: string_from_bytes(&caps.card),
+        bus_info: string_from_bytes(&caps.bus_info),
+        version: caps.version,
+        capabilities: DeviceCaps::from_bits_retain(caps.capabilities),
+        device_caps: DeviceCaps::from_bits_retain(caps.device_caps),
+    })
+}
+
+/// Capabilities a device currently announces to applications.
+///
+/// This is what applications listing cameras will see, and depends on
+/// [`announce_all_caps`](crate::DeviceConfig::announce_all_caps):
+/// - If it is enabled, the device announces both [`VIDEO_CAPTURE`] and [`VIDEO_OUTPUT`].
+/// - Otherwise, the device only announces [`VIDEO_OUTPUT`] until a producer writes to it, then
+///   only [`VIDEO_CAPTURE`].
+///
+/// The interaction between those modes changed across v4l2loopback versions, so this can be
+/// used to verify that a device behaves as intended.
+///
+/// # Errors
+///
+/// See [`query_capabilities`].
+///
+/// [`VIDEO_CAPTURE`]: DeviceCaps::VIDEO_CAPTURE
+/// [`VIDEO_OUTPUT`]: DeviceCaps::VIDEO_OUTPUT
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, effective_caps, DeviceCaps, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     announce_all_caps: true,
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// let caps = effective_caps(device_num).expect("Error when querying capabilities");
+/// assert!(caps.contains(DeviceCaps::VIDEO_CAPTURE | DeviceCaps::VIDEO_OUTPUT));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn effective_caps(device_num: u32) -> Result<DeviceCaps> {
+    let caps = query_capabilities(device_num)?;
+
+    if caps.capabilities.contains(DeviceCaps::DEVICE_CAPS) {
+        Ok(caps.device_caps)
+    } else {
+        Ok(caps.capabilities)
+    }
+}
+
+/// Numbers of the v4l2loopback devices announcing all the `required` capabilities, in
+/// ascending order.
+///
+/// Capabilities are checked with [`effective_caps`], so devices in exclusive caps mode only
+/// match [`VIDEO_OUTPUT`] until a producer writes to them, and [`VIDEO_CAPTURE`] afterwards.
+///
+/// [`VIDEO_CAPTURE`]: DeviceCaps::VIDEO_CAPTURE
+/// [`VIDEO_OUTPUT`]: DeviceCaps::VIDEO_OUTPUT
+///
+/// # Errors
+///
+/// See [`list_devices`] and [`query_capabilities`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{list_devices_with_caps, DeviceCaps};
+///
+/// let outputs = list_devices_with_caps(DeviceCaps::VIDEO_OUTPUT).expect("Error when listing devices");
+/// for device_num in outputs {
+///     println!("Frames can be written to /dev/video{}", device_num);
+/// }
+/// ```
+pub fn list_devices_with_caps(required: DeviceCaps) -> Result<Vec<u32>> {
+    let mut devices = Vec::new();
+    for device_num in list_devices()? {
+        match effective_caps(device_num) {
+            Ok(caps) if caps.contains(required) => devices.push(device_num),
+            Ok(_) => {}
+            // Removed while listing the devices
+            Err(Error::DeviceNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Result of [`verify_label`], comparing the names a device is known by.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LabelCheck {
+    /// Label reported by the control device, see [`query_device`].
+    pub label: String,
+    /// Name reported by `VIDIOC_QUERYCAP`, see [`query_capabilities`]. This is the name
+    /// applications display.
+    pub card: String,
+    /// Whether both names are the same.
+    pub matches: bool,
+}
+
+/// Compare the label of a device with the name applications see.
+///
+/// Both names are usually the same, but they come from different paths in v4l2loopback and
+/// can diverge. This helps diagnosing applications showing a device under an unexpected name.
+///
+/// # Errors
+///
+/// See [`query_device`] and [`query_capabilities`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, verify_label, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     label: "Test Device".to_string(),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// let check = verify_label(device_num).expect("Error when querying the device");
+/// if !check.matches {
+///     println!("{:?} is displayed as {:?}", check.label, check.card);
+/// }
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn verify_label(device_num: u32) -> Result<LabelCheck> {
+    let label = query_device(device_num)?.label;
+    let card = query_capabilities(device_num)?.card;
+
+    Ok(LabelCheck {
+        matches: label == card,
+        label,
+        card,
+    })
+}
+
+/// Whether announcing `caps` means the device is in exclusive caps mode.
+fn exclusive_from_caps(caps: DeviceCaps) -> bool {
+    !caps.contains(DeviceCaps::VIDEO_CAPTURE | DeviceCaps::VIDEO_OUTPUT)
+}
+
+/// Whether a device is running in exclusive caps mode.
+///
+/// This is the opposite of [`announce_all_caps`](crate::DeviceConfig::announce_all_caps), as
+/// reported by v4l2loopback for the running device. It can differ from the configuration a
+/// device was expected to have, for example if it was created by `modprobe`.
+///
+/// Older versions of v4l2loopback don't report this flag, in that case it is deduced from the
+/// capabilities the device announces (see [`effective_caps`]).
+///
+/// # Errors
+///
+/// See [`query_device`] and [`query_capabilities`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, is_exclusive, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     announce_all_caps: false,
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// assert!(is_exclusive(device_num).expect("Error when querying the device"));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn is_exclusive(device_num: u32) -> Result<bool> {
+    match query_device(device_num) {
+        Ok(config) => Ok(!config.announce_all_caps),
+        Err(Error::Unsupported(_) | Error::Ioctl(Errno::EINVAL)) => {
+            Ok(exclusive_from_caps(effective_caps(device_num)?))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exclusive_from_caps, string_from_bytes};
+    use crate::{
+        add_device, delete_device, effective_caps, is_exclusive, list_devices_with_caps,
+        verify_label, Capabilities, DeviceCaps, DeviceConfig,
+    };
+
+    #[test]
+    fn strings_from_bytes() {
+        assert_eq!(string_from_bytes(b"v4l2 loopback\0\0\0"), "v4l2 loopback");
+        assert_eq!(string_from_bytes(b"full"), "full");
+    }
+
+    #[test]
+    fn caps_match_config() {
+        let both = DeviceCaps::VIDEO_CAPTURE | DeviceCaps::VIDEO_OUTPUT;
+
+        for announce_all_caps in [true, false] {
+            let config = DeviceConfig {
+                announce_all_caps,
+                ..Default::default()
+            };
+            let device_num = add_device(None, config).expect("Error when creating the device");
+
+            let caps = effective_caps(device_num).expect("Error when querying capabilities");
+            if announce_all_caps {
+                assert!(caps.contains(both));
+            } else {
+                // Nothing is writing to the device
+                assert!(caps.contains(DeviceCaps::VIDEO_OUTPUT));
+                assert!(!caps.contains(DeviceCaps::VIDEO_CAPTURE));
+            }
+
+            delete_device(device_num).expect("Error when removing device");
+        }
+    }
+
+    #[test]
+    fn exclusive_matches_config() {
+        for announce_all_caps in [true, false] {
+            let config = DeviceConfig {
+                announce_all_caps,
+                ..Default::default()
+            };
+            let device_num = add_device(None, config).expect("Error when creating the device");
+
+            let exclusive = is_exclusive(device_num).expect("Error when querying the device");
+            assert_eq!(exclusive, !announce_all_caps);
+
+            delete_device(device_num).expect("Error when removing device");
+        }
+    }
+
+    #[test]
+    fn label_matches_card() {
+        let config = DeviceConfig {
+            label: "Checked Device".to_string(),
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+
+        let check = verify_label(device_num).expect("Error when querying the device");
+        assert_eq!(check.label, "Checked Device");
+        assert_eq!(check.card, "Checked Device");
+        assert!(check.matches);
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn exclusive_deduced_from_caps() {
+        assert!(exclusive_from_caps(DeviceCaps::VIDEO_OUTPUT));
+        assert!(exclusive_from_caps(DeviceCaps::VIDEO_CAPTURE));
+        assert!(!exclusive_from_caps(
+            DeviceCaps::VIDEO_CAPTURE | DeviceCaps::VIDEO_OUTPUT | DeviceCaps::STREAMING
+        ));
+    }
+
+    #[test]
+    fn loopback_driver() {
+        let mut caps = Capabilities {
+            driver: "v4l2 loopback".to_string(),
+            card: "Dummy video device (0x0000)".to_string(),
+            bus_info: "platform:v4l2loopback-000".to_string(),
+            version: 0,
+            capabilities: DeviceCaps::empty(),
+            device_caps: DeviceCaps::empty(),
+        };
+        assert!(caps.is_loopback());
+
+        caps.driver = "uvcvideo".to_string();
+        assert!(!caps.is_loopback());
+    }
+
+    #[test]
+    fn devices_filtered_by_caps() {
+        let config = DeviceConfig {
+            announce_all_caps: true,
+            ..Default::default()
+        };
+        let all_caps = add_device(None, config).expect("Error when creating the device");
+        let exclusive =
+            add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+
+        let outputs =
+            list_devices_with_caps(DeviceCaps::VIDEO_OUTPUT).expect("Error when listing devices");
+        assert!(outputs.contains(&all_caps));
+        assert!(outputs.contains(&exclusive));
+        let both = list_devices_with_caps(DeviceCaps::VIDEO_OUTPUT | DeviceCaps::VIDEO_CAPTURE)
+            .expect("Error when listing devices");
+        assert!(both.contains(&all_caps));
+        assert!(!both.contains(&exclusive));
+
+        delete_device(all_caps).expect("Error when removing device");
+        delete_device(exclusive).expect("Error when removing device");
+    }
+}
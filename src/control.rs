@@ -0,0 +1,774 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::ErrorKind,
+    os::{
+        fd::{AsRawFd, OwnedFd, RawFd},
+        unix::fs::FileTypeExt,
+    },
+    path::Path,
+    sync::OnceLock,
+};
+
+use crate::{
+    add_device_with, delete_device_with,
+    module::{max_devices_in, DEFAULT_DEVICE_LIMIT, MODULE_ROOT},
+    query_device_with, registry, settings,
+    sys::libc,
+    ControlDeviceError, Device, DeviceConfig, Error, Result, Settings,
+};
+
+/// Default path of the control device, see [`Settings::control_path`].
+const CONTROL_PATH: &str = "/dev/v4l2loopback";
+
+thread_local! {
+    /// Control device kept opened for the current thread, see [`enable_thread_control`].
+    static THREAD_CONTROL: RefCell<Option<OwnedFd>> = const { RefCell::new(None) };
+}
+
+/// File descriptor of the control device, closed when dropped unless it is borrowed from a
+/// [`Control`] or from the current thread's cache.
+pub(crate) enum ControlFd {
+    Owned(OwnedFd),
+    Borrowed(RawFd),
+}
+
+impl AsRawFd for ControlFd {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            ControlFd::Owned(fd) => fd.as_raw_fd(),
+            ControlFd::Borrowed(fd) => *fd,
+        }
+    }
+}
+
+/// Let the file descriptor be inherited by child processes.
+fn clear_cloexec(fd: &OwnedFd) -> std::io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+    if flags < 0
+        || unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, flags & !libc::FD_CLOEXEC) } < 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Path of the control device with `settings`.
+pub(crate) fn control_path(settings: &Settings) -> &Path {
+    match &settings.control_path {
+        Some(path) => path.as_path(),
+        None => Path::new(CONTROL_PATH),
+    }
+}
+
+fn open_new_control_device(settings: &Settings) -> Result<OwnedFd, ControlDeviceError> {
+    let path = control_path(settings);
+    let file = match OpenOptions::new().read(true).open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            return Err(match e.kind() {
+                ErrorKind::NotFound => ControlDeviceError::NotFound,
+                ErrorKind::PermissionDenied => ControlDeviceError::PermissionDenied,
+                _ => ControlDeviceError::Other(Box::new(e)),
+            })
+        }
+    };
+
+    // Ioctls on anything else fail with ENOTTY, which doesn't point at the actual problem
+    match file.metadata() {
+        Ok(metadata) if metadata.file_type().is_char_device() => {}
+        Ok(_) => return Err(ControlDeviceError::NotACharacterDevice(path.to_path_buf())),
+        Err(e) => return Err(ControlDeviceError::Other(Box::new(e))),
+    }
+
+    let fd = OwnedFd::from(file);
+    // The standard library always opens files with O_CLOEXEC
+    if !settings.control_cloexec {
+        if let Err(e) = clear_cloexec(&fd) {
+            return Err(ControlDeviceError::Other(Box::new(e)));
+        }
+    }
+
+    Ok(fd)
+}
+
+/// Open the control device, or reuse the one cached for the current thread.
+pub(crate) fn open_control_device() -> Result<ControlFd, ControlDeviceError> {
+    let cached = THREAD_CONTROL.with(|control| control.borrow().as_ref().map(AsRawFd::as_raw_fd));
+
+    match cached {
+        Some(fd) => Ok(ControlFd::Borrowed(fd)),
+        None => Ok(ControlFd::Owned(open_new_control_device(&settings())?)),
+    }
+}
+
+/// Keep the control device opened for the current thread.
+///
+/// By default, every function of this crate opens the control device when it needs it and
+/// closes it afterwards. Once this is called, the functions called from the current thread
+/// reuse the same file descriptor instead, until [`clear_thread_control`] is called or the
+/// thread exits. Other threads are not affected.
+///
+/// The control device is opened with the current [`Settings`], changing them afterwards
+/// doesn't affect the cached file descriptor.
+///
+/// Calling this when the control device is already cached does nothing.
+///
+/// # Errors
+///
+/// This function will return [`ControlDevice`] if it is unable to open the control device.
+///
+/// [`ControlDevice`]: Error::ControlDevice
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, clear_thread_control, delete_device, enable_thread_control, DeviceConfig};
+///
+/// enable_thread_control().expect("Error when opening the control device");
+///
+/// // Both calls use the same file descriptor
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// delete_device(device_num).expect("Error when removing device");
+///
+/// clear_thread_control();
+/// ```
+pub fn enable_thread_control() -> Result<()> {
+    if THREAD_CONTROL.with(|control| control.borrow().is_some()) {
+        return Ok(());
+    }
+
+    let fd = open_new_control_device(&settings())?;
+    THREAD_CONTROL.with(|control| *control.borrow_mut() = Some(fd));
+
+    Ok(())
+}
+
+/// Close the control device cached for the current thread by [`enable_thread_control`].
+///
+/// Functions called from the current thread go back to opening the control device on every
+/// call.
+pub fn clear_thread_control() {
+    THREAD_CONTROL.with(|control| control.borrow_mut().take());
+}
+
+/// How the number of a device created through a [`Control`] was chosen, see
+/// [`Control::assignment`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Assignment {
+    /// The number was requested by the caller.
+    Explicit,
+    /// The number was picked by v4l2loopback, since no number was requested.
+    Auto,
+}
+
+/// Handle to the control device, keeping it opened.
+///
+/// The free functions of this crate open the control device on every call, while a handle
+/// opens it once and reuses it for every operation made through it.
+///
+/// The handle also keeps track of the devices created through it, so they can be deleted
+/// without touching the devices created by other applications, see [`Control::delete_owned`].
+/// Those devices are not deleted when the handle is dropped.
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{Control, DeviceConfig};
+///
+/// let mut control = Control::open().expect("Error when opening the control device");
+///
+/// let device_num = control
+///     .add_device(None, DeviceConfig::default())
+///     .expect("Error when creating the device");
+/// assert_eq!(control.owned_devices(), &[device_num]);
+///
+/// control.delete_owned().expect("Error when removing devices");
+/// assert!(control.owned_devices().is_empty());
+/// ```
+#[derive(Debug)]
+pub struct Control {
+    fd: OwnedFd,
+    owned: Vec<u32>,
+    assignments: BTreeMap<u32, Assignment>,
+    device_limit: OnceLock<u32>,
+}
+
+impl Control {
+    /// Open the control device.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ControlDevice`] if it is unable to open the control device.
+    ///
+    /// [`ControlDevice`]: Error::ControlDevice
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            fd: open_new_control_device(&settings())?,
+            owned: Vec::new(),
+            assignments: BTreeMap::new(),
+            device_limit: OnceLock::new(),
+        })
+    }
+
+    /// Wrap a file descriptor of the control device opened by someone else.
+    ///
+    /// This is meant for daemons which receive the control device from their parent, for
+    /// example with systemd socket activation (`LISTEN_FDS`) or fd passing over a Unix socket,
+    /// instead of opening it themselves. `fd` must refer to the control device
+    /// (`/dev/v4l2loopback`): this isn't checked, and ioctls on anything else fail, usually
+    /// with [`ENOTTY`](crate::Errno::ENOTTY).
+    ///
+    /// The handle owns `fd` and closes it when dropped. Its close-on-exec flag is kept as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::{fs::File, os::fd::OwnedFd};
+    /// use v4l2loopback::Control;
+    ///
+    /// // Usually inherited from the parent process
+    /// let fd = OwnedFd::from(File::open("/dev/v4l2loopback").expect("Error when opening the control device"));
+    ///
+    /// let control = Control::from_owned_fd(fd);
+    /// ```
+    pub fn from_owned_fd(fd: OwnedFd) -> Self {
+        Self {
+            fd,
+            owned: Vec::new(),
+            assignments: BTreeMap::new(),
+            device_limit: OnceLock::new(),
+        }
+    }
+
+    /// Duplicate the handle, without opening the control device again.
+    ///
+    /// The new handle gets its own file descriptor, closed when it is dropped, but both refer
+    /// to the same opened control device in the kernel. This is cheaper than [`Control::open`]
+    /// to give a handle to every worker of a pool.
+    ///
+    /// The new handle starts without [owned devices](Control::owned_devices), so each device
+    /// is only deleted by the handle which created it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ControlDevice`] if the file descriptor couldn't be
+    /// duplicated.
+    ///
+    /// [`ControlDevice`]: Error::ControlDevice
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::thread;
+    /// use v4l2loopback::{Control, DeviceConfig};
+    ///
+    /// let mut control = Control::open().expect("Error when opening the control device");
+    /// let device_num = control
+    ///     .add_device(None, DeviceConfig::default())
+    ///     .expect("Error when creating the device");
+    ///
+    /// let worker = control.try_clone().expect("Error when cloning the handle");
+    /// let queried = thread::spawn(move || worker.query_device(device_num).is_ok());
+    /// assert!(queried.join().unwrap());
+    ///
+    /// control.delete_owned().expect("Error when removing devices");
+    /// ```
+    pub fn try_clone(&self) -> Result<Self> {
+        // The duplicate is always created with O_CLOEXEC, like a newly opened control device
+        let fd = match self.fd.try_clone() {
+            Ok(fd) => fd,
+            Err(e) => return Err(ControlDeviceError::Other(Box::new(e)).into()),
+        };
+        if !settings().control_cloexec {
+            if let Err(e) = clear_cloexec(&fd) {
+                return Err(ControlDeviceError::Other(Box::new(e)).into());
+            }
+        }
+
+        Ok(Self {
+            fd,
+            owned: Vec::new(),
+            assignments: BTreeMap::new(),
+            device_limit: self.device_limit.clone(),
+        })
+    }
+
+    fn borrow_fd(&self) -> impl FnOnce() -> Result<ControlFd, ControlDeviceError> {
+        let fd = self.fd.as_raw_fd();
+        move || Ok(ControlFd::Borrowed(fd))
+    }
+
+    /// Create a new v4l2loopback device, see [`add_device`](crate::add_device).
+    ///
+    /// The device is added to the [owned devices](Control::owned_devices), and whether `num`
+    /// was given is recorded, see [`Control::assignment`].
+    pub fn add_device(&mut self, num: Option<u32>, config: DeviceConfig) -> Result<u32> {
+        let assignment = match num {
+            Some(_) => Assignment::Explicit,
+            None => Assignment::Auto,
+        };
+        let num = add_device_with(&settings(), self.borrow_fd(), num, config)?;
+        self.owned.push(num);
+        self.assignments.insert(num, assignment);
+
+        Ok(num)
+    }
+
+    /// Delete a v4l2loopback device, see [`delete_device`](crate::delete_device).
+    ///
+    /// This works with any v4l2loopback device, not only owned ones. If the device is owned,
+    /// it is removed from the owned devices.
+    pub fn delete_device(&mut self, device_num: u32) -> Result<()> {
+        delete_device_with(&settings(), self.borrow_fd(), device_num)?;
+        self.owned.retain(|num| *num != device_num);
+        self.assignments.remove(&device_num);
+
+        Ok(())
+    }
+
+    /// Query the configuration of a device, see [`query_device`](crate::query_device).
+    pub fn query_device(&self, device_num: u32) -> Result<DeviceConfig> {
+        Ok(query_device_with(self.borrow_fd(), device_num)?.config)
+    }
+
+    /// Wrap an existing device in a [`Device`] handle, without creating it.
+    ///
+    /// The device is deleted when the handle is dropped, like devices created with
+    /// [`Device::new`]. It isn't added to the owned devices of this handle.
+    ///
+    /// # Errors
+    ///
+    /// See [`Control::query_device`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{add_device, Control, DeviceConfig};
+    ///
+    /// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+    ///
+    /// let control = Control::open().expect("Error when opening the control device");
+    /// let device = control.adopt(device_num).expect("Error when adopting the device");
+    /// assert_eq!(device.number(), device_num);
+    /// ```
+    pub fn adopt(&self, device_num: u32) -> Result<Device> {
+        let config = self.query_device(device_num)?;
+        Ok(Device::adopted(device_num, config))
+    }
+
+    /// Adopt the devices recorded in `registry` by [`Device::persist`].
+    ///
+    /// A device is only adopted if it still exists with the label it had when persisted, so a
+    /// device deleted and replaced by another application in the meantime is left alone.
+    /// Once every device is checked, the registry is emptied, the adopted devices have to be
+    /// persisted again to be kept across another restart.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Other`] if the registry couldn't be read or emptied, or any
+    /// error from [`Control::query_device`] other than [`DeviceNotFound`]. On a query error,
+    /// no device is deleted: the devices adopted so far are released, and the registry keeps
+    /// them along with the devices not checked yet, so adopting can be tried again.
+    ///
+    /// [`Other`]: Error::Other
+    /// [`DeviceNotFound`]: Error::DeviceNotFound
+    pub fn adopt_persisted(&self, registry: impl AsRef<Path>) -> Result<Vec<Device>> {
+        adopt_records(registry.as_ref(), |device_num| {
+            self.query_device(device_num)
+        })
+    }
+
+    /// Maximal number of devices the loaded module accepts.
+    ///
+    /// This is read from the module's `max_devices` parameter the first time, then kept by the
+    /// handle. Versions of v4l2loopback which don't expose this parameter accept 8 devices,
+    /// which is returned in that case.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`ModuleNotLoaded`] if the kernel module is not loaded.
+    ///
+    /// [`ModuleNotLoaded`]: Error::ModuleNotLoaded
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{list_devices, Control};
+    ///
+    /// let control = Control::open().expect("Error when opening the control device");
+    /// let limit = control.device_limit().expect("Error when reading the device limit");
+    /// let count = list_devices().expect("Error when listing devices").len();
+    /// println!("{} more devices can be created", (limit as usize).saturating_sub(count));
+    /// ```
+    pub fn device_limit(&self) -> Result<u32> {
+        if let Some(limit) = self.device_limit.get() {
+            return Ok(*limit);
+        }
+
+        let root = Path::new(MODULE_ROOT);
+        if !root.is_dir() {
+            return Err(Error::ModuleNotLoaded);
+        }
+        let limit = max_devices_in(root).unwrap_or(DEFAULT_DEVICE_LIMIT);
+
+        Ok(*self.device_limit.get_or_init(|| limit))
+    }
+
+    /// Numbers of the devices created through this handle and not deleted yet, in creation
+    /// order.
+    pub fn owned_devices(&self) -> &[u32] {
+        &self.owned
+    }
+
+    /// Whether the number of an owned device was requested or picked by v4l2loopback.
+    ///
+    /// Returns [`None`] if the device wasn't created through this handle, or was deleted
+    /// since.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Assignment, Control, DeviceConfig};
+    ///
+    /// let mut control = Control::open().expect("Error when opening the control device");
+    /// let device_num = control
+    ///     .add_device(None, DeviceConfig::default())
+    ///     .expect("Error when creating the device");
+    /// assert_eq!(control.assignment(device_num), Some(Assignment::Auto));
+    ///
+    /// control.delete_owned().expect("Error when removing devices");
+    /// assert_eq!(control.assignment(device_num), None);
+    /// ```
+    pub fn assignment(&self, device_num: u32) -> Option<Assignment> {
+        self.assignments.get(&device_num).copied()
+    }
+
+    /// Delete every device created through this handle.
+    ///
+    /// # Errors
+    ///
+    /// This stops at the first device which couldn't be deleted, and returns the error from
+    /// [`Control::delete_device`]. The remaining devices are kept in the owned devices.
+    pub fn delete_owned(&mut self) -> Result<()> {
+        while let Some(device_num) = self.owned.last().copied() {
+            self.delete_device(device_num)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Adopt the devices recorded in `registry`, querying them with `query`, see
+/// [`Control::adopt_persisted`].
+fn adopt_records(
+    registry: &Path,
+    mut query: impl FnMut(u32) -> Result<DeviceConfig>,
+) -> Result<Vec<Device>> {
+    let records = registry::read_all(registry)?;
+
+    let mut devices = Vec::new();
+    for (i, (device_num, label)) in records.iter().enumerate() {
+        let config = match query(*device_num) {
+            Ok(config) => config,
+            Err(Error::DeviceNotFound(_)) => continue,
+            Err(e) => {
+                // Dropping the adopted devices would delete them
+                let mut kept: Vec<_> = devices
+                    .into_iter()
+                    .map(|device: Device| {
+                        let label = device.config().label.clone();
+                        (device.leak(), label)
+                    })
+                    .collect();
+                kept.extend_from_slice(&records[i..]);
+                if let Err(rewrite) = registry::write_all(registry, &kept) {
+                    log::warn!("Couldn't restore the registry of devices: {}", rewrite);
+                }
+                return Err(e);
+            }
+        };
+        if config.label == *label {
+            devices.push(Device::adopted(*device_num, config));
+        }
+    }
+    registry::write_all(registry, &[])?;
+
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        os::fd::{AsRawFd, OwnedFd},
+        path::{Path, PathBuf},
+    };
+
+    use super::{
+        adopt_records, control_path, open_control_device, open_new_control_device, ControlFd,
+    };
+    use crate::{
+        add_device, clear_thread_control, delete_device, enable_thread_control,
+        next_free_device_numbers, registry, settings, sys::libc, Assignment, Control,
+        ControlDeviceError, Device, DeviceConfig, Errno, Error, Settings,
+    };
+
+    fn has_cloexec(fd: &impl AsRawFd) -> bool {
+        let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+        assert!(flags >= 0);
+        flags & libc::FD_CLOEXEC != 0
+    }
+
+    #[test]
+    fn control_cloexec() {
+        let fd = open_control_device().expect("Error when opening the control device");
+        assert!(has_cloexec(&fd));
+
+        let settings = Settings {
+            control_cloexec: false,
+            ..Default::default()
+        };
+        let fd = open_new_control_device(&settings).expect("Error when opening the control device");
+        assert!(!has_cloexec(&fd));
+    }
+
+    #[test]
+    fn control_not_a_device() {
+        let path = std::env::temp_dir().join(format!(
+            "v4l2loopback-control-{}-regular",
+            std::process::id()
+        ));
+        fs::write(&path, "").unwrap();
+
+        let settings = Settings {
+            control_path: Some(path.clone()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            open_new_control_device(&settings),
+            Err(ControlDeviceError::NotACharacterDevice(p)) if p == path
+        ));
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            open_new_control_device(&settings),
+            Err(ControlDeviceError::NotFound)
+        ));
+    }
+
+    /// File descriptors of the process referring to the control device.
+    fn control_fds() -> Vec<PathBuf> {
+        let path = control_path(&settings()).to_path_buf();
+        fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|entry| fs::read_link(entry.ok()?.path()).ok())
+            .filter(|target| *target == path)
+            .collect()
+    }
+
+    #[test]
+    fn thread_control_reused() {
+        let before = control_fds().len();
+        enable_thread_control().expect("Error when opening the control device");
+        let fd = match open_control_device() {
+            Ok(ControlFd::Borrowed(fd)) => fd,
+            _ => panic!("The control device should be cached"),
+        };
+        assert_eq!(control_fds().len(), before + 1);
+
+        for _ in 0..3 {
+            let device_num =
+                add_device(None, Default::default()).expect("Error when creating the device");
+            delete_device(device_num).expect("Error when removing device");
+        }
+
+        // The free functions used the cached file descriptor, without opening another one
+        assert_eq!(control_fds().len(), before + 1);
+        let control = open_control_device().expect("Error when opening the control device");
+        assert_eq!(control.as_raw_fd(), fd);
+        let target = fs::read_link(format!("/proc/self/fd/{}", fd)).unwrap();
+        assert_eq!(target, control_path(&settings()));
+
+        clear_thread_control();
+        assert_eq!(control_fds().len(), before);
+        assert!(matches!(open_control_device(), Ok(ControlFd::Owned(_))));
+    }
+
+    #[test]
+    fn delete_only_owned() {
+        let mut control = Control::open().expect("Error when opening the control device");
+        let first = control
+            .add_device(None, Default::default())
+            .expect("Error when creating the device");
+        let second = control
+            .add_device(None, Default::default())
+            .expect("Error when creating the device");
+        let other = add_device(None, Default::default()).expect("Error when creating the device");
+        assert_eq!(control.owned_devices(), &[first, second]);
+
+        control.delete_owned().expect("Error when removing devices");
+        assert!(control.owned_devices().is_empty());
+        assert!(!Path::new(&format!("/dev/video{}", first)).exists());
+        assert!(!Path::new(&format!("/dev/video{}", second)).exists());
+        assert!(Path::new(&format!("/dev/video{}", other)).exists());
+
+        delete_device(other).expect("Error when removing device");
+    }
+
+    #[test]
+    fn assignment_modes() {
+        let mut control = Control::open().expect("Error when opening the control device");
+        let free = next_free_device_numbers(1).expect("Error when listing free numbers")[0];
+        let explicit = control
+            .add_device(Some(free), Default::default())
+            .expect("Error when creating the device");
+        let auto = control
+            .add_device(None, Default::default())
+            .expect("Error when creating the device");
+        let other = add_device(None, Default::default()).expect("Error when creating the device");
+
+        assert_eq!(control.assignment(explicit), Some(Assignment::Explicit));
+        assert_eq!(control.assignment(auto), Some(Assignment::Auto));
+        assert_eq!(control.assignment(other), None);
+
+        control.delete_owned().expect("Error when removing devices");
+        assert_eq!(control.assignment(auto), None);
+        delete_device(other).expect("Error when removing device");
+    }
+
+    #[test]
+    fn control_from_owned_fd() {
+        let file = File::open("/dev/v4l2loopback").expect("Error when opening the control device");
+        let mut control = Control::from_owned_fd(OwnedFd::from(file));
+        assert!(control.owned_devices().is_empty());
+
+        let device_num = control
+            .add_device(None, Default::default())
+            .expect("Error when creating the device");
+        control
+            .query_device(device_num)
+            .expect("Error when querying the device");
+
+        control.delete_owned().expect("Error when removing devices");
+    }
+
+    #[test]
+    fn cloned_control() {
+        let mut control = Control::open().expect("Error when opening the control device");
+        let device_num = control
+            .add_device(None, Default::default())
+            .expect("Error when creating the device");
+
+        let clone = control.try_clone().expect("Error when cloning the handle");
+        assert_ne!(clone.fd.as_raw_fd(), control.fd.as_raw_fd());
+        assert!(clone.owned_devices().is_empty());
+        assert_eq!(
+            clone.query_device(device_num).unwrap(),
+            control.query_device(device_num).unwrap()
+        );
+
+        // Each handle closes its own file descriptor
+        drop(clone);
+        control
+            .query_device(device_num)
+            .expect("Error when querying the device");
+
+        control.delete_owned().expect("Error when removing devices");
+    }
+
+    #[test]
+    fn adopt_external_device() {
+        let config = DeviceConfig {
+            label: "External Device".to_string(),
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+
+        let control = Control::open().expect("Error when opening the control device");
+        let device = control
+            .adopt(device_num)
+            .expect("Error when adopting the device");
+        assert_eq!(device.number(), device_num);
+        assert_eq!(device.config().label, "External Device");
+        assert!(control.owned_devices().is_empty());
+
+        drop(device);
+        assert!(!Path::new(&format!("/dev/video{}", device_num)).exists());
+    }
+
+    #[test]
+    fn adopt_persisted_devices() {
+        let registry = std::env::temp_dir().join(format!(
+            "v4l2loopback-registry-{}-adopt",
+            std::process::id()
+        ));
+        let config = DeviceConfig {
+            label: "Persisted Device".to_string(),
+            ..Default::default()
+        };
+        let device = Device::new(None, config).expect("Error when creating the device");
+        let device_num = device
+            .persist(&registry)
+            .expect("Error when persisting the device");
+        assert!(Path::new(&format!("/dev/video{}", device_num)).exists());
+
+        let control = Control::open().expect("Error when opening the control device");
+        let devices = control
+            .adopt_persisted(&registry)
+            .expect("Error when adopting the devices");
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].number(), device_num);
+        assert!(!registry.exists());
+    }
+
+    #[test]
+    fn adoption_interrupted_by_error() {
+        let registry = std::env::temp_dir().join(format!(
+            "v4l2loopback-registry-{}-interrupted",
+            std::process::id()
+        ));
+        let records = [
+            (900, "First".to_string()),
+            (901, "Gone".to_string()),
+            (902, "Broken".to_string()),
+            (903, "Last".to_string()),
+        ];
+        registry::write_all(&registry, &records).unwrap();
+
+        let mut queried = Vec::new();
+        let res = adopt_records(&registry, |device_num| {
+            queried.push(device_num);
+            match device_num {
+                900 => Ok(DeviceConfig {
+                    label: "First".to_string(),
+                    ..Default::default()
+                }),
+                901 => Err(Error::DeviceNotFound(901)),
+                _ => Err(Error::Ioctl(Errno::EPERM)),
+            }
+        });
+        assert!(matches!(res, Err(Error::Ioctl(Errno::EPERM))));
+        assert_eq!(queried, [900, 901, 902]);
+
+        // The adopted device and the unchecked ones are still recorded
+        assert_eq!(
+            registry::read_all(&registry).unwrap(),
+            [records[0].clone(), records[2].clone(), records[3].clone()]
+        );
+        registry::write_all(&registry, &[]).unwrap();
+    }
+
+    #[test]
+    fn device_limit_of_loaded_module() {
+        let control = Control::open().expect("Error when opening the control device");
+        let limit = control
+            .device_limit()
+            .expect("Error when reading the device limit");
+        assert!(limit > 0);
+        assert_eq!(control.device_limit().unwrap(), limit);
+        assert_eq!(control.try_clone().unwrap().device_limit().unwrap(), limit);
+    }
+}
@@ -0,0 +1,161 @@
+use std::os::fd::AsRawFd;
+
+use crate::{
+    sys::ioctl_readwrite, video::open_video_device, videodev2, Errno, Error, Resolution, Result,
+};
+
+ioctl_readwrite!(vidioc_cropcap, b'V', 58, videodev2::v4l2_cropcap);
+
+/// A rectangle in a frame, in pixels.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Rectangle {
+    /// Horizontal offset of the top left corner.
+    pub left: i32,
+    /// Vertical offset of the top left corner.
+    pub top: i32,
+    /// Dimensions of the rectangle.
+    pub size: Resolution,
+}
+
+impl From<videodev2::v4l2_rect> for Rectangle {
+    fn from(rect: videodev2::v4l2_rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            size: Resolution::new(rect.width, rect.height),
+        }
+    }
+}
+
+/// Shape of a pixel, as the ratio of its width to its height.
+///
+/// Square pixels are `1:1`, a pixel `2:1` is twice as wide as it is high.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct PixelAspect {
+    /// Horizontal term of the ratio.
+    pub width: u32,
+    /// Vertical term of the ratio.
+    pub height: u32,
+}
+
+impl PixelAspect {
+    /// Build the ratio `width:height`.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Width of a pixel relatively to its height.
+    pub fn ratio(&self) -> f64 {
+        f64::from(self.width) / f64::from(self.height)
+    }
+}
+
+impl From<videodev2::v4l2_fract> for PixelAspect {
+    /// V4L2 gives the pixel aspect as `y / x`, so the numerator is the vertical term.
+    fn from(fract: videodev2::v4l2_fract) -> Self {
+        Self::new(fract.denominator, fract.numerator)
+    }
+}
+
+/// Cropping capabilities of a device, as seen by consumers.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct CropCaps {
+    /// Area of the frame which can be captured.
+    pub bounds: Rectangle,
+    /// Area captured by default.
+    pub default_rect: Rectangle,
+    /// Aspect ratio of a pixel.
+    pub pixel_aspect: PixelAspect,
+}
+
+/// Fetch the cropping capabilities of a device, including its pixel aspect ratio.
+///
+/// v4l2loopback doesn't scale frames, so pixels are usually square and the bounds are the
+/// frame itself.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Unsupported`] if the v4l2loopback version doesn't report cropping capabilities.
+/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Unsupported`]: Error::Unsupported
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, crop_caps, delete_device, DeviceConfig, Error};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// match crop_caps(device_num) {
+///     Ok(caps) => println!("Pixel aspect ratio: {:?}", caps.pixel_aspect),
+///     Err(Error::Unsupported(_)) => println!("Unknown pixel aspect ratio"),
+///     Err(e) => panic!("Error when querying the crop capabilities: {}", e),
+/// }
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn crop_caps(device_num: u32) -> Result<CropCaps> {
+    let file = open_video_device(device_num, false)?;
+
+    let mut cropcap: videodev2::v4l2_cropcap = unsafe { std::mem::zeroed() };
+    cropcap.type_ = videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_CAPTURE;
+    match unsafe { vidioc_cropcap(file.as_raw_fd(), &mut cropcap) } {
+        Ok(_) => {}
+        Err(Errno::ENOTTY) => return Err(Error::Unsupported("crop capabilities")),
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(CropCaps {
+        bounds: cropcap.bounds.into(),
+        default_rect: cropcap.defrect.into(),
+        pixel_aspect: cropcap.pixelaspect.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PixelAspect;
+    use crate::{
+        crop_caps, set_format, videodev2, Device, DeviceConfig, Error, Format, PixelFormat,
+        Resolution,
+    };
+
+    #[test]
+    fn pixel_aspect_from_v4l2() {
+        // Pixels of PAL frames sampled following BT.601, wider than high
+        let aspect = PixelAspect::from(videodev2::v4l2_fract {
+            numerator: 11,
+            denominator: 12,
+        });
+        assert_eq!(aspect, PixelAspect::new(12, 11));
+        assert!(aspect.ratio() > 1.0);
+    }
+
+    #[test]
+    fn crop_caps_of_created_device() {
+        let config = DeviceConfig {
+            announce_all_caps: true,
+            ..Default::default()
+        };
+        let device = Device::new(None, config).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(640, 480));
+        set_format(device.number().as_u32(), &format).expect("Error when setting the format");
+
+        match crop_caps(device.number().as_u32()) {
+            Ok(caps) => {
+                assert_eq!(caps.pixel_aspect, PixelAspect::new(1, 1));
+                assert_eq!(caps.bounds.size, format.resolution);
+                assert_eq!(caps.default_rect, caps.bounds);
+            }
+            Err(Error::Unsupported(_)) => {}
+            Err(e) => panic!("Error when querying the crop capabilities: {}", e),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::{self, Write};
+
+/// Skips writing frames identical to the previous one to a sink.
+///
+/// Producers of mostly static content, like a shared document, can write every frame through
+/// a `DedupWriter` to save the cost of passing the same picture again. Each frame is compared
+/// with the last written one, and is skipped when at most `threshold` bytes differ, so a
+/// threshold of 0 only skips exact duplicates. Since skipped frames are not compared with
+/// each other, slow changes still end up written once they add up past the threshold.
+///
+/// Consumers receive no frame while frames are skipped, which some of them report as a
+/// stalled source.
+///
+/// # Example
+///
+/// ```
+/// use std::fs::OpenOptions;
+/// use v4l2loopback::{add_device, delete_device, DedupWriter, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let output = OpenOptions::new()
+///     .write(true)
+///     .open(format!("/dev/video{}", device_num))
+///     .unwrap();
+///
+/// let mut writer = DedupWriter::new(output, 0);
+/// let frame = vec![0; 640 * 480 * 2];
+/// for _ in 0..10 {
+///     writer.write_frame(&frame).expect("Error when writing the frame");
+/// }
+/// assert_eq!(writer.skipped_frames(), 9);
+///
+/// drop(writer);
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+#[derive(Debug)]
+pub struct DedupWriter<W> {
+    sink: W,
+    threshold: usize,
+    last: Option<Vec<u8>>,
+    skipped: u64,
+}
+
+impl<W: Write> DedupWriter<W> {
+    /// Wrap `sink`, skipping frames with at most `threshold` bytes differing from the last
+    /// written frame.
+    pub fn new(sink: W, threshold: usize) -> Self {
+        Self {
+            sink,
+            threshold,
+            last: None,
+            skipped: 0,
+        }
+    }
+
+    /// Whether `frame` is close enough to the last written frame to be skipped.
+    fn is_duplicate(&self, frame: &[u8]) -> bool {
+        let Some(last) = &self.last else {
+            return false;
+        };
+        if last.len() != frame.len() {
+            return false;
+        }
+
+        let mut differing = last.iter().zip(frame).filter(|(a, b)| a != b);
+        differing.nth(self.threshold).is_none()
+    }
+
+    /// Write a whole frame to the sink, unless it is a duplicate of the last written frame.
+    ///
+    /// Returns whether the frame was written.
+    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<bool> {
+        if self.is_duplicate(frame) {
+            self.skipped += 1;
+            return Ok(false);
+        }
+
+        self.sink.write_all(frame)?;
+        match &mut self.last {
+            Some(last) => {
+                last.clear();
+                last.extend_from_slice(frame);
+            }
+            None => self.last = Some(frame.to_vec()),
+        }
+
+        Ok(true)
+    }
+
+    /// Number of frames skipped so far.
+    pub fn skipped_frames(&self) -> u64 {
+        self.skipped
+    }
+
+    /// The wrapped sink.
+    pub fn get_ref(&self) -> &W {
+        &self.sink
+    }
+
+    /// Release the wrapped sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DedupWriter;
+
+    #[test]
+    fn duplicates_skipped() {
+        let mut writer = DedupWriter::new(Vec::new(), 0);
+        assert!(writer.write_frame(&[1, 2, 3]).unwrap());
+        assert!(!writer.write_frame(&[1, 2, 3]).unwrap());
+        assert!(writer.write_frame(&[1, 2, 4]).unwrap());
+        assert!(!writer.write_frame(&[1, 2, 4]).unwrap());
+        // A different size is always a change
+        assert!(writer.write_frame(&[1, 2]).unwrap());
+
+        assert_eq!(writer.skipped_frames(), 2);
+        assert_eq!(writer.into_inner(), vec![1, 2, 3, 1, 2, 4, 1, 2]);
+    }
+
+    #[test]
+    fn changes_under_threshold() {
+        let mut writer = DedupWriter::new(Vec::new(), 1);
+        assert!(writer.write_frame(&[0, 0, 0, 0]).unwrap());
+        assert!(!writer.write_frame(&[1, 0, 0, 0]).unwrap());
+        // Compared with the last written frame, not the skipped one
+        assert!(writer.write_frame(&[1, 1, 0, 0]).unwrap());
+
+        assert_eq!(writer.skipped_frames(), 1);
+        assert_eq!(writer.get_ref(), &[0, 0, 0, 0, 1, 1, 0, 0]);
+    }
+}
@@ -0,0 +1,848 @@
+use std::{
+    fmt,
+    io::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    add_device, delete_device, device_status, get_format, get_frame_interval, leaks, query_device,
+    registry, set_format, set_frame_interval, spec,
+    user_controls::set_keep_format,
+    video::{DeviceFile, Direction, ExclusiveOutput},
+    DeviceConfig, Error, Format, Result,
+};
+
+/// Longest label v4l2loopback keeps, in bytes, without the final null byte.
+pub(crate) const LABEL_MAX_LEN: usize = 31;
+
+/// Number of a v4l2loopback device, `N` in `/dev/videoN`.
+///
+/// It converts from and into a `u32`, and is displayed as the bare number, so it can be passed
+/// to the functions of this crate taking a device number with [`DeviceNumber::as_u32`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{query_device, Device, DeviceConfig};
+///
+/// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let device_num: u32 = device.number().into();
+///
+/// let cfg = query_device(device.number().as_u32()).expect("Error when querying the device");
+/// assert_eq!(format!("/dev/video{}", device.number()), format!("/dev/video{}", device_num));
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct DeviceNumber(u32);
+
+impl DeviceNumber {
+    /// The number as a `u32`.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for DeviceNumber {
+    fn from(num: u32) -> Self {
+        Self(num)
+    }
+}
+
+impl From<DeviceNumber> for u32 {
+    fn from(num: DeviceNumber) -> Self {
+        num.0
+    }
+}
+
+impl PartialEq<u32> for DeviceNumber {
+    fn eq(&self, other: &u32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<DeviceNumber> for u32 {
+    fn eq(&self, other: &DeviceNumber) -> bool {
+        *self == other.0
+    }
+}
+
+impl fmt::Display for DeviceNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Handle to a v4l2loopback device.
+///
+/// The device is created with [`Device::new`] and is deleted when the handle is dropped.
+/// If you want the device to outlive the handle, use [`Device::leak`] or [`Device::persist`].
+///
+/// A handle is the only owner of its device, so it can't be cloned. To share a device, use a
+/// [`SharedDevice`].
+///
+/// In debug builds, a warning is printed when the program exits if some handles were never
+/// dropped (for example with [`std::mem::forget`]), since their devices are not deleted.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use v4l2loopback::{Device, DeviceConfig};
+///
+/// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let path = format!("/dev/video{}", device.number());
+/// assert!(Path::new(&path).exists());
+///
+/// // The device is removed along with its handle
+/// drop(device);
+/// assert!(!Path::new(&path).exists());
+/// ```
+#[derive(Debug)]
+pub struct Device {
+    num: u32,
+    config: DeviceConfig,
+    delete_on_drop: bool,
+}
+
+impl Device {
+    /// Create a new v4l2loopback device.
+    ///
+    /// See [`add_device`] for the meaning of the arguments and the possible errors.
+    pub fn new(num: Option<u32>, config: DeviceConfig) -> Result<Self> {
+        let num = add_device(num, config.clone())?;
+        leaks::track(num);
+
+        Ok(Self {
+            num,
+            config,
+            delete_on_drop: true,
+        })
+    }
+
+    /// Create a new v4l2loopback device showing a solid color until a producer writes to it.
+    ///
+    /// The device gets the first free number, then `format` is set on it (see [`set_format`])
+    /// and a frame of the `(r, g, b)` color is written, converted to the pixel format
+    /// negotiated with the device. Consumers connecting before the actual producer starts
+    /// see this frame instead of a black or garbage frame.
+    ///
+    /// The `keep_format` control of the device is enabled so the frame stays available once
+    /// this function returns, which also keeps the format when later producers close the device.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`Unsupported`] if the negotiated pixel format isn't known by this crate, or if the
+    ///   device has no `keep_format` control (before v4l2loopback 0.10.0).
+    /// - [`Other`] if the frame couldn't be written.
+    /// - Any error from [`add_device`] or [`set_format`].
+    ///
+    /// The device is deleted if anything fails after creating it.
+    ///
+    /// [`Unsupported`]: Error::Unsupported
+    /// [`Other`]: Error::Other
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Device, DeviceConfig, Format, PixelFormat, Resolution};
+    ///
+    /// let format = Format::new(PixelFormat::Yuyv, Resolution::new(1280, 720));
+    /// let device = Device::create_with_fill(DeviceConfig::default(), &format, (0, 0, 255))
+    ///     .expect("Error when creating the device");
+    /// ```
+    pub fn create_with_fill(
+        config: DeviceConfig,
+        format: &Format,
+        color: (u8, u8, u8),
+    ) -> Result<Self> {
+        let device = Self::new(None, config)?;
+        let format = set_format(device.num, format)?;
+        let Some(frame) = format.solid_frame(color) else {
+            return Err(Error::Unsupported("filling frames in this pixel format"));
+        };
+
+        let mut output = device.open_output()?;
+        // Without it, the frame is dropped as soon as the output is closed
+        set_keep_format(output.file(), true)?;
+        if let Err(e) = output.write_all(&frame) {
+            return Err(Error::Other(Box::new(e)));
+        }
+
+        Ok(device)
+    }
+
+    /// Create a new v4l2loopback device from a spec string.
+    ///
+    /// The spec is an optional device number followed by `:`, then the configuration using
+    /// the grammar of [`DeviceConfig::try_from`], for example `10:label=Camera,max=1920x1080`.
+    /// Without a device number, the first free one is used.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`InvalidSpec`] if the spec is malformed, or any error from
+    /// [`add_device`].
+    ///
+    /// [`InvalidSpec`]: Error::InvalidSpec
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::Device;
+    ///
+    /// let device = Device::from_spec("label=Camera,buffers=4").expect("Error when creating the device");
+    /// assert_eq!(device.config().label, "Camera");
+    /// ```
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        let (num, config) = spec::parse_device(spec)?;
+        Self::new(num, config)
+    }
+
+    /// Wrap an existing device in a handle, which will delete it when dropped.
+    pub(crate) fn adopted(num: u32, config: DeviceConfig) -> Self {
+        leaks::track(num);
+
+        Self {
+            num,
+            config,
+            delete_on_drop: true,
+        }
+    }
+
+    /// The device number, `N` in `/dev/videoN`.
+    pub fn number(&self) -> DeviceNumber {
+        DeviceNumber(self.num)
+    }
+
+    /// Path of the device node, `/dev/videoN`.
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(format!("/dev/video{}", self.num))
+    }
+
+    /// Open the device for writing frames, as a producer.
+    ///
+    /// The returned [`DeviceFile`] keeps the device number, and is marked as an
+    /// [`Output`](Direction::Output).
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+    /// - [`Other`] if the device couldn't be opened.
+    ///
+    /// [`DeviceNotFound`]: Error::DeviceNotFound
+    /// [`Other`]: Error::Other
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Device, DeviceConfig};
+    ///
+    /// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    ///
+    /// let output = device.open_output().expect("Error when opening the output");
+    /// let capture = device.open_capture().expect("Error when opening the capture");
+    /// assert_eq!(output.number(), device.number());
+    /// ```
+    pub fn open_output(&self) -> Result<DeviceFile> {
+        DeviceFile::open(self.num, Direction::Output)
+    }
+
+    /// Open the device for writing frames, as the only producer.
+    ///
+    /// The returned [`ExclusiveOutput`] holds an exclusive lock on the device node until it is
+    /// dropped, so a second exclusive open fails, from this process or any other.
+    ///
+    /// This is best effort: `O_EXCL` has no meaning for character devices, so the lock is
+    /// advisory. It only keeps away producers which also open the device with this method
+    /// (or take a `flock` on it), other producers can still write frames.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`DeviceBusy`] if another exclusive producer has the device
+    /// opened, and the errors of [`Device::open_output`] otherwise.
+    ///
+    /// [`DeviceBusy`]: Error::DeviceBusy
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Device, DeviceConfig, Error};
+    ///
+    /// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    ///
+    /// let output = device
+    ///     .open_output_exclusive()
+    ///     .expect("Error when opening the output");
+    /// assert!(matches!(
+    ///     device.open_output_exclusive(),
+    ///     Err(Error::DeviceBusy(_))
+    /// ));
+    /// ```
+    pub fn open_output_exclusive(&self) -> Result<ExclusiveOutput> {
+        ExclusiveOutput::open(self.num)
+    }
+
+    /// Open the device for reading frames, as a consumer.
+    ///
+    /// # Errors
+    ///
+    /// See [`Device::open_output`].
+    pub fn open_capture(&self) -> Result<DeviceFile> {
+        DeviceFile::open(self.num, Direction::Capture)
+    }
+
+    /// Open both sides of the device, as `(output, capture)`, for a process producing and
+    /// consuming its own frames.
+    ///
+    /// The output side is opened first, since a device in exclusive caps mode (see
+    /// [`DeviceConfig::announce_all_caps`]) only accepts consumers once it has a producer.
+    /// Frames can only be read once a frame was written to the output, and a read blocks
+    /// until then, so write before reading from the same thread.
+    ///
+    /// # Errors
+    ///
+    /// See [`Device::open_output`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use v4l2loopback::{set_format, Device, DeviceConfig, Format, PixelFormat, Resolution};
+    ///
+    /// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    /// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+    /// set_format(device.number().as_u32(), &format).expect("Error when setting the format");
+    ///
+    /// let (mut output, mut capture) = device.open_both().expect("Error when opening the device");
+    /// let mut frame = vec![128; format.image_size().unwrap()];
+    /// output.write_all(&frame).expect("Error when writing the frame");
+    /// capture.read_exact(&mut frame).expect("Error when reading the frame");
+    /// ```
+    pub fn open_both(&self) -> Result<(DeviceFile, DeviceFile)> {
+        let output = self.open_output()?;
+        let capture = self.open_capture()?;
+
+        Ok((output, capture))
+    }
+
+    /// The configuration used to create the device.
+    pub fn config(&self) -> &DeviceConfig {
+        &self.config
+    }
+
+    /// Release the handle without deleting the device, returning the device number.
+    ///
+    /// The device will have to be removed manually with [`delete_device`].
+    pub fn leak(mut self) -> u32 {
+        self.delete_on_drop = false;
+        self.num
+    }
+
+    /// Release the handle without deleting the device, and record it in the `registry` file.
+    ///
+    /// This lets a restarted process get the device back with [`Control::adopt_persisted`]
+    /// instead of creating it again, which would disconnect its consumers.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Other`] if the registry couldn't be written. The device is
+    /// kept in that case, and is deleted along with the handle.
+    ///
+    /// [`Control::adopt_persisted`]: crate::Control::adopt_persisted
+    /// [`Other`]: Error::Other
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Control, Device, DeviceConfig};
+    ///
+    /// let registry = std::env::temp_dir().join("v4l2loopback-devices");
+    /// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    /// let device_num = device.persist(&registry).expect("Error when persisting the device");
+    ///
+    /// // After a restart
+    /// let control = Control::open().expect("Error when opening the control device");
+    /// let devices = control
+    ///     .adopt_persisted(&registry)
+    ///     .expect("Error when adopting the devices");
+    /// assert_eq!(devices[0].number(), device_num);
+    /// ```
+    pub fn persist(self, registry: impl AsRef<Path>) -> Result<u32> {
+        registry::record(registry.as_ref(), self.num, &self.config.label)?;
+        Ok(self.leak())
+    }
+
+    /// Delete the device, reporting any error that happens.
+    ///
+    /// Dropping the handle also deletes the device, but errors are ignored.
+    pub fn delete(mut self) -> Result<()> {
+        self.delete_on_drop = false;
+        delete_device(self.num)
+    }
+
+    /// Replace the device with a new one, using the same number and the given `config`.
+    ///
+    /// v4l2loopback can't change the configuration of an existing device, so it is deleted then
+    /// created again, which disconnects every application using it.
+    /// To avoid accidentally cutting a stream, this fails if the device has consumers connected
+    /// to it (see [`device_status`]), unless `force` is `true`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`DeviceBusy`] if the device is opened by someone and `force` is `false`.
+    /// - Any error from [`device_status`], [`delete_device`] or [`add_device`].
+    ///
+    /// If the device was deleted but couldn't be created again, the handle doesn't own a device
+    /// anymore and won't try to delete it when dropped.
+    ///
+    /// [`DeviceBusy`]: Error::DeviceBusy
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{query_device, Device, DeviceConfig};
+    ///
+    /// let mut device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    ///
+    /// let config = DeviceConfig {
+    ///     label: "Reconfigured".to_string(),
+    ///     ..DeviceConfig::default()
+    /// };
+    /// device.recreate_with(config, false).expect("Error when recreating the device");
+    ///
+    /// let cfg = query_device(device.number().as_u32()).expect("Error when querying the device");
+    /// assert_eq!(cfg.label, "Reconfigured");
+    /// ```
+    pub fn recreate_with(&mut self, config: DeviceConfig, force: bool) -> Result<()> {
+        if !force && device_status(self.num)?.openers > 0 {
+            return Err(Error::DeviceBusy(self.num));
+        }
+
+        delete_device(self.num)?;
+        if let Err(e) = add_device(Some(self.num), config.clone()) {
+            self.delete_on_drop = false;
+            return Err(e);
+        }
+        self.config = config;
+
+        Ok(())
+    }
+
+    /// Change the label of the device, keeping the rest of its configuration.
+    ///
+    /// v4l2loopback can't rename an existing device, so it is recreated with the new label
+    /// (see [`Device::recreate_with`]), unless the label doesn't change. The handle stays
+    /// valid and keeps the same device number.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`ConfigConversionError`] if `label` contains null bytes.
+    /// - [`LabelTooLong`] if `label` is longer than the 31 bytes v4l2loopback keeps.
+    /// - [`DeviceBusy`] if the device is opened by someone.
+    /// - Any error from [`Device::recreate_with`].
+    ///
+    /// [`ConfigConversionError`]: Error::ConfigConversionError
+    /// [`LabelTooLong`]: Error::LabelTooLong
+    /// [`DeviceBusy`]: Error::DeviceBusy
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Device, DeviceConfig};
+    ///
+    /// let mut device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    /// device.set_label("Renamed").expect("Error when renaming the device");
+    /// assert_eq!(device.config().label, "Renamed");
+    /// ```
+    pub fn set_label(&mut self, label: &str) -> Result<()> {
+        if label.contains('\0') {
+            return Err(Error::ConfigConversionError(
+                "The label contains null bytes".into(),
+            ));
+        }
+        if label.len() > LABEL_MAX_LEN {
+            return Err(Error::LabelTooLong(label.len()));
+        }
+        if label == self.config.label {
+            return Ok(());
+        }
+
+        let config = DeviceConfig {
+            label: label.to_string(),
+            ..self.config.clone()
+        };
+        self.recreate_with(config, false)
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        leaks::untrack(self.num);
+        if self.delete_on_drop {
+            let _ = delete_device(self.num);
+        }
+    }
+}
+
+/// Handle to a v4l2loopback device, which can be cloned to share the device.
+///
+/// Every clone refers to the same device, which is deleted once the last clone is dropped.
+/// Methods of [`Device`] which don't need exclusive ownership are available through [`Deref`].
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use v4l2loopback::{DeviceConfig, SharedDevice};
+///
+/// let device = SharedDevice::new(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let path = format!("/dev/video{}", device.number());
+///
+/// let clone = device.clone();
+/// drop(device);
+/// assert!(Path::new(&path).exists());
+///
+/// // The last handle removes the device
+/// drop(clone);
+/// assert!(!Path::new(&path).exists());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedDevice(Arc<Device>);
+
+impl SharedDevice {
+    /// Create a new v4l2loopback device.
+    ///
+    /// See [`add_device`] for the meaning of the arguments and the possible errors.
+    pub fn new(num: Option<u32>, config: DeviceConfig) -> Result<Self> {
+        Ok(Self::from(Device::new(num, config)?))
+    }
+
+    /// Get back the exclusive handle, if this is the last clone.
+    ///
+    /// Otherwise, the shared handle is returned as is.
+    pub fn try_unwrap(self) -> Result<Device, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl From<Device> for SharedDevice {
+    fn from(device: Device) -> Self {
+        Self(Arc::new(device))
+    }
+}
+
+impl Deref for SharedDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        &self.0
+    }
+}
+
+/// Delete a device and create it again with the same number, configuration, format and frame
+/// interval.
+///
+/// This is useful to reset a device which ended up in a bad state, without changing how it
+/// appears to applications. Like [`Device::recreate_with`], this disconnects every application
+/// using the device.
+///
+/// The format and frame interval are only restored if they could be read before deleting the
+/// device. The new device is returned in a [`Device`] handle. If they can't be set on the new
+/// device, it is kept without a handle, like the original device, and has to be removed with
+/// [`delete_device`].
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`NumberTaken`] if another device was created with the same number while restarting.
+/// - Any error from [`query_device`], [`delete_device`], [`add_device`], [`set_format`] or
+///   [`set_frame_interval`].
+///
+/// [`NumberTaken`]: Error::NumberTaken
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, restart_device, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let device = restart_device(device_num).expect("Error when restarting the device");
+/// assert_eq!(device.number(), device_num);
+/// ```
+pub fn restart_device(device_num: u32) -> Result<Device> {
+    let config = query_device(device_num)?;
+    let format = get_format(device_num).ok();
+    let interval = get_frame_interval(device_num).ok();
+
+    delete_device(device_num)?;
+    let device = match Device::new(Some(device_num), config) {
+        Ok(device) => device,
+        Err(e) => match query_device(device_num) {
+            Ok(_) => return Err(Error::NumberTaken(device_num)),
+            Err(_) => return Err(e),
+        },
+    };
+
+    restore_settings(device, |device_num| {
+        if let Some(format) = format {
+            set_format(device_num, &format)?;
+        }
+        if let Some(interval) = interval {
+            set_frame_interval(device_num, interval)?;
+        }
+        Ok(())
+    })
+}
+
+/// Apply `restore` to a restarted device, releasing the handle if it fails so the device
+/// isn't deleted.
+fn restore_settings(device: Device, restore: impl FnOnce(u32) -> Result<()>) -> Result<Device> {
+    match restore(device.number().as_u32()) {
+        Ok(()) => Ok(device),
+        Err(e) => {
+            device.leak();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        io::{Read, Write},
+        os::fd::AsRawFd,
+        path::Path,
+    };
+
+    use crate::{
+        add_device, delete_device, device_status, get_format, query_device, restart_device,
+        set_format, Device, DeviceConfig, DeviceNumber, Direction, Errno, Error, Format,
+        PixelFormat, Resolution, SharedDevice,
+    };
+
+    use super::restore_settings;
+
+    #[test]
+    fn typed_device_number() {
+        let num = DeviceNumber::from(7);
+        assert_eq!(num.as_u32(), 7);
+        assert_eq!(u32::from(num), 7);
+        assert_eq!(num, 7);
+        assert_eq!(num.to_string(), "7");
+
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        let device_num: u32 = device.number().into();
+        assert_eq!(device.number().as_u32(), device_num);
+        assert_eq!(
+            device.path(),
+            Path::new(&format!("/dev/video{}", device_num))
+        );
+    }
+
+    #[test]
+    fn restore_failure_keeps_device() {
+        let config = DeviceConfig::default();
+        let device_num = add_device(None, config.clone()).expect("Error when creating the device");
+
+        let res = restore_settings(Device::adopted(device_num, config), |_| {
+            Err(Error::Ioctl(Errno::EINVAL))
+        });
+        assert!(matches!(res, Err(Error::Ioctl(Errno::EINVAL))));
+        query_device(device_num).expect("The device was deleted");
+
+        delete_device(device_num).expect("Error when deleting the device");
+    }
+
+    #[test]
+    fn recreate_busy_device() {
+        let mut device =
+            Device::new(None, Default::default()).expect("Error when creating the device");
+        let config = DeviceConfig {
+            label: "Recreated".to_string(),
+            ..Default::default()
+        };
+
+        let consumer = File::open(format!("/dev/video{}", device.number())).unwrap();
+        let res = device.recreate_with(config.clone(), false);
+        assert!(matches!(res, Err(Error::DeviceBusy(n)) if n == device.number()));
+        drop(consumer);
+
+        device
+            .recreate_with(config.clone(), false)
+            .expect("Error when recreating the device");
+        let cfg = query_device(device.number().as_u32()).expect("Error when querying the device");
+        assert_eq!(cfg.label, config.label);
+    }
+
+    #[test]
+    fn open_both_sides() {
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        assert_eq!(
+            device.path(),
+            Path::new(&format!("/dev/video{}", device.number()))
+        );
+
+        let output = device.open_output().expect("Error when opening the output");
+        let capture = device
+            .open_capture()
+            .expect("Error when opening the capture");
+        assert_eq!(output.direction(), Direction::Output);
+        assert_eq!(capture.direction(), Direction::Capture);
+        for file in [&output, &capture] {
+            assert_eq!(file.number(), device.number());
+            let target = fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+            assert_eq!(target, device.path());
+        }
+        assert_eq!(
+            device_status(device.number().as_u32())
+                .expect("Error when fetching the status")
+                .openers,
+            2
+        );
+    }
+
+    #[test]
+    fn both_sides_round_trip() {
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device.number().as_u32(), &format).expect("Error when setting the format");
+
+        let (mut output, mut capture) = device.open_both().expect("Error when opening the device");
+        let frame: Vec<u8> = (0..64 * 48).map(|i| (i % 251) as u8).collect();
+        output
+            .write_all(&frame)
+            .expect("Error when writing the frame");
+        let mut read = vec![0; frame.len()];
+        capture
+            .read_exact(&mut read)
+            .expect("Error when reading the frame");
+        assert_eq!(read, frame);
+    }
+
+    #[test]
+    fn exclusive_output() {
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+
+        let output = device
+            .open_output_exclusive()
+            .expect("Error when opening the output");
+        assert_eq!(output.direction(), Direction::Output);
+        assert!(matches!(
+            device.open_output_exclusive(),
+            Err(Error::DeviceBusy(n)) if n == device.number()
+        ));
+        // Other openers are not affected
+        device
+            .open_capture()
+            .expect("Error when opening the capture");
+
+        drop(output);
+        device
+            .open_output_exclusive()
+            .expect("Error when opening the output");
+    }
+
+    #[test]
+    fn shared_device_deleted_once() {
+        let device =
+            SharedDevice::new(None, Default::default()).expect("Error when creating the device");
+        let path = format!("/dev/video{}", device.number());
+
+        let clone = device.clone();
+        assert_eq!(clone.number(), device.number());
+
+        drop(device);
+        assert!(Path::new(&path).exists());
+        drop(clone);
+        assert!(!Path::new(&path).exists());
+    }
+
+    #[test]
+    fn restart_keeps_format() {
+        let config = DeviceConfig {
+            label: "Restarted".to_string(),
+            ..Default::default()
+        };
+        let device_num = add_device(None, config.clone()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(320, 240));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let device = restart_device(device_num).expect("Error when restarting the device");
+        assert_eq!(device.number(), device_num);
+        let cfg = query_device(device_num).expect("Error when querying the device");
+        assert_eq!(cfg.label, config.label);
+        assert_eq!(
+            get_format(device_num).expect("Error when getting the format"),
+            format
+        );
+    }
+
+    #[test]
+    fn filled_first_frame() {
+        let format = Format::new(PixelFormat::Rgb24, Resolution::new(64, 48));
+        let device = Device::create_with_fill(DeviceConfig::default(), &format, (255, 128, 0))
+            .expect("Error when creating the device");
+
+        assert_eq!(
+            get_format(device.number().as_u32()).expect("Error when getting the format"),
+            format
+        );
+
+        // The output used to write the frame is closed, the frame has to outlive it
+        let mut frame = vec![0; format.image_size().unwrap()];
+        File::open(format!("/dev/video{}", device.number()))
+            .expect("Error when opening the capture")
+            .read_exact(&mut frame)
+            .expect("Error when reading the frame");
+        assert_eq!(frame, [255, 128, 0].repeat(64 * 48));
+    }
+
+    #[test]
+    fn black_yuyv_frame() {
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(64, 48));
+        set_format(device.number().as_u32(), &format).expect("Error when setting the format");
+
+        let (output, mut capture) = device.open_both().expect("Error when opening the device");
+        assert!(matches!(
+            capture.write_black_frame(),
+            Err(Error::Unsupported(_))
+        ));
+        output
+            .write_black_frame()
+            .expect("Error when writing the frame");
+
+        // The output is still open, so the frame is kept by the device
+        let mut frame = vec![0; format.image_size().unwrap()];
+        capture
+            .read_exact(&mut frame)
+            .expect("Error when reading the frame");
+        assert_eq!(frame, [16, 128, 16, 128].repeat(32 * 48));
+        drop(output);
+    }
+
+    #[test]
+    fn rename_through_handle() {
+        let mut device =
+            Device::new(None, Default::default()).expect("Error when creating the device");
+        let device_num = device.number().as_u32();
+
+        assert!(matches!(
+            device.set_label(&"a".repeat(32)),
+            Err(Error::LabelTooLong(32))
+        ));
+        device
+            .set_label("Renamed Device")
+            .expect("Error when renaming the device");
+        assert_eq!(device.number(), device_num);
+        assert_eq!(device.config().label, "Renamed Device");
+        let cfg = query_device(device_num).expect("Error when querying the device");
+        assert_eq!(cfg.label, "Renamed Device");
+    }
+}
@@ -0,0 +1,1048 @@
+use std::{fmt, time::Duration};
+
+use crate::{videodev2, DeviceConfig, Error};
+
+/// Smallest frame width v4l2loopback accepts.
+const MIN_WIDTH: u32 = 48;
+/// Smallest frame height v4l2loopback accepts.
+const MIN_HEIGHT: u32 = 32;
+/// Largest frame width and height v4l2loopback accepts.
+pub(crate) const MAX_DIMENSION: u32 = 8192;
+
+/// Build a FourCC code the same way as the `v4l2_fourcc` macro from videodev2.h.
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    (code[0] as u32) | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+/// Pixel format of the frames passed through a device.
+///
+/// Formats are identified by their FourCC code. Formats this crate doesn't know about are
+/// kept as [`PixelFormat::Unknown`].
+///
+/// Formats are displayed, and serialized with the `serde` feature, as their FourCC code, like
+/// `YUYV`. Codes which aren't printable are written in hexadecimal instead, like `0x00000001`.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum PixelFormat {
+    /// Packed YUV 4:2:2, `Y0 U Y1 V` (`YUYV`).
+    Yuyv,
+    /// Packed YUV 4:2:2, `U Y0 V Y1` (`UYVY`).
+    Uyvy,
+    /// Packed YUV 4:2:2, `Y0 V Y1 U` (`YVYU`).
+    Yvyu,
+    /// Planar YUV 4:2:0, a Y plane followed by an interleaved UV plane (`NV12`).
+    Nv12,
+    /// Planar YUV 4:2:0, a Y plane followed by an interleaved VU plane (`NV21`).
+    Nv21,
+    /// Planar YUV 4:2:0, with Y, U then V planes (`YU12`, also known as I420).
+    Yuv420,
+    /// Planar YUV 4:2:0, with Y, V then U planes (`YV12`).
+    Yvu420,
+    /// Packed 24 bits RGB (`RGB3`).
+    Rgb24,
+    /// Packed 24 bits BGR (`BGR3`).
+    Bgr24,
+    /// Packed 32 bits RGB, with an unused alpha byte (`RGB4`).
+    Rgb32,
+    /// Packed 32 bits BGR, with an unused alpha byte (`BGR4`).
+    Bgr32,
+    /// 8 bits greyscale (`GREY`).
+    Grey,
+    /// A format not known by this crate, with its FourCC code.
+    Unknown(u32),
+}
+
+impl PixelFormat {
+    /// The FourCC code identifying this format in v4l2.
+    pub const fn fourcc(&self) -> u32 {
+        match self {
+            PixelFormat::Yuyv => fourcc(b"YUYV"),
+            PixelFormat::Uyvy => fourcc(b"UYVY"),
+            PixelFormat::Yvyu => fourcc(b"YVYU"),
+            PixelFormat::Nv12 => fourcc(b"NV12"),
+            PixelFormat::Nv21 => fourcc(b"NV21"),
+            PixelFormat::Yuv420 => fourcc(b"YU12"),
+            PixelFormat::Yvu420 => fourcc(b"YV12"),
+            PixelFormat::Rgb24 => fourcc(b"RGB3"),
+            PixelFormat::Bgr24 => fourcc(b"BGR3"),
+            PixelFormat::Rgb32 => fourcc(b"RGB4"),
+            PixelFormat::Bgr32 => fourcc(b"BGR4"),
+            PixelFormat::Grey => fourcc(b"GREY"),
+            PixelFormat::Unknown(code) => *code,
+        }
+    }
+
+    /// Size in bytes of a frame of `width`x`height` pixels in this format.
+    ///
+    /// This is the value expected in `sizeimage`, summing the size of every plane:
+    /// - Packed YUV 4:2:2 ([`Yuyv`], [`Uyvy`], [`Yvyu`]): 4 bytes for every 2 pixels of a line,
+    ///   so `2 * width * height` for an even width.
+    /// - Planar YUV 4:2:0 ([`Nv12`], [`Nv21`], [`Yuv420`], [`Yvu420`]): a full resolution Y
+    ///   plane, plus two chroma samples for every 2x2 pixels block, so `width * height * 3 / 2`
+    ///   for even dimensions.
+    /// - [`Rgb24`] and [`Bgr24`]: `3 * width * height`.
+    /// - [`Rgb32`] and [`Bgr32`]: `4 * width * height`.
+    /// - [`Grey`]: `width * height`.
+    ///
+    /// Odd dimensions are rounded up for subsampled chroma, the same way v4l2 does.
+    ///
+    /// Returns [`None`] for [`Unknown`] formats.
+    ///
+    /// [`Yuyv`]: PixelFormat::Yuyv
+    /// [`Uyvy`]: PixelFormat::Uyvy
+    /// [`Yvyu`]: PixelFormat::Yvyu
+    /// [`Nv12`]: PixelFormat::Nv12
+    /// [`Nv21`]: PixelFormat::Nv21
+    /// [`Yuv420`]: PixelFormat::Yuv420
+    /// [`Yvu420`]: PixelFormat::Yvu420
+    /// [`Rgb24`]: PixelFormat::Rgb24
+    /// [`Bgr24`]: PixelFormat::Bgr24
+    /// [`Rgb32`]: PixelFormat::Rgb32
+    /// [`Bgr32`]: PixelFormat::Bgr32
+    /// [`Grey`]: PixelFormat::Grey
+    /// [`Unknown`]: PixelFormat::Unknown
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::PixelFormat;
+    ///
+    /// assert_eq!(PixelFormat::Yuyv.image_size(1280, 720), Some(1280 * 720 * 2));
+    /// assert_eq!(PixelFormat::Nv12.image_size(1280, 720), Some(1280 * 720 * 3 / 2));
+    /// ```
+    pub fn image_size(&self, width: u32, height: u32) -> Option<usize> {
+        let width = width as usize;
+        let height = height as usize;
+        let luma = width * height;
+        let chroma_width = width.div_ceil(2);
+        let chroma_height = height.div_ceil(2);
+
+        let size = match self {
+            PixelFormat::Yuyv | PixelFormat::Uyvy | PixelFormat::Yvyu => chroma_width * 4 * height,
+            PixelFormat::Nv12 | PixelFormat::Nv21 => luma + chroma_width * 2 * chroma_height,
+            PixelFormat::Yuv420 | PixelFormat::Yvu420 => luma + 2 * chroma_width * chroma_height,
+            PixelFormat::Rgb24 | PixelFormat::Bgr24 => luma * 3,
+            PixelFormat::Rgb32 | PixelFormat::Bgr32 => luma * 4,
+            PixelFormat::Grey => luma,
+            PixelFormat::Unknown(_) => return None,
+        };
+
+        Some(size)
+    }
+}
+
+impl From<u32> for PixelFormat {
+    fn from(code: u32) -> Self {
+        [
+            PixelFormat::Yuyv,
+            PixelFormat::Uyvy,
+            PixelFormat::Yvyu,
+            PixelFormat::Nv12,
+            PixelFormat::Nv21,
+            PixelFormat::Yuv420,
+            PixelFormat::Yvu420,
+            PixelFormat::Rgb24,
+            PixelFormat::Bgr24,
+            PixelFormat::Rgb32,
+            PixelFormat::Bgr32,
+            PixelFormat::Grey,
+        ]
+        .into_iter()
+        .find(|format| format.fourcc() == code)
+        .unwrap_or(PixelFormat::Unknown(code))
+    }
+}
+
+impl From<PixelFormat> for u32 {
+    fn from(format: PixelFormat) -> Self {
+        format.fourcc()
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = self.fourcc();
+        let bytes = code.to_le_bytes();
+        if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            // Only ASCII, so every byte is a char
+            bytes.iter().try_for_each(|b| write!(f, "{}", *b as char))
+        } else {
+            write!(f, "{:#010x}", code)
+        }
+    }
+}
+
+impl From<PixelFormat> for String {
+    fn from(format: PixelFormat) -> Self {
+        format.to_string()
+    }
+}
+
+impl TryFrom<&str> for PixelFormat {
+    type Error = Error;
+
+    /// Parse a format written by its [`Display`](fmt::Display) implementation.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let code = match value.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => <[u8; 4]>::try_from(value.as_bytes())
+                .ok()
+                .map(|bytes| fourcc(&bytes)),
+        };
+
+        match code {
+            Some(code) => Ok(PixelFormat::from(code)),
+            None => Err(Error::Other(
+                format!("Invalid FourCC code: {:?}", value).into(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for PixelFormat {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        PixelFormat::try_from(value.as_str())
+    }
+}
+
+/// Dimensions of a frame, in pixels.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Resolution {
+    /// Width of the frame.
+    pub width: u32,
+    /// Height of the frame.
+    pub height: u32,
+}
+
+impl Resolution {
+    /// Create a resolution of `width`x`height` pixels.
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Aspect ratio of the resolution, as a reduced `(width, height)` fraction.
+    ///
+    /// A resolution of 0x0 is returned as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::Resolution;
+    ///
+    /// assert_eq!(Resolution::new(1920, 1080).aspect_ratio(), (16, 9));
+    /// assert_eq!(Resolution::new(1280, 800).aspect_ratio(), (8, 5));
+    /// ```
+    pub fn aspect_ratio(&self) -> (u32, u32) {
+        let (mut a, mut b) = (self.width, self.height);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+
+        match a {
+            0 => (self.width, self.height),
+            gcd => (self.width / gcd, self.height / gcd),
+        }
+    }
+
+    /// Whether the aspect ratio is a common one, which every consumer should accept.
+    ///
+    /// Those are 16:9, 4:3, 16:10, 5:4, 3:2, 1:1 and the 21:9 variants (64:27, 43:18 and
+    /// 12:5), in landscape or portrait orientation.
+    pub fn is_standard(&self) -> bool {
+        const STANDARD: [(u32, u32); 9] = [
+            (16, 9),
+            (4, 3),
+            (8, 5),
+            (5, 4),
+            (3, 2),
+            (1, 1),
+            (64, 27),
+            (43, 18),
+            (12, 5),
+        ];
+
+        let (width, height) = self.aspect_ratio();
+        STANDARD.contains(&(width, height)) || STANDARD.contains(&(height, width))
+    }
+
+    /// Smallest and largest resolutions a device created with `cfg` accepts.
+    ///
+    /// Fields left to 0 and values out of the limits of v4l2loopback (48x32 to 8192x8192) are
+    /// replaced by those limits, like v4l2loopback does. The maximal dimensions are never
+    /// below the minimal ones.
+    fn bounds(cfg: &DeviceConfig) -> (Self, Self) {
+        let max = |value: u32, min: u32| match value {
+            0 => MAX_DIMENSION,
+            value => value.clamp(min, MAX_DIMENSION),
+        };
+        let max_width = max(cfg.max_width, MIN_WIDTH);
+        let max_height = max(cfg.max_height, MIN_HEIGHT);
+
+        (
+            Self::new(
+                cfg.min_width.clamp(MIN_WIDTH, max_width),
+                cfg.min_height.clamp(MIN_HEIGHT, max_height),
+            ),
+            Self::new(max_width, max_height),
+        )
+    }
+
+    /// Closest resolution to this one that a device created with `cfg` accepts.
+    ///
+    /// Each dimension is clamped separately between the minimal and maximal dimensions of
+    /// `cfg`, so the aspect ratio may change. This avoids asking the device with
+    /// [`set_format`](crate::set_format) to find a resolution it accepts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{DeviceConfig, Resolution};
+    ///
+    /// let config = DeviceConfig {
+    ///     max_width: 1920,
+    ///     max_height: 1080,
+    ///     ..DeviceConfig::default()
+    /// };
+    /// assert_eq!(
+    ///     Resolution::new(3840, 2160).clamp_to(&config),
+    ///     Resolution::new(1920, 1080)
+    /// );
+    /// ```
+    pub fn clamp_to(&self, cfg: &DeviceConfig) -> Resolution {
+        let (min, max) = Self::bounds(cfg);
+
+        Self::new(
+            self.width.clamp(min.width, max.width),
+            self.height.clamp(min.height, max.height),
+        )
+    }
+
+    /// Whether a device created with `cfg` accepts this resolution as is, see
+    /// [`Resolution::clamp_to`].
+    pub fn fits_in(&self, cfg: &DeviceConfig) -> bool {
+        self.clamp_to(cfg) == *self
+    }
+}
+
+/// Color space of the frames, defining the chromaticities of the primary colors and the white
+/// point.
+///
+/// The values match the `v4l2_colorspace` enum. Values this crate doesn't know about are kept as
+/// [`Colorspace::Unknown`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Colorspace {
+    /// Let the driver pick the color space from the pixel format.
+    Default,
+    /// SMPTE 170M, used by SDTV (`V4L2_COLORSPACE_SMPTE170M`).
+    Smpte170m,
+    /// SMPTE 240M, an obsolete HDTV standard (`V4L2_COLORSPACE_SMPTE240M`).
+    Smpte240m,
+    /// Rec. 709, used by HDTV and most webcams (`V4L2_COLORSPACE_REC709`).
+    Rec709,
+    /// NTSC 1953 (`V4L2_COLORSPACE_470_SYSTEM_M`).
+    System470M,
+    /// EBU Tech. 3213, used by PAL and SECAM (`V4L2_COLORSPACE_470_SYSTEM_BG`).
+    System470Bg,
+    /// JPEG, which is sRGB with full range Y'CbCr (`V4L2_COLORSPACE_JPEG`).
+    Jpeg,
+    /// sRGB, used by computer graphics (`V4L2_COLORSPACE_SRGB`).
+    Srgb,
+    /// opRGB, also known as AdobeRGB (`V4L2_COLORSPACE_OPRGB`).
+    OpRgb,
+    /// Rec. 2020, used by UHDTV (`V4L2_COLORSPACE_BT2020`).
+    Bt2020,
+    /// Raw colors, without any color space (`V4L2_COLORSPACE_RAW`).
+    Raw,
+    /// DCI-P3, used by digital cinema (`V4L2_COLORSPACE_DCI_P3`).
+    DciP3,
+    /// A color space not known by this crate, with its v4l2 value.
+    Unknown(u32),
+}
+
+impl From<u32> for Colorspace {
+    fn from(value: u32) -> Self {
+        match value {
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_DEFAULT => Colorspace::Default,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_SMPTE170M => Colorspace::Smpte170m,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_SMPTE240M => Colorspace::Smpte240m,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_REC709 => Colorspace::Rec709,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_470_SYSTEM_M => Colorspace::System470M,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_470_SYSTEM_BG => Colorspace::System470Bg,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_JPEG => Colorspace::Jpeg,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_SRGB => Colorspace::Srgb,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_OPRGB => Colorspace::OpRgb,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_BT2020 => Colorspace::Bt2020,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_RAW => Colorspace::Raw,
+            videodev2::v4l2_colorspace_V4L2_COLORSPACE_DCI_P3 => Colorspace::DciP3,
+            value => Colorspace::Unknown(value),
+        }
+    }
+}
+
+impl From<Colorspace> for u32 {
+    fn from(colorspace: Colorspace) -> Self {
+        match colorspace {
+            Colorspace::Default => videodev2::v4l2_colorspace_V4L2_COLORSPACE_DEFAULT,
+            Colorspace::Smpte170m => videodev2::v4l2_colorspace_V4L2_COLORSPACE_SMPTE170M,
+            Colorspace::Smpte240m => videodev2::v4l2_colorspace_V4L2_COLORSPACE_SMPTE240M,
+            Colorspace::Rec709 => videodev2::v4l2_colorspace_V4L2_COLORSPACE_REC709,
+            Colorspace::System470M => videodev2::v4l2_colorspace_V4L2_COLORSPACE_470_SYSTEM_M,
+            Colorspace::System470Bg => videodev2::v4l2_colorspace_V4L2_COLORSPACE_470_SYSTEM_BG,
+            Colorspace::Jpeg => videodev2::v4l2_colorspace_V4L2_COLORSPACE_JPEG,
+            Colorspace::Srgb => videodev2::v4l2_colorspace_V4L2_COLORSPACE_SRGB,
+            Colorspace::OpRgb => videodev2::v4l2_colorspace_V4L2_COLORSPACE_OPRGB,
+            Colorspace::Bt2020 => videodev2::v4l2_colorspace_V4L2_COLORSPACE_BT2020,
+            Colorspace::Raw => videodev2::v4l2_colorspace_V4L2_COLORSPACE_RAW,
+            Colorspace::DciP3 => videodev2::v4l2_colorspace_V4L2_COLORSPACE_DCI_P3,
+            Colorspace::Unknown(value) => value,
+        }
+    }
+}
+
+/// Range of the values encoding the colors of the frames.
+///
+/// The values match the `v4l2_quantization` enum.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Quantization {
+    /// Let the driver pick the range from the pixel format and color space.
+    Default,
+    /// Values use the full range, for example 0-255 for 8 bits components.
+    FullRange,
+    /// Values use a limited range, for example 16-235 for 8 bits luma and 16-240 for 8 bits
+    /// chroma.
+    LimitedRange,
+    /// A range not known by this crate, with its v4l2 value.
+    Unknown(u32),
+}
+
+impl From<u32> for Quantization {
+    fn from(value: u32) -> Self {
+        match value {
+            videodev2::v4l2_quantization_V4L2_QUANTIZATION_DEFAULT => Quantization::Default,
+            videodev2::v4l2_quantization_V4L2_QUANTIZATION_FULL_RANGE => Quantization::FullRange,
+            videodev2::v4l2_quantization_V4L2_QUANTIZATION_LIM_RANGE => Quantization::LimitedRange,
+            value => Quantization::Unknown(value),
+        }
+    }
+}
+
+impl From<Quantization> for u32 {
+    fn from(quantization: Quantization) -> Self {
+        match quantization {
+            Quantization::Default => videodev2::v4l2_quantization_V4L2_QUANTIZATION_DEFAULT,
+            Quantization::FullRange => videodev2::v4l2_quantization_V4L2_QUANTIZATION_FULL_RANGE,
+            Quantization::LimitedRange => videodev2::v4l2_quantization_V4L2_QUANTIZATION_LIM_RANGE,
+            Quantization::Unknown(value) => value,
+        }
+    }
+}
+
+/// Transfer function applied to the colors of the frames, also known as gamma.
+///
+/// The values match the `v4l2_xfer_func` enum.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TransferFunction {
+    /// Let the driver pick the transfer function from the color space.
+    Default,
+    /// Rec. 709 transfer function (`V4L2_XFER_FUNC_709`).
+    Rec709,
+    /// sRGB transfer function (`V4L2_XFER_FUNC_SRGB`).
+    Srgb,
+    /// opRGB transfer function (`V4L2_XFER_FUNC_OPRGB`).
+    OpRgb,
+    /// SMPTE 240M transfer function (`V4L2_XFER_FUNC_SMPTE240M`).
+    Smpte240m,
+    /// No transfer function, the colors are linear (`V4L2_XFER_FUNC_NONE`).
+    Linear,
+    /// DCI-P3 transfer function (`V4L2_XFER_FUNC_DCI_P3`).
+    DciP3,
+    /// SMPTE ST 2084 transfer function, used by HDR content (`V4L2_XFER_FUNC_SMPTE2084`).
+    Smpte2084,
+    /// A transfer function not known by this crate, with its v4l2 value.
+    Unknown(u32),
+}
+
+impl From<u32> for TransferFunction {
+    fn from(value: u32) -> Self {
+        match value {
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_DEFAULT => TransferFunction::Default,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_709 => TransferFunction::Rec709,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_SRGB => TransferFunction::Srgb,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_OPRGB => TransferFunction::OpRgb,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_SMPTE240M => TransferFunction::Smpte240m,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_NONE => TransferFunction::Linear,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_DCI_P3 => TransferFunction::DciP3,
+            videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_SMPTE2084 => TransferFunction::Smpte2084,
+            value => TransferFunction::Unknown(value),
+        }
+    }
+}
+
+impl From<TransferFunction> for u32 {
+    fn from(transfer_function: TransferFunction) -> Self {
+        match transfer_function {
+            TransferFunction::Default => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_DEFAULT,
+            TransferFunction::Rec709 => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_709,
+            TransferFunction::Srgb => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_SRGB,
+            TransferFunction::OpRgb => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_OPRGB,
+            TransferFunction::Smpte240m => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_SMPTE240M,
+            TransferFunction::Linear => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_NONE,
+            TransferFunction::DciP3 => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_DCI_P3,
+            TransferFunction::Smpte2084 => videodev2::v4l2_xfer_func_V4L2_XFER_FUNC_SMPTE2084,
+            TransferFunction::Unknown(value) => value,
+        }
+    }
+}
+
+/// Format of the frames passed through a device.
+///
+/// Formats created with [`Format::new`] use the colorimetry of most webcams: [`Rec709`] color
+/// space and transfer function, with [`LimitedRange`] quantization. The colorimetry fields can
+/// be changed to describe other sources.
+///
+/// [`Rec709`]: Colorspace::Rec709
+/// [`LimitedRange`]: Quantization::LimitedRange
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{Colorspace, Format, PixelFormat, Quantization, Resolution, TransferFunction};
+///
+/// let format = Format {
+///     colorspace: Colorspace::Srgb,
+///     quantization: Quantization::FullRange,
+///     transfer_function: TransferFunction::Srgb,
+///     ..Format::new(PixelFormat::Rgb24, Resolution::new(1280, 720))
+/// };
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Format {
+    /// Pixel format of the frames.
+    pub pixel_format: PixelFormat,
+    /// Dimensions of the frames.
+    pub resolution: Resolution,
+    /// Color space of the frames.
+    pub colorspace: Colorspace,
+    /// Range of the values encoding the colors.
+    pub quantization: Quantization,
+    /// Transfer function applied to the colors.
+    pub transfer_function: TransferFunction,
+}
+
+impl Format {
+    /// Create a format of `resolution` frames in `pixel_format`, with the default colorimetry.
+    pub const fn new(pixel_format: PixelFormat, resolution: Resolution) -> Self {
+        Self {
+            pixel_format,
+            resolution,
+            colorspace: Colorspace::Rec709,
+            quantization: Quantization::LimitedRange,
+            transfer_function: TransferFunction::Rec709,
+        }
+    }
+
+    /// Guess the format of `width`x`height` frames from the size of a frame buffer.
+    ///
+    /// Formats with the same frame size can't be told apart, so only the most common format
+    /// of each size is considered: [`Yuyv`], [`Nv12`], [`Rgb24`], [`Rgb32`] and [`Grey`].
+    /// For example, a buffer of a [`Uyvy`] frame is guessed as [`Yuyv`].
+    ///
+    /// Returns [`None`] if no format matches the size, or if several do (which can happen for
+    /// tiny odd dimensions).
+    ///
+    /// [`Yuyv`]: PixelFormat::Yuyv
+    /// [`Uyvy`]: PixelFormat::Uyvy
+    /// [`Nv12`]: PixelFormat::Nv12
+    /// [`Rgb24`]: PixelFormat::Rgb24
+    /// [`Rgb32`]: PixelFormat::Rgb32
+    /// [`Grey`]: PixelFormat::Grey
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{Format, PixelFormat};
+    ///
+    /// let frame = vec![0; 640 * 480 * 3];
+    /// let format = Format::infer(640, 480, frame.len()).expect("Unknown frame size");
+    /// assert_eq!(format.pixel_format, PixelFormat::Rgb24);
+    /// ```
+    pub fn infer(width: u32, height: u32, buffer_len: usize) -> Option<Self> {
+        const COMMON: [PixelFormat; 5] = [
+            PixelFormat::Yuyv,
+            PixelFormat::Nv12,
+            PixelFormat::Rgb24,
+            PixelFormat::Rgb32,
+            PixelFormat::Grey,
+        ];
+
+        let mut matching = COMMON
+            .into_iter()
+            .filter(|format| format.image_size(width, height) == Some(buffer_len));
+        match (matching.next(), matching.next()) {
+            (Some(pixel_format), None) => {
+                Some(Self::new(pixel_format, Resolution::new(width, height)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of a frame in this format, see [`PixelFormat::image_size`].
+    pub fn image_size(&self) -> Option<usize> {
+        self.pixel_format
+            .image_size(self.resolution.width, self.resolution.height)
+    }
+
+    /// A frame in this format with every pixel of the `(r, g, b)` color.
+    ///
+    /// Colors are converted to YUV with the BT.601 coefficients, in the range given by
+    /// [`Format::quantization`]. Returns [`None`] for an [`Unknown`](PixelFormat::Unknown)
+    /// pixel format.
+    pub(crate) fn solid_frame(&self, (r, g, b): (u8, u8, u8)) -> Option<Vec<u8>> {
+        let Resolution { width, height } = self.resolution;
+        let luma = width as usize * height as usize;
+        let chroma = width.div_ceil(2) as usize * height.div_ceil(2) as usize;
+        let [y, u, v] = rgb_to_yuv(r, g, b, self.quantization == Quantization::FullRange);
+
+        let frame = match self.pixel_format {
+            PixelFormat::Yuyv => [y, u, y, v].repeat(width.div_ceil(2) as usize * height as usize),
+            PixelFormat::Uyvy => [u, y, v, y].repeat(width.div_ceil(2) as usize * height as usize),
+            PixelFormat::Yvyu => [y, v, y, u].repeat(width.div_ceil(2) as usize * height as usize),
+            PixelFormat::Nv12 => [vec![y; luma], [u, v].repeat(chroma)].concat(),
+            PixelFormat::Nv21 => [vec![y; luma], [v, u].repeat(chroma)].concat(),
+            PixelFormat::Yuv420 => [vec![y; luma], vec![u; chroma], vec![v; chroma]].concat(),
+            PixelFormat::Yvu420 => [vec![y; luma], vec![v; chroma], vec![u; chroma]].concat(),
+            PixelFormat::Rgb24 => [r, g, b].repeat(luma),
+            PixelFormat::Bgr24 => [b, g, r].repeat(luma),
+            PixelFormat::Rgb32 => [0xff, r, g, b].repeat(luma),
+            PixelFormat::Bgr32 => [b, g, r, 0xff].repeat(luma),
+            // Greyscale frames always use the full range
+            PixelFormat::Grey => vec![rgb_to_yuv(r, g, b, true)[0]; luma],
+            PixelFormat::Unknown(_) => return None,
+        };
+
+        Some(frame)
+    }
+}
+
+/// Time between two frames, as a fraction of a second.
+///
+/// For example, 30 frames per second is an interval of `1/30`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct FrameInterval {
+    /// Numerator of the interval, in seconds.
+    pub numerator: u32,
+    /// Denominator of the interval, in seconds.
+    pub denominator: u32,
+}
+
+impl FrameInterval {
+    /// Create an interval of `numerator/denominator` seconds.
+    pub const fn new(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Number of frames per second matching this interval.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::FrameInterval;
+    ///
+    /// assert_eq!(FrameInterval::new(1, 30).fps(), 30.0);
+    /// assert_eq!(FrameInterval::new(1001, 30000).fps(), 30000.0 / 1001.0);
+    /// ```
+    pub fn fps(&self) -> f64 {
+        Fps::from(*self).as_f64()
+    }
+}
+
+/// Rate of frames, as a fraction of frames per second.
+///
+/// Rates like 29.97 fps are actually `30000/1001`, which a fraction keeps exact where a float
+/// wouldn't. It converts to and from a [`FrameInterval`], which is its inverse.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Fps {
+    /// Numerator of the rate, in frames per second.
+    pub numerator: u32,
+    /// Denominator of the rate, in frames per second.
+    pub denominator: u32,
+}
+
+impl Fps {
+    /// Create a rate of `numerator/denominator` frames per second.
+    pub const fn new(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Rate closest to `hz` frames per second, [`None`] if it isn't a positive number.
+    ///
+    /// Integer rates and the NTSC rates (like 29.97 or 59.94, which are `N*1000/1001`) are
+    /// exact, other rates are rounded to the thousandth.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::Fps;
+    ///
+    /// assert_eq!(Fps::from_hz(30.0), Some(Fps::new(30, 1)));
+    /// assert_eq!(Fps::from_hz(29.97), Some(Fps::new(30000, 1001)));
+    /// assert_eq!(Fps::from_hz(12.5), Some(Fps::new(25, 2)));
+    /// ```
+    pub fn from_hz(hz: f64) -> Option<Self> {
+        if !hz.is_finite() || hz <= 0.0 || hz > u32::MAX as f64 {
+            return None;
+        }
+
+        if (hz - hz.round()).abs() < 1e-6 {
+            return Some(Self::new(hz.round() as u32, 1));
+        }
+        let ntsc = (hz * 1.001).round();
+        if ntsc * 1000.0 <= u32::MAX as f64 && (hz - ntsc * 1000.0 / 1001.0).abs() < 1e-3 {
+            return Some(Self::new(ntsc as u32 * 1000, 1001));
+        }
+
+        let thousandths = (hz * 1000.0).round();
+        if thousandths == 0.0 || thousandths > u32::MAX as f64 {
+            return None;
+        }
+        let thousandths = thousandths as u32;
+        let gcd = gcd(thousandths, 1000);
+        Some(Self::new(thousandths / gcd, 1000 / gcd))
+    }
+
+    /// Number of frames per second, as a float.
+    pub fn as_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Time between two frames at this rate, rounded to the nanosecond.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use v4l2loopback::Fps;
+    ///
+    /// assert_eq!(Fps::new(25, 1).frame_duration(), Duration::from_millis(40));
+    /// ```
+    pub fn frame_duration(&self) -> Duration {
+        if self.numerator == 0 {
+            return Duration::MAX;
+        }
+
+        let numerator = u128::from(self.numerator);
+        let nanos = (u128::from(self.denominator) * 1_000_000_000 + numerator / 2) / numerator;
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+impl From<FrameInterval> for Fps {
+    fn from(interval: FrameInterval) -> Self {
+        Self::new(interval.denominator, interval.numerator)
+    }
+}
+
+impl From<Fps> for FrameInterval {
+    fn from(fps: Fps) -> Self {
+        Self::new(fps.denominator, fps.numerator)
+    }
+}
+
+/// Convert an RGB color to YUV, with the BT.601 coefficients.
+fn rgb_to_yuv(r: u8, g: u8, b: u8, full_range: bool) -> [u8; 3] {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let (y, u, v) = if full_range {
+        (
+            (77 * r + 150 * g + 29 * b + 128) >> 8,
+            ((-43 * r - 85 * g + 128 * b + 128) >> 8) + 128,
+            ((128 * r - 107 * g - 21 * b + 128) >> 8) + 128,
+        )
+    } else {
+        (
+            ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16,
+            ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128,
+            ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128,
+        )
+    };
+
+    [y, u, v].map(|channel| channel.clamp(0, 255) as u8)
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        Colorspace, DeviceConfig, Format, Fps, FrameInterval, PixelFormat, Quantization,
+        Resolution, TransferFunction,
+    };
+
+    #[test]
+    fn image_sizes() {
+        let resolutions = [
+            (640, 480),
+            (1280, 720),
+            (1920, 1080),
+            (3840, 2160),
+            (33, 17),
+        ];
+        #[rustfmt::skip]
+        let expected: [(PixelFormat, [usize; 5]); 12] = [
+            (PixelFormat::Yuyv, [614400, 1843200, 4147200, 16588800, 1156]),
+            (PixelFormat::Uyvy, [614400, 1843200, 4147200, 16588800, 1156]),
+            (PixelFormat::Yvyu, [614400, 1843200, 4147200, 16588800, 1156]),
+            (PixelFormat::Nv12, [460800, 1382400, 3110400, 12441600, 867]),
+            (PixelFormat::Nv21, [460800, 1382400, 3110400, 12441600, 867]),
+            (PixelFormat::Yuv420, [460800, 1382400, 3110400, 12441600, 867]),
+            (PixelFormat::Yvu420, [460800, 1382400, 3110400, 12441600, 867]),
+            (PixelFormat::Rgb24, [921600, 2764800, 6220800, 24883200, 1683]),
+            (PixelFormat::Bgr24, [921600, 2764800, 6220800, 24883200, 1683]),
+            (PixelFormat::Rgb32, [1228800, 3686400, 8294400, 33177600, 2244]),
+            (PixelFormat::Bgr32, [1228800, 3686400, 8294400, 33177600, 2244]),
+            (PixelFormat::Grey, [307200, 921600, 2073600, 8294400, 561]),
+        ];
+
+        for (format, sizes) in expected {
+            for ((width, height), size) in resolutions.into_iter().zip(sizes) {
+                assert_eq!(
+                    format.image_size(width, height),
+                    Some(size),
+                    "{:?} at {}x{}",
+                    format,
+                    width,
+                    height
+                );
+            }
+        }
+        assert_eq!(PixelFormat::Unknown(0).image_size(640, 480), None);
+    }
+
+    #[test]
+    fn fourcc_conversion() {
+        assert_eq!(PixelFormat::Yuyv.fourcc(), 0x5659_5559);
+        assert_eq!(PixelFormat::from(0x5659_5559), PixelFormat::Yuyv);
+        assert_eq!(
+            PixelFormat::from(0x4750_4a4d),
+            PixelFormat::Unknown(0x4750_4a4d)
+        );
+        assert_eq!(u32::from(PixelFormat::Nv12), PixelFormat::Nv12.fourcc());
+    }
+
+    #[test]
+    fn fourcc_strings() {
+        assert_eq!(PixelFormat::Yuyv.to_string(), "YUYV");
+        assert_eq!(PixelFormat::Unknown(0x4750_4a4d).to_string(), "MJPG");
+        assert_eq!(PixelFormat::Unknown(1).to_string(), "0x00000001");
+
+        for format in [
+            PixelFormat::Yuv420,
+            PixelFormat::Unknown(0x4750_4a4d),
+            PixelFormat::Unknown(1),
+        ] {
+            assert_eq!(PixelFormat::try_from(format.to_string()).unwrap(), format);
+        }
+        assert!(PixelFormat::try_from("YUYV2").is_err());
+        assert!(PixelFormat::try_from("0xnope").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let format = Format::new(PixelFormat::Unknown(0x4750_4a4d), Resolution::new(640, 480));
+        let json = serde_json::to_string(&format).expect("Error when serializing the format");
+        assert!(json.contains(r#""pixel_format":"MJPG""#));
+        let parsed: Format = serde_json::from_str(&json).expect("Error when parsing the format");
+        assert_eq!(parsed, format);
+
+        let fps = Fps::new(30000, 1001);
+        let json = serde_json::to_string(&fps).expect("Error when serializing the rate");
+        assert_eq!(serde_json::from_str::<Fps>(&json).unwrap(), fps);
+    }
+
+    #[test]
+    fn aspect_ratios() {
+        assert_eq!(Resolution::new(1920, 1080).aspect_ratio(), (16, 9));
+        assert_eq!(Resolution::new(1280, 720).aspect_ratio(), (16, 9));
+        assert_eq!(Resolution::new(640, 480).aspect_ratio(), (4, 3));
+        assert_eq!(Resolution::new(1920, 1200).aspect_ratio(), (8, 5));
+        assert_eq!(Resolution::new(2560, 1080).aspect_ratio(), (64, 27));
+        assert_eq!(Resolution::new(33, 17).aspect_ratio(), (33, 17));
+        assert_eq!(Resolution::new(0, 480).aspect_ratio(), (0, 1));
+        assert_eq!(Resolution::new(0, 0).aspect_ratio(), (0, 0));
+
+        assert!(Resolution::new(1920, 1080).is_standard());
+        assert!(Resolution::new(1080, 1920).is_standard());
+        assert!(Resolution::new(1024, 1024).is_standard());
+        assert!(!Resolution::new(33, 17).is_standard());
+        assert!(!Resolution::new(1366, 768).is_standard());
+    }
+
+    #[test]
+    fn infer_from_size() {
+        let infer = |len| Format::infer(1280, 720, len).map(|format| format.pixel_format);
+
+        assert_eq!(infer(1280 * 720 * 2), Some(PixelFormat::Yuyv));
+        assert_eq!(infer(1280 * 720 * 3), Some(PixelFormat::Rgb24));
+        assert_eq!(infer(1280 * 720 * 3 / 2), Some(PixelFormat::Nv12));
+        assert_eq!(infer(1280 * 720 * 2 + 1), None);
+        assert_eq!(
+            Format::infer(640, 480, 640 * 480 * 2),
+            Some(Format::new(PixelFormat::Yuyv, Resolution::new(640, 480)))
+        );
+
+        // NV12 and RGB24 frames have the same size
+        assert_eq!(Format::infer(1, 1, 3), None);
+    }
+
+    #[test]
+    fn colorimetry_conversion() {
+        for value in 0..16 {
+            assert_eq!(u32::from(Colorspace::from(value)), value);
+            assert_eq!(u32::from(Quantization::from(value)), value);
+            assert_eq!(u32::from(TransferFunction::from(value)), value);
+        }
+        assert_eq!(Colorspace::from(3), Colorspace::Rec709);
+        assert_eq!(Quantization::from(2), Quantization::LimitedRange);
+        assert_eq!(TransferFunction::from(5), TransferFunction::Linear);
+        assert_eq!(Colorspace::from(4), Colorspace::Unknown(4));
+    }
+
+    #[test]
+    fn fps_conversions() {
+        let thirty = Fps::from_hz(30.0).unwrap();
+        assert_eq!(thirty, Fps::new(30, 1));
+        assert_eq!(thirty.frame_duration(), Duration::from_nanos(33_333_333));
+        assert_eq!(FrameInterval::from(thirty), FrameInterval::new(1, 30));
+
+        let ntsc = Fps::from_hz(29.97).unwrap();
+        assert_eq!(ntsc, Fps::new(30000, 1001));
+        assert_eq!(ntsc.frame_duration(), Duration::from_nanos(33_366_667));
+        assert_eq!(Fps::from(FrameInterval::new(1001, 30000)), ntsc);
+        assert_eq!(ntsc.as_f64(), 30000.0 / 1001.0);
+
+        let sixty = Fps::from_hz(60.0).unwrap();
+        assert_eq!(sixty.frame_duration(), Duration::from_nanos(16_666_667));
+        assert_eq!(Fps::from_hz(59.94), Some(Fps::new(60000, 1001)));
+        assert_eq!(sixty.as_f64(), 60.0);
+
+        assert_eq!(Fps::from_hz(0.0), None);
+        assert_eq!(Fps::from_hz(f64::NAN), None);
+    }
+
+    #[test]
+    fn solid_frames() {
+        let resolution = Resolution::new(3, 2);
+        let frame = |pixel_format| Format::new(pixel_format, resolution).solid_frame((255, 0, 0));
+
+        assert_eq!(frame(PixelFormat::Rgb24).unwrap(), [255, 0, 0].repeat(6));
+        assert_eq!(
+            frame(PixelFormat::Bgr32).unwrap(),
+            [0, 0, 255, 255].repeat(6)
+        );
+        assert_eq!(
+            frame(PixelFormat::Yuyv).unwrap(),
+            [82, 90, 82, 240].repeat(4)
+        );
+        assert_eq!(
+            frame(PixelFormat::Nv12).unwrap(),
+            [vec![82; 6], [90, 240].repeat(2)].concat()
+        );
+        assert_eq!(frame(PixelFormat::Grey).unwrap(), [77; 6]);
+        assert_eq!(frame(PixelFormat::Unknown(0)), None);
+        // Black isn't all zeros in YUV
+        assert_eq!(
+            Format::new(PixelFormat::Yuyv, resolution)
+                .solid_frame((0, 0, 0))
+                .unwrap(),
+            [16, 128, 16, 128].repeat(4)
+        );
+
+        for pixel_format in [PixelFormat::Uyvy, PixelFormat::Yvu420, PixelFormat::Rgb32] {
+            let format = Format::new(pixel_format, resolution);
+            assert_eq!(
+                format.solid_frame((0, 128, 255)).unwrap().len(),
+                format.image_size().unwrap()
+            );
+        }
+
+        let white = Format {
+            quantization: Quantization::FullRange,
+            ..Format::new(PixelFormat::Yuyv, resolution)
+        };
+        assert_eq!(
+            white.solid_frame((255, 255, 255)).unwrap(),
+            [255, 128, 255, 128].repeat(4)
+        );
+    }
+
+    #[test]
+    fn resolution_within_bounds() {
+        let config = DeviceConfig {
+            min_width: 320,
+            min_height: 240,
+            max_width: 1920,
+            max_height: 1080,
+            ..Default::default()
+        };
+
+        let in_range = Resolution::new(1280, 720);
+        assert!(in_range.fits_in(&config));
+        assert_eq!(in_range.clamp_to(&config), in_range);
+
+        let too_large = Resolution::new(3840, 2160);
+        assert!(!too_large.fits_in(&config));
+        assert_eq!(too_large.clamp_to(&config), Resolution::new(1920, 1080));
+
+        let too_small = Resolution::new(160, 120);
+        assert!(!too_small.fits_in(&config));
+        assert_eq!(too_small.clamp_to(&config), Resolution::new(320, 240));
+
+        // Unset bounds are the limits of v4l2loopback
+        assert_eq!(
+            Resolution::new(16, 10000).clamp_to(&DeviceConfig::default()),
+            Resolution::new(48, 8192)
+        );
+    }
+
+    #[test]
+    fn bounds_below_limits() {
+        let config = DeviceConfig {
+            max_width: 16,
+            max_height: 8,
+            ..Default::default()
+        };
+
+        // The limits of v4l2loopback win over the configuration
+        assert_eq!(
+            Resolution::new(640, 480).clamp_to(&config),
+            Resolution::new(48, 32)
+        );
+        assert!(Resolution::new(48, 32).fits_in(&config));
+        assert!(!Resolution::new(16, 8).fits_in(&config));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+/// Measures the rate at which frames are written to a sink.
+///
+/// Frames are written with [`FpsMeter::write_frame`], and the rate is measured over the frames
+/// written during the last `window`.
+///
+/// # Example
+///
+/// ```
+/// use std::{fs::OpenOptions, time::Duration};
+/// use v4l2loopback::{add_device, delete_device, DeviceConfig, FpsMeter};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let output = OpenOptions::new()
+///     .write(true)
+///     .open(format!("/dev/video{}", device_num))
+///     .unwrap();
+///
+/// let mut meter = FpsMeter::new(output, Duration::from_secs(1));
+/// let frame = vec![0; 640 * 480 * 2];
+/// for _ in 0..10 {
+///     meter.write_frame(&frame).expect("Error when writing the frame");
+/// }
+/// println!("Writing at {} fps", meter.current_fps());
+///
+/// drop(meter);
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+#[derive(Debug)]
+pub struct FpsMeter<W> {
+    sink: W,
+    window: Duration,
+    frames: VecDeque<Instant>,
+}
+
+impl<W: Write> FpsMeter<W> {
+    /// Wrap `sink`, measuring the rate over the last `window`.
+    pub fn new(sink: W, window: Duration) -> Self {
+        Self {
+            sink,
+            window,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Write a whole frame to the sink, and count it once written.
+    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.sink.write_all(frame)?;
+        self.record_at(Instant::now());
+
+        Ok(())
+    }
+
+    fn record_at(&mut self, instant: Instant) {
+        self.frames.push_back(instant);
+        while let Some(oldest) = self.frames.front() {
+            if instant.duration_since(*oldest) <= self.window {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    /// Frames per second written during the last window.
+    ///
+    /// The rate is measured up to now, so it falls when the producer stalls, down to 0 once no
+    /// frames were written during a whole window. This is 0 until at least two frames were
+    /// written.
+    pub fn current_fps(&self) -> f64 {
+        self.fps_at(Instant::now())
+    }
+
+    fn fps_at(&self, now: Instant) -> f64 {
+        let mut recent = self
+            .frames
+            .iter()
+            .filter(|frame| now.duration_since(**frame) <= self.window);
+        let Some(oldest) = recent.next() else {
+            return 0.0;
+        };
+        let count = recent.count();
+
+        let elapsed = now.duration_since(*oldest).as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        count as f64 / elapsed
+    }
+
+    /// The wrapped sink.
+    pub fn get_ref(&self) -> &W {
+        &self.sink
+    }
+
+    /// Release the wrapped sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::FpsMeter;
+
+    #[test]
+    fn known_rate() {
+        let mut meter = FpsMeter::new(Vec::new(), Duration::from_secs(1));
+        assert_eq!(meter.current_fps(), 0.0);
+
+        let start = Instant::now();
+        for i in 0..90 {
+            meter.record_at(start + Duration::from_secs(i) / 30);
+        }
+        let now = start + Duration::from_secs(89) / 30;
+        assert!((meter.fps_at(now) - 30.0).abs() < 0.5);
+
+        // The rate drops, only the last second is considered
+        let start = start + Duration::from_secs(3);
+        for i in 0..20 {
+            meter.record_at(start + Duration::from_secs(i) / 10);
+        }
+        let now = start + Duration::from_secs(19) / 10;
+        assert!((meter.fps_at(now) - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn stalled_producer() {
+        let mut meter = FpsMeter::new(Vec::new(), Duration::from_secs(1));
+        let start = Instant::now();
+        for i in 0..30 {
+            meter.record_at(start + Duration::from_secs(i) / 30);
+        }
+        let last = start + Duration::from_secs(29) / 30;
+
+        // No frames for half a window, then for a whole one
+        let half_stalled = meter.fps_at(last + Duration::from_millis(500));
+        assert!(half_stalled < 20.0);
+        assert_eq!(meter.fps_at(last + Duration::from_secs(2)), 0.0);
+    }
+
+    #[test]
+    fn frames_written() {
+        let mut meter = FpsMeter::new(Vec::new(), Duration::from_secs(1));
+        meter.write_frame(&[1, 2, 3]).unwrap();
+        meter.write_frame(&[4, 5, 6]).unwrap();
+        assert_eq!(meter.get_ref(), &[1, 2, 3, 4, 5, 6]);
+
+        let start = Instant::now();
+        meter.record_at(start);
+        meter.record_at(start + Duration::from_millis(100));
+        assert!(meter.fps_at(start + Duration::from_millis(100)) > 0.0);
+        assert_eq!(meter.into_inner(), vec![1, 2, 3, 4, 5, 6]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Read},
+    os::unix::fs::OpenOptionsExt,
+};
+
+use crate::{sys::libc, Error, Result};
+
+/// Message of a `/dev/kmsg` record, if it was logged by v4l2loopback.
+///
+/// A record is `PRIORITY,SEQUENCE,TIMESTAMP,FLAGS;MESSAGE`, optionally followed by
+/// continuation lines starting with a space.
+fn module_message(record: &str) -> Option<&str> {
+    let (_, message) = record.split_once(';')?;
+    let message = message.lines().next()?;
+
+    message.contains("v4l2loopback").then_some(message)
+}
+
+/// Messages logged by v4l2loopback in the kernel log, from the oldest to the most recent.
+///
+/// When an ioctl fails, the module often logs the reason, which can't be carried by the error
+/// code. This reads the kernel log ring from `/dev/kmsg`, like `dmesg`, and keeps the messages
+/// mentioning v4l2loopback, so they can be shown to the user or attached to a bug report.
+///
+/// Reading the kernel log may be restricted to root (see the `kernel.dmesg_restrict` sysctl).
+/// In that case a warning is logged and no message is returned, since the log is only a
+/// diagnostic help.
+///
+/// This is only available with the `kmsg` feature.
+///
+/// # Errors
+///
+/// This function will return [`Other`] if `/dev/kmsg` couldn't be opened or read, for another
+/// reason than missing permissions.
+///
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, recent_module_messages, DeviceConfig};
+///
+/// if let Err(e) = add_device(None, DeviceConfig::default()) {
+///     eprintln!("{}", e);
+///     for message in recent_module_messages().unwrap_or_default() {
+///         eprintln!("kernel: {}", message);
+///     }
+/// }
+/// ```
+pub fn recent_module_messages() -> Result<Vec<String>> {
+    // Without O_NONBLOCK, reading past the last record waits for a new one
+    let mut kmsg = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/kmsg")
+    {
+        Ok(kmsg) => kmsg,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            log::warn!("Not allowed to read the kernel log from /dev/kmsg");
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    // Each read returns a single record, which is at most 8KiB long
+    let mut messages = Vec::new();
+    let mut record = vec![0; 8192];
+    loop {
+        match kmsg.read(&mut record) {
+            Ok(0) => break,
+            Ok(len) => {
+                if let Some(message) = module_message(&String::from_utf8_lossy(&record[..len])) {
+                    messages.push(message.to_string());
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            // The oldest records were overwritten while reading
+            Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                log::warn!("Not allowed to read the kernel log from /dev/kmsg");
+                break;
+            }
+            Err(e) => return Err(Error::Other(Box::new(e))),
+        }
+    }
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::module_message;
+
+    #[test]
+    fn kmsg_sample() {
+        let sample = [
+            "6,1201,8042113519,-;v4l2loopback driver version 0.13.1 loaded\n",
+            "4,1202,8042113602,-;usb 1-2: new high-speed USB device number 5 using xhci_hcd\n",
+            "3,1203,9123004410,-;v4l2loopback: allocating 4 buffers of 4147200 bytes failed\n SUBSYSTEM=video4linux\n DEVICE=c81:3\n",
+            "not a record",
+        ];
+
+        let messages: Vec<_> = sample.iter().filter_map(|r| module_message(r)).collect();
+        assert_eq!(
+            messages,
+            [
+                "v4l2loopback driver version 0.13.1 loaded",
+                "v4l2loopback: allocating 4 buffers of 4147200 bytes failed",
+            ]
+        );
+    }
+}
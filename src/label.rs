@@ -0,0 +1,125 @@
+use crate::device::LABEL_MAX_LEN;
+
+/// Character between the application and the instance in an encoded label.
+const SEPARATOR: char = ':';
+
+/// Shortest part of the budget the application keeps when the label is truncated.
+const MIN_APP_LEN: usize = 15;
+
+/// Structured labels, letting several applications share the label of devices.
+///
+/// A label is encoded as `app:instance`, where `app` identifies the application owning the
+/// device and `instance` tells its devices apart. Encoded labels fit in the 31 bytes
+/// v4l2loopback keeps, so they survive [`add_device`](crate::add_device) and
+/// [`query_device`](crate::query_device) unchanged.
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, query_device, DeviceConfig, DeviceLabel};
+///
+/// let config = DeviceConfig {
+///     label: DeviceLabel::encode("recorder", "screen-1"),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, config).expect("Error when creating the device");
+///
+/// let label = query_device(device_num).expect("Error when querying the device").label;
+/// assert_eq!(
+///     DeviceLabel::decode(&label),
+///     Some(("recorder".to_string(), "screen-1".to_string()))
+/// );
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct DeviceLabel;
+
+/// Longest prefix of `s` which is at most `len` bytes long and ends on a character boundary.
+fn truncate(s: &str, len: usize) -> &str {
+    let mut end = len.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &s[..end]
+}
+
+impl DeviceLabel {
+    /// Encode `app` and `instance` into a label.
+    ///
+    /// Since the separator is `:`, any `:` in `app` is replaced by `_`, while `instance` may
+    /// contain any character. Null bytes, which labels can't contain, are removed.
+    ///
+    /// If both parts don't fit in 31 bytes, the longest one is truncated first, but `app`
+    /// always keeps at least 15 bytes. Parts are only cut on character boundaries, so the label
+    /// may be a bit shorter than 31 bytes. Truncated parts can't be recovered by
+    /// [`DeviceLabel::decode`].
+    pub fn encode(app: &str, instance: &str) -> String {
+        let app = app.replace(SEPARATOR, "_").replace('\0', "");
+        let instance = instance.replace('\0', "");
+
+        let budget = LABEL_MAX_LEN - SEPARATOR.len_utf8();
+        let app_len = app
+            .len()
+            .min(budget.saturating_sub(instance.len()).max(MIN_APP_LEN));
+        let app = truncate(&app, app_len);
+        let instance = truncate(&instance, budget - app.len());
+
+        format!("{}{}{}", app, SEPARATOR, instance)
+    }
+
+    /// Decode a label encoded by [`DeviceLabel::encode`] into the application and the
+    /// instance.
+    ///
+    /// Returns [`None`] if the label has no `:`, which is the case of labels set without
+    /// [`DeviceLabel::encode`] most of the time.
+    pub fn decode(label: &str) -> Option<(String, String)> {
+        let (app, instance) = label.split_once(SEPARATOR)?;
+
+        Some((app.to_string(), instance.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceLabel;
+
+    fn roundtrip(app: &str, instance: &str) -> (String, String) {
+        let label = DeviceLabel::encode(app, instance);
+        assert!(label.len() <= 31, "{:?} is too long", label);
+
+        DeviceLabel::decode(&label).expect("Error when decoding the label")
+    }
+
+    #[test]
+    fn short_labels_roundtrip() {
+        assert_eq!(
+            roundtrip("recorder", "screen-1"),
+            ("recorder".to_string(), "screen-1".to_string())
+        );
+        assert_eq!(
+            roundtrip("my:app", "host:1"),
+            ("my_app".to_string(), "host:1".to_string())
+        );
+        assert_eq!(DeviceLabel::decode("Dummy video device"), None);
+    }
+
+    #[test]
+    fn long_labels_are_truncated() {
+        let app = "a-very-long-application-identifier";
+        let (decoded_app, instance) = roundtrip(app, "cam0");
+        assert_eq!(instance, "cam0");
+        assert!(app.starts_with(&decoded_app));
+        assert_eq!(decoded_app.len() + instance.len(), 30);
+
+        let instance = "an-instance-name-which-is-way-too-long";
+        let (decoded_app, decoded_instance) = roundtrip(app, instance);
+        assert_eq!(decoded_app.len(), 15);
+        assert!(instance.starts_with(&decoded_instance));
+
+        // "é" is 2 bytes long, it can't be cut in half
+        let (decoded_app, _) = roundtrip(&"é".repeat(20), instance);
+        assert_eq!(decoded_app, "é".repeat(7));
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(debug_assertions)]
+use std::{
+    collections::BTreeSet,
+    sync::{Mutex, Once, PoisonError},
+};
+
+#[cfg(debug_assertions)]
+static LIVE_DEVICES: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+#[cfg(debug_assertions)]
+static REPORT_AT_EXIT: Once = Once::new();
+
+/// Numbers of the devices with a live handle.
+#[cfg(debug_assertions)]
+pub(crate) fn live_devices() -> Vec<u32> {
+    LIVE_DEVICES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .copied()
+        .collect()
+}
+
+/// Warning printed at exit when handles were leaked.
+#[cfg(debug_assertions)]
+pub(crate) fn leak_warning(device_nums: &[u32]) -> Option<String> {
+    if device_nums.is_empty() {
+        return None;
+    }
+
+    let devices: Vec<String> = device_nums
+        .iter()
+        .map(|num| format!("/dev/video{}", num))
+        .collect();
+    Some(format!(
+        "warning: v4l2loopback device handles were never dropped, those devices still exist: {}",
+        devices.join(", ")
+    ))
+}
+
+/// Warning for the handles which are still live, printed by the report at exit.
+#[cfg(debug_assertions)]
+fn pending_report() -> Option<String> {
+    leak_warning(&live_devices())
+}
+
+#[cfg(debug_assertions)]
+extern "C" fn report_leaks() {
+    if let Some(warning) = pending_report() {
+        eprintln!("{}", warning);
+    }
+}
+
+/// Start tracking the handle of a device.
+///
+/// The first call registers the report of leaked handles at exit.
+#[cfg(debug_assertions)]
+pub(crate) fn track(device_num: u32) {
+    REPORT_AT_EXIT.call_once(|| unsafe {
+        crate::sys::libc::atexit(report_leaks);
+    });
+    LIVE_DEVICES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(device_num);
+}
+
+/// Stop tracking the handle of a device, once it is dropped.
+#[cfg(debug_assertions)]
+pub(crate) fn untrack(device_num: u32) {
+    LIVE_DEVICES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&device_num);
+}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn track(_device_num: u32) {}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn untrack(_device_num: u32) {}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::{leak_warning, live_devices, pending_report, untrack, REPORT_AT_EXIT};
+    use crate::{delete_device, Device};
+
+    #[test]
+    fn forgotten_handle_reported() {
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        let device_num = device.number().as_u32();
+        std::mem::forget(device);
+
+        // The report is registered, and would name the device at exit
+        assert!(REPORT_AT_EXIT.is_completed());
+        assert!(live_devices().contains(&device_num));
+        let warning = pending_report().expect("The leak should be reported");
+        assert!(warning.contains(&format!("/dev/video{}", device_num)));
+
+        // Cleaning up what the handle should have done
+        untrack(device_num);
+        delete_device(device_num).expect("Error when removing device");
+        assert!(!live_devices().contains(&device_num));
+    }
+
+    #[test]
+    fn released_handles_untracked() {
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        let device_num = device.number().as_u32();
+        assert!(live_devices().contains(&device_num));
+        assert_eq!(device.leak(), device_num);
+        assert!(!live_devices().contains(&device_num));
+        delete_device(device_num).expect("Error when removing device");
+
+        let device = Device::new(None, Default::default()).expect("Error when creating the device");
+        let device_num = device.number().as_u32();
+        assert!(live_devices().contains(&device_num));
+        drop(device);
+        assert!(!live_devices().contains(&device_num));
+    }
+
+    #[test]
+    fn no_warning_without_leak() {
+        assert_eq!(leak_warning(&[]), None);
+    }
+}
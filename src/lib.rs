@@ -20,7 +20,7 @@
 //!
 //! ```
 //! use std::path::Path;
-//! use v4l2loopback::{add_device, delete_device, query_device, DeviceConfig};
+//! use v4l2loopback::{add_device, delete_device, query_device, DebugFlags, DeviceConfig};
 //!
 //! // Device configuration
 //! // Here you declare informations about the camera device that will be created.
@@ -33,6 +33,8 @@
 //!     max_height: 4000,
 //!     max_buffers: 9,
 //!     max_openers: 3,
+//!     announce_all_caps: false,
+//!     debug: DebugFlags::empty(),
 //! };
 //! // Create a device
 //! let device_num =
@@ -53,16 +55,104 @@
 //! [v4l2loopback-dkms-git]: https://aur.archlinux.org/packages/v4l2loopback-dkms-git
 
 use std::{
-    ffi::{CStr, CString},
-    fs::OpenOptions,
-    io::ErrorKind,
-    os::fd::{IntoRawFd, RawFd},
-    slice::from_raw_parts,
+    ffi::CString,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use nix::{errno::Errno, ioctl_read_bad, ioctl_readwrite_bad, ioctl_write_int_bad};
+use bitflags::bitflags;
 use thiserror::Error;
 
+use control::{open_control_device, ControlFd};
+use sys::{ioctl_read_bad, ioctl_readwrite_bad, ioctl_write_int_bad};
+use timeout::with_timeout;
+
+mod broadcast;
+mod builder;
+mod caps;
+mod control;
+mod crop;
+mod dedup;
+mod device;
+mod format;
+mod fps;
+#[cfg(feature = "kmsg")]
+mod kmsg;
+mod label;
+mod leaks;
+mod module;
+mod openers;
+mod params;
+mod priority;
+mod registry;
+mod settings;
+mod sizes;
+#[cfg(feature = "image")]
+mod snapshot;
+mod spec;
+#[cfg(feature = "serde")]
+mod state;
+mod status;
+mod stream;
+mod sys;
+mod sysfs;
+mod timeout;
+mod user_controls;
+mod video;
+mod writer;
+
+pub use broadcast::Broadcaster;
+pub use builder::DeviceConfigBuilder;
+pub use caps::{
+    effective_caps, is_exclusive, list_devices_with_caps, query_capabilities, verify_label,
+    Capabilities, DeviceCaps, LabelCheck,
+};
+pub use control::{clear_thread_control, enable_thread_control, Assignment, Control};
+pub use crop::{crop_caps, CropCaps, PixelAspect, Rectangle};
+pub use dedup::DedupWriter;
+pub use device::{restart_device, Device, DeviceNumber, SharedDevice};
+pub use format::{
+    Colorspace, Format, Fps, FrameInterval, PixelFormat, Quantization, Resolution, TransferFunction,
+};
+pub use fps::FpsMeter;
+#[cfg(feature = "kmsg")]
+pub use kmsg::recent_module_messages;
+pub use label::DeviceLabel;
+pub use module::{
+    compiled_version, module_features, module_version, module_version_string, preflight,
+    supports_dynamic_devices, ModuleFeatures, Preflight, COMPILED_MODULE_VERSION,
+};
+pub use openers::{max_openers, set_max_openers};
+pub use params::{
+    get_format, get_frame_interval, negotiate_format, set_format, set_formats, set_frame_interval,
+};
+pub use priority::{get_priority, set_priority, Priority};
+pub use settings::{set_settings, settings, Settings};
+pub use sizes::{enum_frame_intervals, enum_frame_sizes, FrameIntervals, FrameSizes};
+#[cfg(feature = "image")]
+pub use snapshot::capture_snapshot;
+pub use spec::SpecParseError;
+#[cfg(feature = "serde")]
+pub use state::{export_state, import_state, DeviceState, NumberConflict, StateDocument};
+pub use status::{
+    device_status, last_frame_info, verify_roundtrip, wait_for_consumer, DeviceStatus, FrameInfo,
+};
+#[cfg(feature = "bytes")]
+pub use stream::write_bytes;
+pub use stream::{write_frames_from, TrailingFrame};
+pub use sys::Errno;
+pub use sysfs::{
+    current_format_sysfs, device_index, device_stable_id, device_sysfs_info, enumerate_video_nodes,
+    find_by_stable_id, is_loopback_device_sysfs, metrics_all, DeviceMetrics, NodeKind, SysfsInfo,
+    VideoNode,
+};
+pub use user_controls::{
+    get_brightness, get_contrast, get_saturation, set_brightness, set_contrast, set_saturation,
+};
+pub use video::{DeviceFile, Direction, ExclusiveOutput};
+pub use writer::{FrameErrorAction, FrameWriter, MemoryType};
+
 mod ffi {
     #![allow(non_upper_case_globals)]
     #![allow(non_camel_case_types)]
@@ -90,12 +180,22 @@ mod ffi {
     }
 }
 
+mod videodev2 {
+    #![allow(non_upper_case_globals)]
+    #![allow(non_camel_case_types)]
+    #![allow(non_snake_case)]
+    #![allow(dead_code)]
+
+    include!(concat!(env!("OUT_DIR"), "/videodev2.rs"));
+}
+
 pub use ffi::V4L2LOOPBACK_VERSION_BUGFIX;
 pub use ffi::V4L2LOOPBACK_VERSION_MAJOR;
 pub use ffi::V4L2LOOPBACK_VERSION_MINOR;
 
 /// Wrapper type describing a v4l2loopback device.
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceConfig {
     /// A nice name for you device.
     /// If empty, v4l2loopback will choose a generic name
@@ -106,21 +206,305 @@ pub struct DeviceConfig {
     pub min_width: u32,
     /// Allowed maximum frame witdh.
     /// Setting this value above 8192 as no effets as it is the maximal accepted value.
+    ///
+    /// If 0, the default maximum of the module is used when creating a device, which is its
+    /// `max_width` parameter, or 8192 if the module doesn't expose it.
     pub max_width: u32,
     /// Allowed minimum frame height.
     /// Setting this value below 32 as no effets as it is the minimal accepted value.
     pub min_height: u32,
     /// Allowed maximum frame height.
     /// Setting this value above 8192 as no effets as it is the maximal accepted value.
+    ///
+    /// If 0, the default maximum of the module is used when creating a device, which is its
+    /// `max_height` parameter, or 8192 if the module doesn't expose it.
     pub max_height: u32,
 
     /// Number of buffers to allocate for the queue.
     /// If 0, then a default value is picked by v4l2loopback.
+    ///
+    /// When querying a device, this is the number of buffers actually allocated, so the value
+    /// picked by v4l2loopback is returned instead of 0. See [`DeviceConfig::buffers`] for an
+    /// explicit view of this value.
     pub max_buffers: u32,
 
     /// How many consumers are allowed to open this device concurrently.
     /// If 0, then a default value is picked by v4l2loopback.
     pub max_openers: u32,
+
+    /// Announce both capture and output capabilities at all times.
+    ///
+    /// If `false`, the device is in "exclusive caps" mode: it only announces output
+    /// capabilities until a producer starts writing to it, and only capture capabilities
+    /// afterwards. Some applications (like Chrome or WebRTC based ones) only list devices in
+    /// this mode.
+    ///
+    /// Use [`effective_caps`] to check what a device actually announces.
+    pub announce_all_caps: bool,
+
+    /// Categories of debug messages v4l2loopback should print in the kernel log.
+    pub debug: DebugFlags,
+}
+
+bitflags! {
+    /// Categories of debug messages printed by v4l2loopback.
+    ///
+    /// Bits which are not named are passed to v4l2loopback as is.
+    #[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct DebugFlags: u32 {
+        /// Messages about ioctl calls.
+        const IOCTL = 1 << 0;
+        /// Messages about reads and writes.
+        const FILEIO = 1 << 1;
+        /// Messages about the capabilities announced by the device.
+        const CAPS = 1 << 2;
+
+        const _ = !0;
+    }
+}
+
+/// A numeric field of [`DeviceConfig`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ConfigField {
+    /// [`DeviceConfig::min_width`]
+    MinWidth,
+    /// [`DeviceConfig::max_width`]
+    MaxWidth,
+    /// [`DeviceConfig::min_height`]
+    MinHeight,
+    /// [`DeviceConfig::max_height`]
+    MaxHeight,
+    /// [`DeviceConfig::max_buffers`]
+    MaxBuffers,
+    /// [`DeviceConfig::max_openers`]
+    MaxOpeners,
+}
+
+/// A field of [`DeviceConfig`] adjusted by v4l2loopback, see [`DeviceConfig::was_clamped`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ClampedField {
+    /// The adjusted field.
+    pub field: ConfigField,
+    /// Value which was requested.
+    pub requested: u32,
+    /// Value the device actually uses.
+    pub actual: u32,
+}
+
+impl ClampedField {
+    /// How much the value was adjusted, negative if it was lowered.
+    pub fn difference(&self) -> i64 {
+        i64::from(self.actual) - i64::from(self.requested)
+    }
+}
+
+/// A field which differs between two [`DeviceConfig`], see [`DeviceConfig::diff`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct FieldChange {
+    /// Name of the field in [`DeviceConfig`], like `"max_width"`.
+    pub field: &'static str,
+    /// Value of the field in the original configuration.
+    pub from: String,
+    /// Value of the field in the other configuration.
+    pub to: String,
+}
+
+impl DeviceConfig {
+    /// Number of buffers to allocate for the queue, or [`None`] to let v4l2loopback pick a
+    /// default value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::num::NonZeroU32;
+    /// use v4l2loopback::DeviceConfig;
+    ///
+    /// assert_eq!(DeviceConfig::default().buffers(), None);
+    ///
+    /// let device_config = DeviceConfig {
+    ///     max_buffers: 4,
+    ///     ..DeviceConfig::default()
+    /// };
+    /// assert_eq!(device_config.buffers(), NonZeroU32::new(4));
+    /// ```
+    pub fn buffers(&self) -> Option<NonZeroU32> {
+        NonZeroU32::new(self.max_buffers)
+    }
+
+    /// Fields v4l2loopback adjusted, comparing the `requested` configuration with this one,
+    /// returned by [`query_device`].
+    ///
+    /// v4l2loopback clamps values to the limits of the module, for example the number of
+    /// buffers. Fields left to 0 in `requested` are picked by v4l2loopback, so they are not
+    /// reported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{add_device, delete_device, query_device, DeviceConfig};
+    ///
+    /// let requested = DeviceConfig {
+    ///     max_buffers: 64,
+    ///     ..DeviceConfig::default()
+    /// };
+    /// let device_num = add_device(None, requested.clone()).expect("Error when creating the device");
+    ///
+    /// let cfg = query_device(device_num).expect("Error when querying the device");
+    /// for clamped in cfg.was_clamped(&requested) {
+    ///     println!(
+    ///         "{:?} was changed from {} to {}",
+    ///         clamped.field, clamped.requested, clamped.actual
+    ///     );
+    /// }
+    ///
+    /// delete_device(device_num).expect("Error when removing device");
+    /// ```
+    pub fn was_clamped(&self, requested: &DeviceConfig) -> Vec<ClampedField> {
+        [
+            (ConfigField::MinWidth, requested.min_width, self.min_width),
+            (ConfigField::MaxWidth, requested.max_width, self.max_width),
+            (
+                ConfigField::MinHeight,
+                requested.min_height,
+                self.min_height,
+            ),
+            (
+                ConfigField::MaxHeight,
+                requested.max_height,
+                self.max_height,
+            ),
+            (
+                ConfigField::MaxBuffers,
+                requested.max_buffers,
+                self.max_buffers,
+            ),
+            (
+                ConfigField::MaxOpeners,
+                requested.max_openers,
+                self.max_openers,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, requested, actual)| *requested != 0 && requested != actual)
+        .map(|(field, requested, actual)| ClampedField {
+            field,
+            requested,
+            actual,
+        })
+        .collect()
+    }
+
+    /// First field v4l2loopback would reject, found without asking it.
+    fn invalid_field(&self) -> Option<ConfigField> {
+        if self.max_width != 0 && self.min_width > self.max_width {
+            return Some(ConfigField::MinWidth);
+        }
+        if self.max_height != 0 && self.min_height > self.max_height {
+            return Some(ConfigField::MinHeight);
+        }
+
+        None
+    }
+
+    /// Fields which differ from this configuration to `other`, in declaration order.
+    ///
+    /// Values are formatted for display, so the changes can be shown to the user as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::DeviceConfig;
+    ///
+    /// let current = DeviceConfig::default();
+    /// let wanted = DeviceConfig {
+    ///     max_buffers: 4,
+    ///     ..DeviceConfig::default()
+    /// };
+    ///
+    /// for change in current.diff(&wanted) {
+    ///     println!("{}: {} -> {}", change.field, change.from, change.to);
+    /// }
+    /// ```
+    pub fn diff(&self, other: &DeviceConfig) -> Vec<FieldChange> {
+        let numbers = [
+            ("min_width", self.min_width, other.min_width),
+            ("max_width", self.max_width, other.max_width),
+            ("min_height", self.min_height, other.min_height),
+            ("max_height", self.max_height, other.max_height),
+            ("max_buffers", self.max_buffers, other.max_buffers),
+            ("max_openers", self.max_openers, other.max_openers),
+        ]
+        .into_iter()
+        .filter(|(_, from, to)| from != to)
+        .map(|(field, from, to)| (field, from.to_string(), to.to_string()));
+
+        let label =
+            (self.label != other.label).then(|| ("label", self.label.clone(), other.label.clone()));
+        let announce_all_caps = (self.announce_all_caps != other.announce_all_caps).then(|| {
+            (
+                "announce_all_caps",
+                self.announce_all_caps.to_string(),
+                other.announce_all_caps.to_string(),
+            )
+        });
+        let debug = (self.debug != other.debug).then(|| {
+            (
+                "debug",
+                format!("{:?}", self.debug),
+                format!("{:?}", other.debug),
+            )
+        });
+
+        label
+            .into_iter()
+            .chain(numbers)
+            .chain(announce_all_caps)
+            .chain(debug)
+            .map(|(field, from, to)| FieldChange { field, from, to })
+            .collect()
+    }
+
+    /// Header of the table made of [`DeviceConfig::summary`] lines.
+    pub const fn table_header() -> &'static str {
+        "NUM   LABEL                           RESOLUTION            BUFFERS OPENERS"
+    }
+
+    /// One line summary of the configuration of device `device_num`, aligned with
+    /// [`DeviceConfig::table_header`].
+    ///
+    /// Buffers and openers left to v4l2loopback are shown as `auto`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{list_devices, query_device, DeviceConfig};
+    ///
+    /// println!("{}", DeviceConfig::table_header());
+    /// for device_num in list_devices().expect("Error when listing devices") {
+    ///     let config = query_device(device_num).expect("Error when querying the device");
+    ///     println!("{}", config.summary(device_num));
+    /// }
+    /// ```
+    pub fn summary(&self, device_num: u32) -> String {
+        let auto = |value: u32| match value {
+            0 => "auto".to_string(),
+            value => value.to_string(),
+        };
+        let resolution = format!(
+            "{}x{}-{}x{}",
+            self.min_width, self.min_height, self.max_width, self.max_height
+        );
+
+        format!(
+            "{:<5} {:<31} {:<21} {:>7} {:>7}",
+            device_num,
+            self.label,
+            resolution,
+            auto(self.max_buffers),
+            auto(self.max_openers)
+        )
+    }
 }
 
 impl TryInto<ffi::v4l2_loopback_config> for DeviceConfig {
@@ -129,12 +513,15 @@ impl TryInto<ffi::v4l2_loopback_config> for DeviceConfig {
     fn try_into(self) -> Result<ffi::v4l2_loopback_config, Self::Error> {
         let mut cfg = ffi::v4l2_loopback_config::default();
 
-        let mut slice: [i8; 32] = [0; 32];
-        unsafe { from_raw_parts(CString::new(self.label)?.as_ptr(), 32) }
-            .iter()
-            .enumerate()
-            .for_each(|(i, v)| slice[i] = *v);
-        cfg.card_label = slice;
+        // The label is kept NUL-terminated, so the driver never reads past the array
+        let label = CString::new(self.label)?;
+        let label = label.as_bytes();
+        if label.len() > device::LABEL_MAX_LEN {
+            return Err(format!("The label is longer than {} bytes", device::LABEL_MAX_LEN).into());
+        }
+        for (c, b) in cfg.card_label.iter_mut().zip(label) {
+            *c = *b as _;
+        }
 
         cfg.min_width = self.min_width;
         cfg.max_width = self.max_width;
@@ -142,6 +529,9 @@ impl TryInto<ffi::v4l2_loopback_config> for DeviceConfig {
         cfg.max_height = self.max_height;
         cfg.max_buffers = self.max_buffers.try_into()?;
         cfg.max_openers = self.max_openers.try_into()?;
+        cfg.announce_all_caps = self.announce_all_caps.into();
+        // This is a bitmask, so the bits are kept as is
+        cfg.debug = self.debug.bits() as i32;
 
         Ok(cfg)
     }
@@ -161,13 +551,14 @@ impl TryFrom<ffi::v4l2_loopback_config> for DeviceConfig {
             max_height,
             max_buffers,
             max_openers,
-            debug: _,
-            announce_all_caps: _,
+            debug,
+            announce_all_caps,
         } = value;
 
-        let label = unsafe { CStr::from_ptr(card_label.as_ptr()) }
-            .to_str()?
-            .to_string();
+        // A label using the whole array has no terminating NUL
+        let bytes = card_label.map(|c| c as u8);
+        let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        let label = std::str::from_utf8(&bytes[..len])?.to_string();
 
         Ok(Self {
             label,
@@ -175,8 +566,31 @@ impl TryFrom<ffi::v4l2_loopback_config> for DeviceConfig {
             max_width,
             min_height,
             max_height,
-            max_buffers: max_buffers.try_into()?,
+            // v4l2loopback treats negative values like 0, the default
+            max_buffers: max_buffers.try_into().unwrap_or(0),
             max_openers: max_openers.try_into()?,
+            announce_all_caps: announce_all_caps > 0,
+            debug: DebugFlags::from_bits_retain(debug as u32),
+        })
+    }
+}
+
+/// Informations about a device, as reported by v4l2loopback.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DeviceInfo {
+    /// The device number, `N` in `/dev/videoN`.
+    pub output_nr: u32,
+    /// The device configuration.
+    pub config: DeviceConfig,
+}
+
+impl TryFrom<ffi::v4l2_loopback_config> for DeviceInfo {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(value: ffi::v4l2_loopback_config) -> Result<Self, Self::Error> {
+        Ok(Self {
+            output_nr: value.output_nr.try_into()?,
+            config: DeviceConfig::try_from(value)?,
         })
     }
 }
@@ -196,21 +610,18 @@ pub enum ControlDeviceError {
     #[error("Can't find control device /dev/v4l2loopback, check if the kernel module is properly loaded")]
     NotFound,
 
+    /// The control device isn't a character device, so it can't be v4l2loopback.
+    /// This usually comes from a misconfigured udev rule.
+    #[error("Control device {} is not a character device", .0.display())]
+    NotACharacterDevice(PathBuf),
+
     /// An error resulting from trying to access the control device.
     #[error("Error when opening the control device: {0}")]
-    Other(Box<dyn std::error::Error>),
+    Other(#[source] Box<dyn std::error::Error>),
 }
 
-fn open_control_device() -> Result<RawFd, ControlDeviceError> {
-    match OpenOptions::new().read(true).open("/dev/v4l2loopback") {
-        Ok(f) => Ok(f.into_raw_fd()),
-        Err(e) => match e.kind() {
-            ErrorKind::NotFound => Err(ControlDeviceError::NotFound),
-            ErrorKind::PermissionDenied => Err(ControlDeviceError::PermissionDenied),
-            _ => Err(ControlDeviceError::Other(Box::new(e))),
-        },
-    }
-}
+/// Result returned by the functions of this crate, with [`Error`] as the default error.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// Error which can occure when calling a function from this crate
 #[derive(Debug, Error)]
@@ -228,10 +639,45 @@ pub enum Error {
     #[error("Failed to create device")]
     DeviceCreationFailed,
 
+    /// The v4l2loopback kernel module is not loaded.
+    #[error("The v4l2loopback kernel module is not loaded")]
+    ModuleNotLoaded,
+
     /// Couldn't find the specified device
     #[error("Device /dev/video{0} not found")]
     DeviceNotFound(u32),
 
+    /// The device still has consumers connected to it.
+    ///
+    /// Returned by operations that would disconnect them, like [`Device::recreate_with`].
+    #[error("Device /dev/video{0} is busy")]
+    DeviceBusy(u32),
+
+    /// The device number is used by another device.
+    ///
+    /// Returned by [`restart_device`] when another application created a device with the same
+    /// number while it was restarting.
+    #[error("Device number {0} is already taken")]
+    NumberTaken(u32),
+
+    /// The device number is used by a video device not managed by v4l2loopback, like a
+    /// webcam.
+    #[error("Device /dev/video{0} is not a v4l2loopback device")]
+    NumberIsHardware(u32),
+
+    /// The device can't be used as a video source.
+    ///
+    /// Returned by [`DeviceConfig::from_capture_device`] for v4l2loopback devices and devices
+    /// without capture capabilities.
+    #[error("Device /dev/video{0} is not a capture device")]
+    NotACaptureDevice(u32),
+
+    /// The loaded v4l2loopback module doesn't support this operation.
+    ///
+    /// This generally means the module is too old, the operation is described in the error.
+    #[error("Not supported by the loaded v4l2loopback module: {0}")]
+    Unsupported(&'static str),
+
     /// Unable to properly convert the config.
     ///
     /// Something went wrong when converting a [`DeviceConfig`] from/to the v4l2loopback device
@@ -240,18 +686,95 @@ pub enum Error {
     /// - The label containing null bytes
     /// - a too high value for `max_buffers` and `max_openers` (above [`i32::MAX`])
     #[error("Failed to convert device configuration: {0}")]
-    ConfigConversionError(Box<dyn std::error::Error>),
+    ConfigConversionError(#[source] Box<dyn std::error::Error>),
+
+    /// A field of a [`DeviceConfig`] has a value v4l2loopback rejects.
+    ///
+    /// Returned by [`add_device`] when v4l2loopback refuses the configuration, to point at the
+    /// faulty field.
+    #[error("Invalid value for {0:?} in the device configuration")]
+    InvalidConfig(ConfigField),
+
+    /// The label is longer than the 31 bytes v4l2loopback keeps, with its length in bytes.
+    ///
+    /// Returned by [`Device::set_label`] before touching the device, and by [`add_device`]
+    /// when v4l2loopback rejects a configuration because of its label.
+    #[error("The label is {0} bytes long, v4l2loopback only keeps 31")]
+    LabelTooLong(usize),
+
+    /// A frame doesn't have the size expected by the device.
+    ///
+    /// Returned by [`FrameWriter`] when a frame doesn't match the format of the device, or
+    /// doesn't fit in its buffers.
+    #[error("The frame is {actual} bytes long instead of {expected}")]
+    FrameSize {
+        /// Size expected by the device, in bytes.
+        expected: usize,
+        /// Size of the frame, in bytes.
+        actual: usize,
+    },
+
+    /// An operation didn't complete in time, see [`Settings::ioctl_timeout`].
+    #[error("Operation timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// A spec string is malformed, see [`DeviceConfig::try_from`].
+    #[error("Invalid spec: {0}")]
+    InvalidSpec(#[from] SpecParseError),
 
     /// Any other error
     #[error(transparent)]
     Other(Box<dyn std::error::Error>),
 }
 
+impl Error {
+    /// A short hint on how to fix the error, for the most common failures.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{add_device, DeviceConfig};
+    ///
+    /// if let Err(e) = add_device(None, DeviceConfig::default()) {
+    ///     match e.advice() {
+    ///         Some(advice) => eprintln!("{} ({})", e, advice),
+    ///         None => eprintln!("{}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn advice(&self) -> Option<&'static str> {
+        const PERMISSIONS: &str = "run as root or adjust device permissions";
+        const RELOAD: &str = "reload the module";
+        const BUSY: &str = "a consumer still has the device open";
+
+        match self {
+            Error::ControlDevice(ControlDeviceError::PermissionDenied) => Some(PERMISSIONS),
+            Error::ControlDevice(ControlDeviceError::NotFound) | Error::ModuleNotLoaded => {
+                Some("load the module with `modprobe v4l2loopback`")
+            }
+            Error::Ioctl(Errno::EPERM | Errno::EACCES) => Some(PERMISSIONS),
+            Error::Ioctl(Errno::ENODEV) => Some(RELOAD),
+            Error::Ioctl(Errno::EBUSY) | Error::DeviceBusy(_) => Some(BUSY),
+            Error::InvalidConfig(_) | Error::LabelTooLong(_) => {
+                Some("fix the device configuration")
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Create a new v4l2loopback device.
 ///
 /// If you pass [`None`] to `num`, the device will be created using the next available device
 /// number.
 ///
+/// A [`max_width`](DeviceConfig::max_width) or [`max_height`](DeviceConfig::max_height) of 0
+/// is replaced by the default maximum of the module, so [`DeviceConfig::default`] creates a
+/// usable device.
+///
+/// In [dry run](Settings::dry_run) mode, the device is not created and the number it would
+/// likely get is returned.
+///
 /// This function returns a result containing the device number is it is [`Ok`], and one of the
 /// following error if it is [`Err`].
 ///
@@ -260,14 +783,27 @@ pub enum Error {
 /// This function will return the following errors:
 /// - [`ConfigConversionError`] if the label given in `config` contains null bytes.
 /// - [`ControlDevice`] if it is unable to open the control device
-/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Ioctl`] if the underlying ioctl call fails. For `EINVAL`, the configuration looked
+///   valid but v4l2loopback rejected it anyway, which can happen when the loaded module
+///   doesn't match the version this crate was built against.
+/// - [`InvalidConfig`] if v4l2loopback rejected the configuration, with the faulty field
+///   (like a `min_width` greater than `max_width`).
+/// - [`LabelTooLong`] if the label is longer than the 31 bytes v4l2loopback keeps.
+/// - [`NumberIsHardware`] if `num` is the number of a video device not managed by
+///   v4l2loopback, like a webcam.
 /// - [`DeviceCreationFailed`] if v4l2loopback was unable to create a device. This generally
-/// happens when you specify an explicit number in `num`.
+///   happens when you specify an explicit number in `num`, see [`try_add_device`] if you need
+///   to know which device is using it.
+/// - [`Timeout`] if [`Settings::ioctl_timeout`] is set and the creation takes longer.
 ///
 /// [`ConfigConversionError`]: Error::ConfigConversionError
 /// [`ControlDevice`]: Error::ControlDevice
 /// [`Ioctl`]: Error::Ioctl
+/// [`InvalidConfig`]: Error::InvalidConfig
+/// [`LabelTooLong`]: Error::LabelTooLong
+/// [`NumberIsHardware`]: Error::NumberIsHardware
 /// [`DeviceCreationFailed`]: Error::DeviceCreationFailed
+/// [`Timeout`]: Error::Timeout
 ///
 /// # Example
 ///
@@ -283,14 +819,75 @@ pub enum Error {
 /// delete_device(device_num.try_into().unwrap()).expect("Error when removing device");
 /// assert!(!Path::new(&format!("/dev/video{}", device_num)).exists());
 /// ```
-pub fn add_device(num: Option<u32>, config: DeviceConfig) -> Result<u32, Error> {
-    let mut cfg: ffi::v4l2_loopback_config = match config.try_into() {
+pub fn add_device(num: Option<u32>, config: DeviceConfig) -> Result<u32> {
+    add_device_with(&settings(), open_control_device, num, config)
+}
+
+/// Convert an error from `V4L2LOOPBACK_CTL_ADD` for `config`.
+///
+/// v4l2loopback rejects invalid configurations with EINVAL, without telling which field is
+/// wrong, so the configuration is checked again to find it.
+fn add_error(e: Errno, config: &DeviceConfig) -> Error {
+    if e != Errno::EINVAL {
+        return Error::Ioctl(e);
+    }
+
+    match config.invalid_field() {
+        Some(field) => Error::InvalidConfig(field),
+        None if config.label.len() > device::LABEL_MAX_LEN => {
+            Error::LabelTooLong(config.label.len())
+        }
+        None => {
+            log::warn!(
+                "v4l2loopback rejected a configuration which looks valid, the loaded module may \
+                 not match the version this crate was built against"
+            );
+            Error::Ioctl(e)
+        }
+    }
+}
+
+pub(crate) fn add_device_with(
+    settings: &Settings,
+    control: impl FnOnce() -> Result<ControlFd, ControlDeviceError>,
+    num: Option<u32>,
+    config: DeviceConfig,
+) -> Result<u32> {
+    if config.label.len() > device::LABEL_MAX_LEN {
+        return Err(Error::LabelTooLong(config.label.len()));
+    }
+
+    let config = module::with_default_bounds_in(Path::new(module::MODULE_ROOT), config);
+    let label = config.label.clone();
+    let mut cfg: ffi::v4l2_loopback_config = match config.clone().try_into() {
         Ok(cfg) => cfg,
         Err(e) => return Err(Error::ConfigConversionError(e)),
     };
+
+    if let Some(num) = num {
+        if Path::new(&format!("/dev/video{}", num)).exists()
+            && query_capabilities(num).is_ok_and(|caps| !caps.is_loopback())
+        {
+            return Err(Error::NumberIsHardware(num));
+        }
+    }
+
+    if settings.dry_run {
+        let num = match num {
+            Some(num) => num,
+            None => next_free_device_numbers(1)?[0],
+        };
+        log::info!(
+            "[dry run] Would create device /dev/video{} labeled \"{}\"",
+            num,
+            label
+        );
+        return Ok(num);
+    }
+
     cfg.output_nr = num.map(i32::try_from).and_then(Result::ok).unwrap_or(-1);
 
-    let fd = open_control_device()?;
+    let fd = control()?;
 
     ioctl_readwrite_bad!(
         v4l2loopback_ctl_add,
@@ -298,7 +895,12 @@ pub fn add_device(num: Option<u32>, config: DeviceConfig) -> Result<u32, Error>
         ffi::v4l2_loopback_config
     );
 
-    let dev = unsafe { v4l2loopback_ctl_add(fd, &mut cfg as *mut ffi::v4l2_loopback_config) }?;
+    let dev = match with_timeout(settings.ioctl_timeout, &fd, move |fd| unsafe {
+        v4l2loopback_ctl_add(fd, &mut cfg as *mut ffi::v4l2_loopback_config)
+    })? {
+        Ok(dev) => dev,
+        Err(e) => return Err(add_error(e, &config)),
+    };
 
     if dev.is_negative() {
         return Err(Error::DeviceCreationFailed);
@@ -307,22 +909,84 @@ pub fn add_device(num: Option<u32>, config: DeviceConfig) -> Result<u32, Error>
     Ok(dev as u32)
 }
 
+/// Error returned by [`try_add_device`].
+#[derive(Debug, Error)]
+pub enum AddConflict {
+    /// The requested number is already used by another v4l2loopback device.
+    #[error("Device number already taken by \"{}\"", existing.label)]
+    NumberTaken {
+        /// Configuration of the device using the requested number.
+        existing: DeviceConfig,
+    },
+
+    /// The device couldn't be created for another reason.
+    #[error(transparent)]
+    Other(#[from] Error),
+}
+
+/// Create a new v4l2loopback device with an explicit number, reporting conflicts in details.
+///
+/// This works like [`add_device`], but when the number is already used by a v4l2loopback
+/// device, the configuration of that device is returned in [`AddConflict::NumberTaken`].
+/// This lets you decide whether to reuse the existing device or to pick another number.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`NumberTaken`] if a v4l2loopback device already uses `num`.
+/// - [`Other`] with any error [`add_device`] can return otherwise.
+///
+/// [`NumberTaken`]: AddConflict::NumberTaken
+/// [`Other`]: AddConflict::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, try_add_device, AddConflict, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     label: "First Device".to_string(),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// match try_add_device(device_num, DeviceConfig::default()) {
+///     Err(AddConflict::NumberTaken { existing }) => assert_eq!(existing.label, "First Device"),
+///     _ => panic!("The number should be taken"),
+/// }
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn try_add_device(num: u32, config: DeviceConfig) -> Result<u32, AddConflict> {
+    match add_device(Some(num), config) {
+        Ok(num) => Ok(num),
+        Err(e) => match query_device(num) {
+            Ok(existing) => Err(AddConflict::NumberTaken { existing }),
+            Err(_) => Err(AddConflict::Other(e)),
+        },
+    }
+}
+
 /// Delete a v4l2loopback device.
 ///
 /// Given the device number, this function will attempt to delete thev4l2loopback device.
 ///
+/// In [dry run](Settings::dry_run) mode, nothing is deleted.
+///
 /// # Errors
 ///
 /// This function will return the following errors:
 /// - [`ControlDevice`] if it is unable to open the control device
 /// - [`Ioctl`] if the underlying ioctl call fails
 /// - [`DeviceNotFound`] if the specified device is not recognized by v4l2loopback.
+/// - [`Timeout`] if [`Settings::ioctl_timeout`] is set and the removal takes longer.
 /// - [`Other`] for other errors
 ///
 /// [`ControlDevice`]: Error::ControlDevice
 /// [`Ioctl`]: Error::Ioctl
 /// [`DeviceNotFound`]: Error::DeviceNotFound
 /// [`Other`]: Error::Other
+/// [`Timeout`]: Error::Timeout
 ///
 /// # Example
 ///
@@ -340,8 +1004,21 @@ pub fn add_device(num: Option<u32>, config: DeviceConfig) -> Result<u32, Error>
 /// delete_device(device_num.try_into().unwrap()).expect("Error when removing device");
 /// assert!(!Path::new(&format!("/dev/video{}", device_num)).exists());
 /// ```
-pub fn delete_device(device_num: u32) -> Result<(), Error> {
-    let fd = open_control_device()?;
+pub fn delete_device(device_num: u32) -> Result<()> {
+    delete_device_with(&settings(), open_control_device, device_num)
+}
+
+pub(crate) fn delete_device_with(
+    settings: &Settings,
+    control: impl FnOnce() -> Result<ControlFd, ControlDeviceError>,
+    device_num: u32,
+) -> Result<()> {
+    if settings.dry_run {
+        log::info!("[dry run] Would delete device /dev/video{}", device_num);
+        return Ok(());
+    }
+
+    let fd = control()?;
 
     let converted_num = match device_num.try_into() {
         Ok(n) => n,
@@ -350,7 +1027,9 @@ pub fn delete_device(device_num: u32) -> Result<(), Error> {
 
     ioctl_write_int_bad!(v4l2loopback_ctl_remove, ffi::V4L2LOOPBACK_CTL_REMOVE);
 
-    let res = unsafe { v4l2loopback_ctl_remove(fd, converted_num) }?;
+    let res = with_timeout(settings.ioctl_timeout, &fd, move |fd| unsafe {
+        v4l2loopback_ctl_remove(fd, converted_num)
+    })??;
 
     if res.is_negative() {
         return Err(Error::DeviceNotFound(device_num));
@@ -359,6 +1038,45 @@ pub fn delete_device(device_num: u32) -> Result<(), Error> {
     Ok(())
 }
 
+/// Delete a v4l2loopback device, succeeding if it doesn't exist.
+///
+/// This is [`delete_device`], except that a device deleted in the meantime, for example by
+/// another process tearing down the same setup, counts as deleted, since that was the goal.
+///
+/// # Errors
+///
+/// This function will return the same errors as [`delete_device`], other than
+/// [`DeviceNotFound`] and [`Ioctl`] with [`ENODEV`]. In particular, a device still opened by
+/// someone fails with [`Ioctl`] and [`EBUSY`].
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`ENODEV`]: Errno::ENODEV
+/// [`EBUSY`]: Errno::EBUSY
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device_idempotent, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// delete_device_idempotent(device_num).expect("Error when removing device");
+/// // Already deleted, nothing to do
+/// delete_device_idempotent(device_num).expect("Error when removing device");
+/// ```
+pub fn delete_device_idempotent(device_num: u32) -> Result<()> {
+    ignore_missing(delete_device(device_num))
+}
+
+/// Turn the result of a deletion into a success if the device didn't exist.
+fn ignore_missing(res: Result<()>) -> Result<()> {
+    match res {
+        Err(Error::DeviceNotFound(_) | Error::Ioctl(Errno::ENODEV)) => Ok(()),
+        res => res,
+    }
+}
+
 /// Queries the configuration for a specified device.
 ///
 /// Given the device number, this function will fetch the corresponding device configuration
@@ -372,20 +1090,24 @@ pub fn delete_device(device_num: u32) -> Result<(), Error> {
 /// - [`ControlDevice`] if it is unable to open the control device
 /// - [`Ioctl`] if the underlying ioctl call fails
 /// - [`DeviceNotFound`] if the specified device is not recognized by v4l2loopback.
+/// - [`Unsupported`] if the loaded v4l2loopback module can't query devices.
+/// - [`Timeout`] if [`Settings::ioctl_timeout`] is set and the query takes longer.
 /// - [`ConfigConversionError`] if the label returned by v4l2loopback contains null bytes.
 /// - [`Other`] for other errors
 ///
 /// [`ControlDevice`]: Error::ControlDevice
 /// [`Ioctl`]: Error::Ioctl
 /// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Unsupported`]: Error::Unsupported
 /// [`ConfigConversionError`]: Error::ConfigConversionError
 /// [`Other`]: Error::Other
+/// [`Timeout`]: Error::Timeout
 ///
 /// # Example
 ///
 /// ```
 /// use std::path::Path;
-/// use v4l2loopback::{add_device, delete_device, query_device, DeviceConfig};
+/// use v4l2loopback::{add_device, delete_device, query_device, DebugFlags, DeviceConfig};
 ///
 /// // We specify our desired config
 /// let device_config = DeviceConfig {
@@ -396,6 +1118,8 @@ pub fn delete_device(device_num: u32) -> Result<(), Error> {
 ///     max_height: 4000,
 ///     max_buffers: 9,
 ///     max_openers: 3,
+///     announce_all_caps: false,
+///     debug: DebugFlags::empty(),
 /// };
 /// // Device creation
 /// let device_num =
@@ -411,7 +1135,26 @@ pub fn delete_device(device_num: u32) -> Result<(), Error> {
 /// delete_device(device_num).expect("Error when removing device");
 /// assert!(!Path::new(&format!("/dev/video{}", device_num)).exists());
 /// ```
-pub fn query_device(device_num: u32) -> Result<DeviceConfig, Error> {
+pub fn query_device(device_num: u32) -> Result<DeviceConfig> {
+    Ok(query_device_info(device_num)?.config)
+}
+
+/// Map the errno of a failed query to an error.
+///
+/// v4l2loopback fails with `ENODEV` when no loopback device has this number, while modules
+/// without the query ioctl fail with `ENOSYS` (or `ENOTTY`). Anything else is a real failure.
+fn query_error(e: Errno, device_num: u32) -> Error {
+    match e {
+        Errno::ENODEV => Error::DeviceNotFound(device_num),
+        Errno::ENOSYS | Errno::ENOTTY => Error::Unsupported("querying devices"),
+        e => Error::Ioctl(e),
+    }
+}
+
+pub(crate) fn query_device_with(
+    control: impl FnOnce() -> Result<ControlFd, ControlDeviceError>,
+    device_num: u32,
+) -> Result<DeviceInfo> {
     let mut cfg = ffi::v4l2_loopback_config {
         output_nr: match device_num.try_into() {
             Ok(n) => n,
@@ -420,7 +1163,7 @@ pub fn query_device(device_num: u32) -> Result<DeviceConfig, Error> {
         ..Default::default()
     };
 
-    let fd = open_control_device()?;
+    let fd = control()?;
 
     ioctl_read_bad!(
         v4l2loopback_ctl_query,
@@ -428,25 +1171,254 @@ pub fn query_device(device_num: u32) -> Result<DeviceConfig, Error> {
         ffi::v4l2_loopback_config
     );
 
-    let res = unsafe { v4l2loopback_ctl_query(fd, &mut cfg as *mut ffi::v4l2_loopback_config) }?;
+    let (res, cfg) = with_timeout(settings().ioctl_timeout, &fd, move |fd| {
+        let res = unsafe { v4l2loopback_ctl_query(fd, &mut cfg as *mut ffi::v4l2_loopback_config) };
+        (res, cfg)
+    })?;
+    if let Err(e) = res {
+        return Err(query_error(e, device_num));
+    }
 
-    if res.is_negative() {
-        return Err(Error::DeviceNotFound(device_num));
+    match DeviceInfo::try_from(cfg) {
+        Ok(info) => Ok(info),
+        Err(e) => Err(Error::ConfigConversionError(e)),
     }
+}
 
-    let device_config = match DeviceConfig::try_from(cfg) {
-        Ok(cfg) => cfg,
-        Err(e) => return Err(Error::ConfigConversionError(e)),
-    };
+/// Queries the configuration for a specified device, along with the device number reported by
+/// v4l2loopback.
+///
+/// This is the same as [`query_device`], but keeps the `output_nr` returned by v4l2loopback,
+/// which can be used to confirm which device was queried.
+///
+/// # Errors
+///
+/// See [`query_device`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, query_device_info, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let info = query_device_info(device_num).expect("Error when querying the device");
+/// assert_eq!(info.output_nr, device_num);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn query_device_info(device_num: u32) -> Result<DeviceInfo> {
+    query_device_with(open_control_device, device_num)
+}
 
-    Ok(device_config)
+/// List the numbers of all v4l2loopback devices, in ascending order.
+///
+/// Video devices not managed by v4l2loopback (like real webcams) are left out.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`ControlDevice`] if it is unable to open the control device
+/// - [`Ioctl`] if querying one of the video devices fails
+/// - [`Unsupported`] if the loaded v4l2loopback module can't query devices
+/// - [`Other`] if the video devices couldn't be listed from sysfs
+///
+/// Video devices which are not managed by v4l2loopback are skipped.
+///
+/// [`ControlDevice`]: Error::ControlDevice
+/// [`Ioctl`]: Error::Ioctl
+/// [`Unsupported`]: Error::Unsupported
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, list_devices, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let devices = list_devices().expect("Error when listing devices");
+/// assert!(devices.contains(&device_num));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn list_devices() -> Result<Vec<u32>> {
+    let mut devices = Vec::new();
+    for device_num in sysfs::video_numbers(Path::new(sysfs::VIDEO4LINUX_ROOT))? {
+        match query_device(device_num) {
+            Ok(_) => devices.push(device_num),
+            // Not a v4l2loopback device
+            Err(Error::DeviceNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Delete every v4l2loopback device whose label starts with `prefix`, returning the numbers
+/// of the deleted devices.
+///
+/// This is meant to clean up the devices left behind by an application which crashed, when
+/// all its labels share a prefix (see also [`DeviceLabel`]). Devices of other applications are
+/// left alone, as long as their labels don't start with the same prefix. An empty prefix
+/// matches every device.
+///
+/// Devices opened by someone can't be deleted, they are skipped and logged as warnings.
+/// Devices deleted by someone else in the meantime are skipped too.
+///
+/// # Errors
+///
+/// This function will return any error from [`list_devices`], and any error from
+/// [`query_device`] or [`delete_device`] other than [`DeviceNotFound`] and the device being
+/// busy. Devices deleted before the error stay deleted.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_devices_by_label_prefix, DeviceConfig};
+///
+/// let config = DeviceConfig {
+///     label: "MyApp camera".to_string(),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, config).expect("Error when creating the device");
+///
+/// // After a crash, on the next start
+/// let deleted = delete_devices_by_label_prefix("MyApp ").expect("Error when removing devices");
+/// assert!(deleted.contains(&device_num));
+/// ```
+pub fn delete_devices_by_label_prefix(prefix: &str) -> Result<Vec<u32>> {
+    let mut deleted = Vec::new();
+    for device_num in list_devices()? {
+        let config = match query_device(device_num) {
+            Ok(config) => config,
+            Err(Error::DeviceNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        if !config.label.starts_with(prefix) {
+            continue;
+        }
+
+        match delete_device(device_num) {
+            Ok(()) => deleted.push(device_num),
+            Err(Error::DeviceNotFound(_) | Error::Ioctl(Errno::ENODEV)) => {}
+            Err(Error::Ioctl(Errno::EBUSY) | Error::DeviceBusy(_)) => log::warn!(
+                "Not deleting /dev/video{} labeled \"{}\": the device is busy",
+                device_num,
+                config.label
+            ),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Memory used by the buffers of a device with this configuration, in bytes, when frames
+/// are in the pixel format of `format`.
+///
+/// This is the worst case, each of the [`max_buffers`](DeviceConfig::max_buffers) holding a
+/// frame of [`max_width`](DeviceConfig::max_width) by
+/// [`max_height`](DeviceConfig::max_height), which lets you estimate the memory needed before
+/// creating many devices. Fields left to 0 are picked by v4l2loopback and count as 0, so
+/// use the configuration returned by [`query_device`] to get the actual footprint.
+///
+/// Returns 0 for [unknown](PixelFormat::Unknown) pixel formats.
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{device_memory_footprint, DeviceConfig, Format, PixelFormat, Resolution};
+///
+/// let config = DeviceConfig {
+///     max_width: 1920,
+///     max_height: 1080,
+///     max_buffers: 4,
+///     ..DeviceConfig::default()
+/// };
+/// let format = Format::new(PixelFormat::Yuyv, Resolution::new(1920, 1080));
+/// assert_eq!(device_memory_footprint(&config, &format), 4 * 1920 * 1080 * 2);
+/// ```
+pub fn device_memory_footprint(config: &DeviceConfig, format: &Format) -> usize {
+    let frame_size = format
+        .pixel_format
+        .image_size(config.max_width, config.max_height)
+        .unwrap_or(0);
+
+    frame_size.saturating_mul(config.max_buffers as usize)
+}
+
+/// List the numbers used by video devices, in ascending order.
+///
+/// A number is used as soon as `/dev/videoN` exists, whether the device is managed by
+/// v4l2loopback or not.
+///
+/// # Errors
+///
+/// This function will return [`Other`] if `/dev` couldn't be listed.
+///
+/// [`Other`]: Error::Other
+pub fn used_device_numbers() -> Result<Vec<u32>> {
+    sysfs::video_numbers(Path::new("/dev"))
+}
+
+/// Lowest `count` numbers not in `used`, which must be sorted in ascending order.
+pub(crate) fn free_numbers(used: &[u32], count: usize) -> Vec<u32> {
+    let mut used = used.iter().peekable();
+
+    (0..=u32::MAX)
+        .filter(|num| {
+            while used.next_if(|used| *used < num).is_some() {}
+            used.next_if_eq(&num).is_none()
+        })
+        .take(count)
+        .collect()
+}
+
+/// Compute the lowest `count` device numbers which are currently unused.
+///
+/// This doesn't create anything, it is meant to plan the creation of several devices at once.
+/// The numbers are not reserved, so another application could take one of them before you
+/// create your devices: use [`try_add_device`] to handle this case.
+///
+/// See [`used_device_numbers`] for what makes a number used.
+///
+/// # Errors
+///
+/// See [`used_device_numbers`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{delete_device, next_free_device_numbers, try_add_device, DeviceConfig};
+///
+/// let numbers = next_free_device_numbers(2).expect("Error when listing devices");
+/// assert_eq!(numbers.len(), 2);
+///
+/// for num in numbers {
+///     try_add_device(num, DeviceConfig::default()).expect("Error when creating the device");
+///     delete_device(num).expect("Error when removing device");
+/// }
+/// ```
+pub fn next_free_device_numbers(count: usize) -> Result<Vec<u32>> {
+    Ok(free_numbers(&used_device_numbers()?, count))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use std::{io, num::NonZeroU32, path::Path};
 
-    use crate::{add_device, delete_device};
+    use crate::{
+        add_device, add_error, delete_device, delete_device_idempotent,
+        delete_devices_by_label_prefix, device_memory_footprint, ffi, free_numbers, ignore_missing,
+        query_capabilities, query_device, query_device_info, query_error, set_format,
+        try_add_device, used_device_numbers, AddConflict, ClampedField, ConfigField,
+        ControlDeviceError, DebugFlags, DeviceConfig, Errno, Error, FieldChange, Format,
+        PixelFormat, Resolution,
+    };
 
     #[test]
     fn device_with_num() {
@@ -483,4 +1455,450 @@ mod tests {
             assert!(!Path::new("/dev/video0").exists());
         }
     }
+
+    #[test]
+    fn hardware_number_guard() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        assert!(matches!(
+            add_device(Some(device_num), Default::default()),
+            Err(Error::DeviceCreationFailed | Error::Ioctl(_))
+        ));
+        delete_device(device_num).expect("Error when removing device");
+
+        // Only possible with a real camera plugged in
+        let numbers = used_device_numbers().expect("Error when listing devices");
+        let hardware = numbers
+            .into_iter()
+            .find(|num| query_capabilities(*num).is_ok_and(|caps| !caps.is_loopback()));
+        if let Some(num) = hardware {
+            assert!(matches!(
+                add_device(Some(num), Default::default()),
+                Err(Error::NumberIsHardware(n)) if n == num
+            ));
+        }
+    }
+
+    #[test]
+    fn try_add_conflict() {
+        let config = DeviceConfig {
+            label: "Conflicting Device".to_string(),
+            ..Default::default()
+        };
+        let device_num = add_device(None, config.clone()).expect("Error when creating the device");
+
+        let res = try_add_device(device_num, Default::default());
+        match res {
+            Err(AddConflict::NumberTaken { existing }) => assert_eq!(existing.label, config.label),
+            other => panic!("Expected a conflict, got {:?}", other),
+        }
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn default_and_explicit_buffers() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let cfg = query_device(device_num).expect("Error when querying the device");
+        // v4l2loopback reports the number of buffers it picked
+        assert!(cfg.buffers().is_some());
+        delete_device(device_num).expect("Error when removing device");
+
+        let config = DeviceConfig {
+            max_buffers: 5,
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+        let cfg = query_device(device_num).expect("Error when querying the device");
+        assert_eq!(cfg.buffers(), NonZeroU32::new(5));
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn default_bounds_device() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let cfg = query_device(device_num).expect("Error when querying the device");
+        assert!(cfg.max_width > 0 && cfg.max_height > 0);
+
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(640, 480));
+        let set = set_format(device_num, &format).expect("Error when setting the format");
+        assert_eq!(set.resolution, format.resolution);
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn delete_by_label_prefix() {
+        let labeled = |label: &str| DeviceConfig {
+            label: label.to_string(),
+            ..Default::default()
+        };
+        let first =
+            add_device(None, labeled("prefix-test first")).expect("Error when creating the device");
+        let second = add_device(None, labeled("prefix-test second"))
+            .expect("Error when creating the device");
+        let other =
+            add_device(None, labeled("other prefix-test")).expect("Error when creating the device");
+
+        let mut deleted =
+            delete_devices_by_label_prefix("prefix-test ").expect("Error when removing devices");
+        deleted.sort();
+        assert_eq!(deleted, [first.min(second), first.max(second)]);
+        assert!(query_device(other).is_ok());
+
+        delete_device(other).expect("Error when removing device");
+    }
+
+    #[test]
+    fn negative_buffers_conversion() {
+        let cfg = ffi::v4l2_loopback_config {
+            max_buffers: -1,
+            ..Default::default()
+        };
+        let config = DeviceConfig::try_from(cfg).expect("Error when converting the config");
+        assert_eq!(config.buffers(), None);
+    }
+
+    #[test]
+    fn full_length_label() {
+        let cfg = ffi::v4l2_loopback_config {
+            card_label: [b'a' as _; 32],
+            ..Default::default()
+        };
+        let config = DeviceConfig::try_from(cfg).expect("Error when converting the config");
+        assert_eq!(config.label, "a".repeat(32));
+
+        let mut card_label = [0; 32];
+        card_label[..6].copy_from_slice(&b"Camera".map(|b| b as _));
+        let cfg = ffi::v4l2_loopback_config {
+            card_label,
+            ..Default::default()
+        };
+        let config = DeviceConfig::try_from(cfg).expect("Error when converting the config");
+        assert_eq!(config.label, "Camera");
+    }
+
+    #[test]
+    fn label_into_config() {
+        let to_ffi = |label: &str| -> Result<ffi::v4l2_loopback_config, _> {
+            DeviceConfig {
+                label: label.to_string(),
+                ..Default::default()
+            }
+            .try_into()
+        };
+
+        let cfg = to_ffi("Camera").expect("Error when converting the config");
+        let mut card_label = [0; 32];
+        card_label[..6].copy_from_slice(&b"Camera".map(|b| b as _));
+        assert_eq!(cfg.card_label, card_label);
+
+        // The longest label still ends with a NUL
+        let cfg = to_ffi(&"a".repeat(31)).expect("Error when converting the config");
+        assert_eq!(cfg.card_label[31], 0);
+
+        assert!(to_ffi(&"a".repeat(32)).is_err());
+        assert!(to_ffi("Cam\0era").is_err());
+
+        // Rejected before reaching the driver
+        let long_label = DeviceConfig {
+            label: "a".repeat(32),
+            ..Default::default()
+        };
+        assert!(matches!(
+            add_device(None, long_label),
+            Err(Error::LabelTooLong(32))
+        ));
+    }
+
+    #[test]
+    fn errors_advice() {
+        let permissions = Some("run as root or adjust device permissions");
+        assert_eq!(Error::Ioctl(Errno::EPERM).advice(), permissions);
+        assert_eq!(
+            Error::ControlDevice(ControlDeviceError::PermissionDenied).advice(),
+            permissions
+        );
+        assert_eq!(
+            Error::Ioctl(Errno::ENODEV).advice(),
+            Some("reload the module")
+        );
+        assert_eq!(
+            Error::Ioctl(Errno::EBUSY).advice(),
+            Some("a consumer still has the device open")
+        );
+        assert_eq!(
+            Error::DeviceBusy(0).advice(),
+            Error::Ioctl(Errno::EBUSY).advice()
+        );
+        assert_eq!(Error::Ioctl(Errno::EINVAL).advice(), None);
+        assert_eq!(Error::DeviceNotFound(0).advice(), None);
+    }
+
+    #[test]
+    fn idempotent_deletion() {
+        assert!(ignore_missing(Err(Error::DeviceNotFound(3))).is_ok());
+        assert!(ignore_missing(Err(Error::Ioctl(Errno::ENODEV))).is_ok());
+        assert!(matches!(
+            ignore_missing(Err(Error::Ioctl(Errno::EBUSY))),
+            Err(Error::Ioctl(Errno::EBUSY))
+        ));
+
+        let used = used_device_numbers().expect("Error when listing used numbers");
+        let unused = free_numbers(&used, 1)[0];
+        delete_device_idempotent(unused).expect("Error when removing a missing device");
+    }
+
+    #[test]
+    fn query_errors() {
+        // Not a loopback device
+        assert!(matches!(
+            query_error(Errno::ENODEV, 3),
+            Error::DeviceNotFound(3)
+        ));
+        let used = used_device_numbers().expect("Error when listing used numbers");
+        let unused = free_numbers(&used, 1)[0];
+        assert!(matches!(
+            query_device(unused),
+            Err(Error::DeviceNotFound(n)) if n == unused
+        ));
+
+        // Query not supported by the module
+        assert!(matches!(
+            query_error(Errno::ENOSYS, 3),
+            Error::Unsupported(_)
+        ));
+        assert!(matches!(
+            query_error(Errno::ENOTTY, 3),
+            Error::Unsupported(_)
+        ));
+
+        // Real failures are kept
+        assert!(matches!(
+            query_error(Errno::EPERM, 3),
+            Error::Ioctl(Errno::EPERM)
+        ));
+    }
+
+    #[test]
+    fn add_errors() {
+        let bad_dimensions = DeviceConfig {
+            min_width: 1920,
+            max_width: 640,
+            ..Default::default()
+        };
+        assert!(matches!(
+            add_error(Errno::EINVAL, &bad_dimensions),
+            Error::InvalidConfig(ConfigField::MinWidth)
+        ));
+
+        // Rejected by the driver even though it looks valid
+        assert!(matches!(
+            add_error(Errno::EINVAL, &DeviceConfig::default()),
+            Error::Ioctl(Errno::EINVAL)
+        ));
+        assert!(matches!(
+            add_error(Errno::EPERM, &bad_dimensions),
+            Error::Ioctl(Errno::EPERM)
+        ));
+
+        // Rejected by the driver because of the label
+        let long_label = DeviceConfig {
+            label: "a".repeat(40),
+            ..Default::default()
+        };
+        assert!(matches!(
+            add_error(Errno::EINVAL, &long_label),
+            Error::LabelTooLong(40)
+        ));
+
+        assert!(matches!(
+            add_device(None, bad_dimensions),
+            Err(Error::InvalidConfig(ConfigField::MinWidth))
+        ));
+    }
+
+    #[test]
+    fn boxed_errors_source() {
+        fn reaches_io_error(error: &dyn std::error::Error) -> bool {
+            let mut source = error.source();
+            while let Some(error) = source {
+                if error.is::<io::Error>() {
+                    return true;
+                }
+                source = error.source();
+            }
+            false
+        }
+        let io_error = || Box::new(io::Error::other("underlying"));
+
+        let control = Error::ControlDevice(ControlDeviceError::Other(io_error()));
+        assert!(reaches_io_error(&control));
+
+        let conversion = Error::ConfigConversionError(io_error());
+        assert!(reaches_io_error(&conversion));
+
+        let conflict = AddConflict::Other(Error::ConfigConversionError(io_error()));
+        assert!(reaches_io_error(&conflict));
+
+        // Transparent, so it is displayed as the underlying error itself
+        let other = Error::Other(io_error());
+        assert_eq!(other.to_string(), "underlying");
+    }
+
+    #[test]
+    fn queried_output_nr() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+
+        let info = query_device_info(device_num).expect("Error when querying the device");
+        assert_eq!(info.output_nr, device_num);
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn free_numbers_around_used() {
+        assert_eq!(free_numbers(&[], 3), vec![0, 1, 2]);
+        assert_eq!(free_numbers(&[0, 1, 3, 6], 4), vec![2, 4, 5, 7]);
+        assert_eq!(free_numbers(&[1, 2], 0), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn config_summary() {
+        let config = DeviceConfig {
+            label: "Dummy video device".to_string(),
+            min_width: 48,
+            max_width: 1920,
+            min_height: 32,
+            max_height: 1080,
+            max_buffers: 4,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            DeviceConfig::table_header(),
+            "NUM   LABEL                           RESOLUTION            BUFFERS OPENERS"
+        );
+        assert_eq!(
+            config.summary(12),
+            "12    Dummy video device              48x32-1920x1080             4    auto"
+        );
+        assert_eq!(config.summary(12).len(), DeviceConfig::table_header().len());
+    }
+
+    #[test]
+    fn debug_flags_round_trip() {
+        let config = DeviceConfig {
+            debug: DebugFlags::IOCTL | DebugFlags::CAPS,
+            ..Default::default()
+        };
+
+        let cfg: ffi::v4l2_loopback_config = config.clone().try_into().unwrap();
+        assert_eq!(cfg.debug, 0b101);
+        assert_eq!(DeviceConfig::try_from(cfg).unwrap(), config);
+
+        assert_eq!(DebugFlags::default(), DebugFlags::empty());
+        assert!(DebugFlags::all().contains(DebugFlags::IOCTL | DebugFlags::FILEIO));
+    }
+
+    #[test]
+    fn clamped_buffers() {
+        let requested = DeviceConfig {
+            max_buffers: 1000,
+            ..Default::default()
+        };
+        let device_num =
+            add_device(None, requested.clone()).expect("Error when creating the device");
+
+        let cfg = query_device(device_num).expect("Error when querying the device");
+        let clamped = cfg.was_clamped(&requested);
+        assert_eq!(clamped.len(), 1);
+        assert_eq!(clamped[0].field, ConfigField::MaxBuffers);
+        assert!(clamped[0].difference() < 0);
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn footprint_of_4k_device() {
+        let config = DeviceConfig {
+            max_width: 3840,
+            max_height: 2160,
+            max_buffers: 30,
+            ..Default::default()
+        };
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(3840, 2160));
+        assert_eq!(device_memory_footprint(&config, &format), 497_664_000);
+
+        let unknown = Format::new(PixelFormat::Unknown(0), Resolution::new(3840, 2160));
+        assert_eq!(device_memory_footprint(&config, &unknown), 0);
+    }
+
+    #[test]
+    fn config_diff() {
+        let current = DeviceConfig {
+            label: "Camera".to_string(),
+            max_width: 1920,
+            ..Default::default()
+        };
+        let wanted = DeviceConfig {
+            label: "Studio Camera".to_string(),
+            max_width: 3840,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            current.diff(&wanted),
+            [
+                FieldChange {
+                    field: "label",
+                    from: "Camera".to_string(),
+                    to: "Studio Camera".to_string(),
+                },
+                FieldChange {
+                    field: "max_width",
+                    from: "1920".to_string(),
+                    to: "3840".to_string(),
+                },
+            ]
+        );
+        assert!(current.diff(&current).is_empty());
+    }
+
+    #[test]
+    fn clamped_fields() {
+        let requested = DeviceConfig {
+            min_width: 2,
+            max_width: 10000,
+            max_openers: 3,
+            ..Default::default()
+        };
+        let actual = DeviceConfig {
+            min_width: 48,
+            max_width: 8192,
+            max_buffers: 2,
+            max_openers: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            actual.was_clamped(&requested),
+            [
+                ClampedField {
+                    field: ConfigField::MinWidth,
+                    requested: 2,
+                    actual: 48,
+                },
+                ClampedField {
+                    field: ConfigField::MaxWidth,
+                    requested: 10000,
+                    actual: 8192,
+                },
+            ]
+        );
+        assert_eq!(actual.was_clamped(&requested)[1].difference(), -1808);
+        assert!(actual.was_clamped(&actual).is_empty());
+    }
 }
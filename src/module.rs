@@ -0,0 +1,435 @@
+use std::path::Path;
+
+use crate::{
+    control::control_path, format::MAX_DIMENSION, list_devices, open_control_device, settings,
+    sysfs, DeviceConfig, Error, Result, V4L2LOOPBACK_VERSION_BUGFIX, V4L2LOOPBACK_VERSION_MAJOR,
+    V4L2LOOPBACK_VERSION_MINOR,
+};
+
+/// Where the kernel exposes the v4l2loopback module in sysfs.
+pub(crate) const MODULE_ROOT: &str = "/sys/module/v4l2loopback";
+
+/// Version of the v4l2loopback headers this crate was built against, as
+/// `(major, minor, bugfix)`.
+///
+/// This is [`V4L2LOOPBACK_VERSION_MAJOR`], [`V4L2LOOPBACK_VERSION_MINOR`] and
+/// [`V4L2LOOPBACK_VERSION_BUGFIX`] together, so it can be compared to [`module_version`]
+/// directly.
+pub const COMPILED_MODULE_VERSION: (u32, u32, u32) = (
+    V4L2LOOPBACK_VERSION_MAJOR,
+    V4L2LOOPBACK_VERSION_MINOR,
+    V4L2LOOPBACK_VERSION_BUGFIX,
+);
+
+/// Version of the v4l2loopback headers this crate was built against, see
+/// [`COMPILED_MODULE_VERSION`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{compiled_version, module_version};
+///
+/// let loaded = module_version().expect("Error when reading the module version");
+/// if loaded < compiled_version() {
+///     eprintln!("v4l2loopback {:?} is older than {:?}", loaded, compiled_version());
+/// }
+/// ```
+pub const fn compiled_version() -> (u32, u32, u32) {
+    COMPILED_MODULE_VERSION
+}
+
+/// Parse a version like `0.13.1`, ignoring anything after a `-` (like a git hash).
+///
+/// A missing bugfix number is considered to be 0.
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let numeric = version.split('-').next()?;
+    let mut parts = numeric.split('.').map(str::parse::<u32>);
+
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let bugfix = match parts.next() {
+        Some(bugfix) => bugfix.ok()?,
+        None => 0,
+    };
+
+    Some((major, minor, bugfix))
+}
+
+/// Version of the loaded v4l2loopback kernel module, as reported by the module.
+///
+/// Unlike [`module_version`], this is not parsed, so it keeps any suffix added when building
+/// the module from git (like `0.13.1-7-gabc123`). This is useful in bug reports, when the
+/// numeric version isn't enough to identify the module.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`ModuleNotLoaded`] if the kernel module is not loaded.
+/// - [`Unsupported`] if the module doesn't report a version.
+///
+/// [`ModuleNotLoaded`]: Error::ModuleNotLoaded
+/// [`Unsupported`]: Error::Unsupported
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::module_version_string;
+///
+/// let version = module_version_string().expect("Error when reading the module version");
+/// println!("Running v4l2loopback {}", version);
+/// ```
+pub fn module_version_string() -> Result<String> {
+    let root = Path::new(MODULE_ROOT);
+    if !root.is_dir() {
+        return Err(Error::ModuleNotLoaded);
+    }
+
+    match sysfs::read_attribute(root, "version") {
+        Some(version) => Ok(version),
+        None => Err(Error::Unsupported("reporting its version")),
+    }
+}
+
+/// Version of the loaded v4l2loopback kernel module, as `(major, minor, bugfix)`.
+///
+/// This can be compared to the version this crate was built against,
+/// [`COMPILED_MODULE_VERSION`].
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - Any error from [`module_version_string`].
+/// - [`Other`] if the version couldn't be parsed.
+///
+/// [`Other`]: Error::Other
+pub fn module_version() -> Result<(u32, u32, u32)> {
+    let version = module_version_string()?;
+
+    match parse_version(&version) {
+        Some(version) => Ok(version),
+        None => Err(Error::Other(
+            format!("Invalid v4l2loopback version: {}", version).into(),
+        )),
+    }
+}
+
+/// Limit of devices of v4l2loopback versions which don't expose it, see
+/// [`Control::device_limit`](crate::Control::device_limit).
+pub(crate) const DEFAULT_DEVICE_LIMIT: u32 = 8;
+
+/// Read the `max_devices` parameter of the module exposed in `root`.
+pub(crate) fn max_devices_in(root: &Path) -> Option<u32> {
+    sysfs::read_attribute(&root.join("parameters"), "max_devices")
+        .and_then(|limit| limit.parse().ok())
+}
+
+/// Replace the maximal width and height of `config` left to 0 by the defaults of the module
+/// exposed in `root`.
+///
+/// Those are the `max_width` and `max_height` parameters of the module, or the largest
+/// dimension v4l2loopback accepts for versions which don't expose them.
+pub(crate) fn with_default_bounds_in(root: &Path, mut config: DeviceConfig) -> DeviceConfig {
+    let parameter = |name| {
+        sysfs::read_attribute(&root.join("parameters"), name)
+            .and_then(|value| value.parse().ok())
+            .filter(|value| *value != 0)
+            .unwrap_or(MAX_DIMENSION)
+    };
+    if config.max_width == 0 {
+        config.max_width = parameter("max_width");
+    }
+    if config.max_height == 0 {
+        config.max_height = parameter("max_height");
+    }
+
+    config
+}
+
+pub(crate) fn dynamic_devices_in(root: &Path, control_path: &Path) -> Result<bool> {
+    if control_path.exists() {
+        return Ok(true);
+    }
+    if !root.is_dir() {
+        return Err(Error::ModuleNotLoaded);
+    }
+
+    Ok(false)
+}
+
+/// Whether the loaded module can create and delete devices at runtime.
+///
+/// Devices are created and deleted through the control device, which only exists when the
+/// module was built with it. Otherwise, the module only has the devices given in its
+/// `devices` parameter when it was loaded, and [`add_device`](crate::add_device) can't work.
+/// Checking this first lets you ask the user to reload the module with a suitable version,
+/// instead of failing when creating a device.
+///
+/// This doesn't check that the control device can be opened, see [`preflight`] for that.
+///
+/// # Errors
+///
+/// This function will return [`ModuleNotLoaded`] if the kernel module is not loaded.
+///
+/// [`ModuleNotLoaded`]: Error::ModuleNotLoaded
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::supports_dynamic_devices;
+///
+/// if !supports_dynamic_devices().expect("Error when checking the module") {
+///     eprintln!("Please load a v4l2loopback version which supports creating devices");
+/// }
+/// ```
+pub fn supports_dynamic_devices() -> Result<bool> {
+    dynamic_devices_in(Path::new(MODULE_ROOT), control_path(&settings()))
+}
+
+/// Features of the loaded v4l2loopback module this crate can use, see [`module_features`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ModuleFeatures {
+    /// Devices can show an image when the producer stops writing for too long, with the
+    /// `timeout` and `timeout_image_io` controls. Since v4l2loopback 0.12.0.
+    pub timeout_image: bool,
+    /// Devices can keep their format and last frame when the producer closes them, with the
+    /// `keep_format` control. Since v4l2loopback 0.10.0.
+    pub keep_last_frame: bool,
+    /// Devices can be created and deleted at runtime, see [`supports_dynamic_devices`]. Since
+    /// v4l2loopback 0.12.0.
+    pub dynamic_devices: bool,
+    /// Debug messages can be enabled per device, with
+    /// [`DeviceConfig::debug`](crate::DeviceConfig::debug). Since v4l2loopback 0.12.0.
+    pub per_device_debug: bool,
+}
+
+impl ModuleFeatures {
+    /// Features of a v4l2loopback `version`, as `(major, minor, bugfix)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::ModuleFeatures;
+    ///
+    /// let features = ModuleFeatures::from_version((0, 12, 7));
+    /// assert!(features.dynamic_devices);
+    /// ```
+    pub fn from_version(version: (u32, u32, u32)) -> Self {
+        Self {
+            timeout_image: version >= (0, 12, 0),
+            keep_last_frame: version >= (0, 10, 0),
+            dynamic_devices: version >= (0, 12, 0),
+            per_device_debug: version >= (0, 12, 0),
+        }
+    }
+}
+
+/// Features of the loaded v4l2loopback module this crate can use.
+///
+/// This lets you check what the module supports before using a feature, instead of trying it
+/// and handling the error. v4l2loopback doesn't advertise its features, so they are deduced
+/// from [`module_version`] (see [`ModuleFeatures::from_version`]), except
+/// [`dynamic_devices`](ModuleFeatures::dynamic_devices) which is checked with
+/// [`supports_dynamic_devices`].
+///
+/// # Errors
+///
+/// See [`module_version`] and [`supports_dynamic_devices`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::module_features;
+///
+/// let features = module_features().expect("Error when reading the module features");
+/// if !features.timeout_image {
+///     println!("Consumers will see a frozen frame when the producer stops");
+/// }
+/// ```
+pub fn module_features() -> Result<ModuleFeatures> {
+    Ok(ModuleFeatures {
+        dynamic_devices: supports_dynamic_devices()?,
+        ..ModuleFeatures::from_version(module_version()?)
+    })
+}
+
+/// Result of [`preflight`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Preflight {
+    /// Version of the loaded kernel module, see [`module_version`].
+    pub version: (u32, u32, u32),
+    /// Whether the loaded module is compatible with the version this crate was built against.
+    ///
+    /// The major versions must match, and the loaded module must not be older.
+    pub compatible: bool,
+    /// Maximal number of devices the module accepts.
+    ///
+    /// This is read from the module's `max_devices` parameter, which is not exposed by every
+    /// version of v4l2loopback.
+    pub device_limit: Option<u32>,
+    /// Number of v4l2loopback devices currently existing.
+    pub device_count: usize,
+}
+
+/// Check that everything is ready to manage v4l2loopback devices.
+///
+/// This verifies that the kernel module is loaded, that the control device can be opened and
+/// reports informations about the module. This is meant to be called when your application
+/// starts, before creating any device.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`ModuleNotLoaded`] if the kernel module is not loaded.
+/// - [`ControlDevice`] if it is unable to open the control device.
+/// - Any error from [`module_version`] or [`list_devices`].
+///
+/// [`ModuleNotLoaded`]: Error::ModuleNotLoaded
+/// [`ControlDevice`]: Error::ControlDevice
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::preflight;
+///
+/// let preflight = preflight().expect("v4l2loopback is not usable");
+/// if !preflight.compatible {
+///     eprintln!("Unsupported v4l2loopback version: {:?}", preflight.version);
+/// }
+/// ```
+pub fn preflight() -> Result<Preflight> {
+    let root = Path::new(MODULE_ROOT);
+    if !root.is_dir() {
+        return Err(Error::ModuleNotLoaded);
+    }
+
+    // Only checking that we can open it
+    drop(open_control_device()?);
+
+    let version = module_version()?;
+    let compiled = COMPILED_MODULE_VERSION;
+
+    Ok(Preflight {
+        version,
+        compatible: version.0 == compiled.0 && version >= compiled,
+        device_limit: max_devices_in(root),
+        device_count: list_devices()?.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{
+        dynamic_devices_in, max_devices_in, parse_version, with_default_bounds_in, ModuleFeatures,
+    };
+    use crate::{
+        compiled_version, module_version_string, preflight, supports_dynamic_devices,
+        sysfs::tests::FakeSysfs, DeviceConfig, Error, COMPILED_MODULE_VERSION,
+        V4L2LOOPBACK_VERSION_BUGFIX, V4L2LOOPBACK_VERSION_MAJOR, V4L2LOOPBACK_VERSION_MINOR,
+    };
+
+    #[test]
+    fn compiled_version_tuple() {
+        assert_eq!(
+            COMPILED_MODULE_VERSION,
+            (
+                V4L2LOOPBACK_VERSION_MAJOR,
+                V4L2LOOPBACK_VERSION_MINOR,
+                V4L2LOOPBACK_VERSION_BUGFIX
+            )
+        );
+        assert_eq!(compiled_version(), COMPILED_MODULE_VERSION);
+    }
+
+    #[test]
+    fn version_parsing() {
+        assert_eq!(parse_version("0.13.1"), Some((0, 13, 1)));
+        assert_eq!(parse_version("0.12"), Some((0, 12, 0)));
+        assert_eq!(parse_version("0.13.1-7-gabc123"), Some((0, 13, 1)));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn features_of_versions() {
+        assert_eq!(
+            ModuleFeatures::from_version((0, 12, 7)),
+            ModuleFeatures {
+                timeout_image: true,
+                keep_last_frame: true,
+                dynamic_devices: true,
+                per_device_debug: true,
+            }
+        );
+        assert_eq!(
+            ModuleFeatures::from_version((0, 10, 0)),
+            ModuleFeatures {
+                keep_last_frame: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            ModuleFeatures::from_version((0, 9, 1)),
+            ModuleFeatures::default()
+        );
+    }
+
+    #[test]
+    fn healthy_preflight() {
+        let preflight = preflight().expect("Error during preflight");
+        assert_eq!(preflight.version.0, V4L2LOOPBACK_VERSION_MAJOR);
+        assert!(preflight.compatible);
+    }
+
+    #[test]
+    fn raw_version_string() {
+        let version = module_version_string().expect("Error when reading the module version");
+        assert!(!version.is_empty());
+    }
+
+    #[test]
+    fn max_devices_parameter() {
+        let sysfs = FakeSysfs::new("max_devices_parameter");
+        assert_eq!(max_devices_in(sysfs.root()), None);
+
+        fs::create_dir_all(sysfs.root().join("parameters")).unwrap();
+        fs::write(sysfs.root().join("parameters/max_devices"), "16\n").unwrap();
+        assert_eq!(max_devices_in(sysfs.root()), Some(16));
+    }
+
+    #[test]
+    fn default_bounds_parameters() {
+        let sysfs = FakeSysfs::new("default_bounds_parameters");
+        let config = with_default_bounds_in(sysfs.root(), DeviceConfig::default());
+        assert_eq!((config.max_width, config.max_height), (8192, 8192));
+
+        fs::create_dir_all(sysfs.root().join("parameters")).unwrap();
+        fs::write(sysfs.root().join("parameters/max_width"), "1920\n").unwrap();
+        fs::write(sysfs.root().join("parameters/max_height"), "1080\n").unwrap();
+        let config = with_default_bounds_in(sysfs.root(), DeviceConfig::default());
+        assert_eq!((config.max_width, config.max_height), (1920, 1080));
+
+        // Explicit bounds are kept
+        let explicit = DeviceConfig {
+            max_width: 640,
+            ..Default::default()
+        };
+        let config = with_default_bounds_in(sysfs.root(), explicit);
+        assert_eq!((config.max_width, config.max_height), (640, 1080));
+    }
+
+    #[test]
+    fn dynamic_devices() {
+        let sysfs = FakeSysfs::new("dynamic_devices");
+        let control = sysfs.root().join("v4l2loopback");
+        assert!(!dynamic_devices_in(sysfs.root(), &control).unwrap());
+        fs::write(&control, "").unwrap();
+        assert!(dynamic_devices_in(sysfs.root(), &control).unwrap());
+        assert!(matches!(
+            dynamic_devices_in(&sysfs.root().join("missing"), &sysfs.root().join("missing")),
+            Err(Error::ModuleNotLoaded)
+        ));
+
+        assert!(supports_dynamic_devices().expect("Error when checking the module"));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use crate::{query_device, settings, sysfs, Errno, Error, Result};
+
+/// Current limit of simultaneous openers of a device.
+///
+/// This is the `max_openers` value of the device configuration, including any change made
+/// with [`set_max_openers`] since the device was created.
+///
+/// # Errors
+///
+/// See [`query_device`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, max_openers, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     max_openers: 3,
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// assert_eq!(max_openers(device_num).expect("Error when querying the device"), 3);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn max_openers(device_num: u32) -> Result<u32> {
+    Ok(query_device(device_num)?.max_openers)
+}
+
+pub(crate) fn set_max_openers_in(root: &Path, device_num: u32, max_openers: u32) -> Result<()> {
+    if i32::try_from(max_openers).is_err() {
+        return Err(Error::Other(
+            format!("Invalid max_openers: {}", max_openers).into(),
+        ));
+    }
+
+    let dir = sysfs::device_dir(root, device_num);
+    if !dir.is_dir() {
+        return Err(Error::DeviceNotFound(device_num));
+    }
+
+    let attribute = dir.join("max_openers");
+    if !attribute.is_file() {
+        return Err(Error::Unsupported("changing max_openers at runtime"));
+    }
+
+    match fs::write(attribute, max_openers.to_string()) {
+        Ok(()) => Ok(()),
+        // v4l2loopback refuses a limit below the current number of openers
+        Err(e) if e.raw_os_error().map(Errno::from_i32) == Some(Errno::EINVAL) => {
+            Err(Error::DeviceBusy(device_num))
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(Error::DeviceNotFound(device_num)),
+        Err(e) => Err(Error::Other(Box::new(e))),
+    }
+}
+
+/// Change how many openers are allowed on a device, without recreating it.
+///
+/// Applications which already opened the device are not affected, this only restricts new
+/// ones. This can be used to keep new consumers away while reconfiguring a device.
+///
+/// The limit is changed through the `max_openers` sysfs attribute of the device, which
+/// requires write permissions on it (root by default).
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`Unsupported`] if the loaded v4l2loopback module can't change the limit at runtime.
+/// - [`DeviceNotFound`] if the kernel has no video device with this number.
+/// - [`DeviceBusy`] if `max_openers` is below the number of applications using the device.
+/// - [`Other`] if `max_openers` is above [`i32::MAX`], or if the attribute couldn't be
+///   written.
+///
+/// [`Unsupported`]: Error::Unsupported
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`DeviceBusy`]: Error::DeviceBusy
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, max_openers, set_max_openers, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// set_max_openers(device_num, 1).expect("Error when setting max_openers");
+/// assert_eq!(max_openers(device_num).expect("Error when querying the device"), 1);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn set_max_openers(device_num: u32, max_openers: u32) -> Result<()> {
+    if settings().dry_run {
+        log::info!(
+            "[dry run] Would set max_openers of /dev/video{} to {}",
+            device_num,
+            max_openers
+        );
+        return Ok(());
+    }
+
+    set_max_openers_in(Path::new(sysfs::VIDEO4LINUX_ROOT), device_num, max_openers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::set_max_openers_in;
+    use crate::{
+        add_device, delete_device, max_openers, sysfs::tests::FakeSysfs, DeviceConfig, Error,
+    };
+
+    #[test]
+    fn configured_max_openers() {
+        let config = DeviceConfig {
+            max_openers: 5,
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+
+        assert_eq!(
+            max_openers(device_num).expect("Error when querying the device"),
+            5
+        );
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn unsupported_max_openers() {
+        let sysfs = FakeSysfs::new("unsupported_max_openers");
+        sysfs.add_device(2, &[("name", "Old Module")]);
+
+        assert!(matches!(
+            set_max_openers_in(sysfs.root(), 2, 1),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            set_max_openers_in(sysfs.root(), 3, 1),
+            Err(Error::DeviceNotFound(3))
+        ));
+    }
+}
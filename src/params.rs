@@ -0,0 +1,467 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::File,
+    os::fd::AsRawFd,
+};
+
+use crate::{
+    settings, sys::ioctl_readwrite, video::open_video_device, videodev2, Colorspace, Error, Format,
+    FrameInterval, PixelFormat, Quantization, Resolution, Result, Settings, TransferFunction,
+};
+
+ioctl_readwrite!(vidioc_g_fmt, b'V', 4, videodev2::v4l2_format);
+ioctl_readwrite!(vidioc_s_fmt, b'V', 5, videodev2::v4l2_format);
+ioctl_readwrite!(vidioc_g_parm, b'V', 21, videodev2::v4l2_streamparm);
+ioctl_readwrite!(vidioc_s_parm, b'V', 22, videodev2::v4l2_streamparm);
+ioctl_readwrite!(vidioc_try_fmt, b'V', 64, videodev2::v4l2_format);
+
+fn format_from_pix(pix: &videodev2::v4l2_pix_format) -> Format {
+    Format {
+        colorspace: Colorspace::from(pix.colorspace),
+        quantization: Quantization::from(pix.quantization),
+        transfer_function: TransferFunction::from(pix.xfer_func),
+        ..Format::new(
+            PixelFormat::from(pix.pixelformat),
+            Resolution::new(pix.width, pix.height),
+        )
+    }
+}
+
+/// Build the output format structure passed to the driver.
+fn output_format(format: &Format) -> videodev2::v4l2_format {
+    let mut v4l2_format: videodev2::v4l2_format = unsafe { std::mem::zeroed() };
+    v4l2_format.type_ = videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT;
+    let pix = unsafe { &mut v4l2_format.fmt.pix };
+    pix.width = format.resolution.width;
+    pix.height = format.resolution.height;
+    pix.pixelformat = format.pixel_format.fourcc();
+    pix.field = videodev2::v4l2_field_V4L2_FIELD_NONE;
+    pix.colorspace = format.colorspace.into();
+    pix.quantization = format.quantization.into();
+    pix.xfer_func = format.transfer_function.into();
+    pix.sizeimage = format
+        .image_size()
+        .and_then(|size| size.try_into().ok())
+        .unwrap_or(0);
+
+    v4l2_format
+}
+
+/// Read the format of an opened device, for the given buffer type.
+pub(crate) fn read_format(file: &File, buf_type: videodev2::v4l2_buf_type) -> Result<Format> {
+    let mut format: videodev2::v4l2_format = unsafe { std::mem::zeroed() };
+    format.type_ = buf_type;
+    unsafe { vidioc_g_fmt(file.as_raw_fd(), &mut format) }?;
+
+    Ok(format_from_pix(unsafe { &format.fmt.pix }))
+}
+
+/// Fetch the format of the frames a device is configured for.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+pub fn get_format(device_num: u32) -> Result<Format> {
+    let file = open_video_device(device_num, false)?;
+    read_format(&file, videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT)
+}
+
+/// Configure the format of the frames passed through a device, as a producer.
+///
+/// The driver can adjust the format (for example to fit the resolution in the device bounds),
+/// so the format actually applied is returned. The colorimetry is passed along with the format,
+/// so consumers interpret the colors the same way as the producer.
+///
+/// v4l2loopback can't change the format while frames are being streamed through the device.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the underlying ioctl call fails, for example with [`EBUSY`] while
+///   streaming.
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`EBUSY`]: Errno::EBUSY
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, delete_device, get_format, set_format, DeviceConfig, Format, PixelFormat,
+///     Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let format = Format::new(PixelFormat::Yuyv, Resolution::new(1280, 720));
+/// let applied = set_format(device_num, &format).expect("Error when setting the format");
+/// assert_eq!(applied, format);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn set_format(device_num: u32, format: &Format) -> Result<Format> {
+    set_format_with(&settings(), device_num, format)
+}
+
+/// [`set_format`] with the given settings.
+pub(crate) fn set_format_with(
+    settings: &Settings,
+    device_num: u32,
+    format: &Format,
+) -> Result<Format> {
+    if settings.dry_run {
+        log::info!(
+            "[dry run] Would set the format of /dev/video{} to {:?}",
+            device_num,
+            format
+        );
+        return Ok(*format);
+    }
+
+    let file = open_video_device(device_num, true)?;
+    write_format(&file, format)
+}
+
+fn write_format(file: &File, format: &Format) -> Result<Format> {
+    let mut v4l2_format = output_format(format);
+    unsafe { vidioc_s_fmt(file.as_raw_fd(), &mut v4l2_format) }?;
+
+    Ok(format_from_pix(unsafe { &v4l2_format.fmt.pix }))
+}
+
+/// Set the format of several devices, as a producer.
+///
+/// Each device is opened once, even if it appears several times in `formats`. A device failing
+/// doesn't prevent setting the format of the next ones. The result for each entry of `formats`
+/// is returned in the same order, with the errors of [`set_format`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, delete_device, set_formats, DeviceConfig, Format, PixelFormat, Resolution,
+/// };
+///
+/// let first = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let second = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let format = Format::new(PixelFormat::Yuyv, Resolution::new(1280, 720));
+/// for res in set_formats(&[(first, format), (second, format)]) {
+///     res.expect("Error when setting the format");
+/// }
+///
+/// delete_device(first).expect("Error when removing device");
+/// delete_device(second).expect("Error when removing device");
+/// ```
+pub fn set_formats(formats: &[(u32, Format)]) -> Vec<Result<()>> {
+    if settings().dry_run {
+        return formats
+            .iter()
+            .map(|(device_num, format)| {
+                log::info!(
+                    "[dry run] Would set the format of /dev/video{} to {:?}",
+                    device_num,
+                    format
+                );
+                Ok(())
+            })
+            .collect();
+    }
+
+    let mut files: HashMap<u32, File> = HashMap::new();
+
+    formats
+        .iter()
+        .map(|(device_num, format)| {
+            let file = match files.entry(*device_num) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(open_video_device(*device_num, true)?),
+            };
+            write_format(file, format).map(|_| ())
+        })
+        .collect()
+}
+
+/// How far the format adjusted by the driver is from the requested one.
+///
+/// Changing the pixel format is worse than any change in resolution, then the difference of
+/// areas is used.
+fn format_distance(requested: &Format, adjusted: &Format) -> (bool, u64) {
+    let area = |format: &Format| format.resolution.width as u64 * format.resolution.height as u64;
+    (
+        requested.pixel_format != adjusted.pixel_format,
+        area(requested).abs_diff(area(adjusted)),
+    )
+}
+
+/// Configure the first format of `preferred` the device accepts, as a producer.
+///
+/// Every candidate is tried in order with `VIDIOC_TRY_FMT`, which doesn't change the device.
+/// The first candidate the driver accepts without adjusting it is applied.
+///
+/// If the driver adjusts every candidate, the closest match is applied instead: the adjusted
+/// format which keeps the requested pixel format, then the one with the closest area to the
+/// requested resolution. On a tie, the candidate coming first in `preferred` wins.
+///
+/// The format actually applied is returned.
+///
+/// # Errors
+///
+/// This function will return [`Other`] if `preferred` is empty, or any error from
+/// [`set_format`].
+///
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, delete_device, negotiate_format, DeviceConfig, Format, PixelFormat, Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let preferred = [
+///     Format::new(PixelFormat::Nv12, Resolution::new(1920, 1080)),
+///     Format::new(PixelFormat::Yuyv, Resolution::new(1280, 720)),
+/// ];
+/// let format = negotiate_format(device_num, &preferred).expect("Error when negotiating the format");
+/// println!("Using {:?}", format);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn negotiate_format(device_num: u32, preferred: &[Format]) -> Result<Format> {
+    let file = open_video_device(device_num, true)?;
+
+    let mut closest: Option<((bool, u64), Format)> = None;
+    for candidate in preferred {
+        let mut v4l2_format = output_format(candidate);
+        unsafe { vidioc_try_fmt(file.as_raw_fd(), &mut v4l2_format) }?;
+        let adjusted = format_from_pix(unsafe { &v4l2_format.fmt.pix });
+        if adjusted == *candidate {
+            closest = Some(((false, 0), adjusted));
+            break;
+        }
+
+        let distance = format_distance(candidate, &adjusted);
+        if closest.map_or(true, |(best, _)| distance < best) {
+            closest = Some((distance, adjusted));
+        }
+    }
+
+    let Some((_, format)) = closest else {
+        return Err(Error::Other("No format to negotiate".into()));
+    };
+    if settings().dry_run {
+        log::info!(
+            "[dry run] Would set the format of /dev/video{} to {:?}",
+            device_num,
+            format
+        );
+        return Ok(format);
+    }
+
+    let mut v4l2_format = output_format(&format);
+    unsafe { vidioc_s_fmt(file.as_raw_fd(), &mut v4l2_format) }?;
+
+    Ok(format_from_pix(unsafe { &v4l2_format.fmt.pix }))
+}
+
+/// Fetch the time between two frames a device is configured for.
+///
+/// # Errors
+///
+/// See [`get_format`].
+pub fn get_frame_interval(device_num: u32) -> Result<FrameInterval> {
+    let file = open_video_device(device_num, false)?;
+
+    let mut parm: videodev2::v4l2_streamparm = unsafe { std::mem::zeroed() };
+    parm.type_ = videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT;
+    unsafe { vidioc_g_parm(file.as_raw_fd(), &mut parm) }?;
+
+    let interval = unsafe { parm.parm.output.timeperframe };
+    Ok(FrameInterval::new(interval.numerator, interval.denominator))
+}
+
+/// Configure the time between two frames of a device, as a producer.
+///
+/// The driver can adjust the interval, so the interval actually applied is returned.
+///
+/// # Errors
+///
+/// See [`set_format`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, set_frame_interval, DeviceConfig, FrameInterval};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let interval = set_frame_interval(device_num, FrameInterval::new(1, 60))
+///     .expect("Error when setting the frame interval");
+/// assert_eq!(interval.fps(), 60.0);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn set_frame_interval(device_num: u32, interval: FrameInterval) -> Result<FrameInterval> {
+    if settings().dry_run {
+        log::info!(
+            "[dry run] Would set the frame interval of /dev/video{} to {}/{}",
+            device_num,
+            interval.numerator,
+            interval.denominator
+        );
+        return Ok(interval);
+    }
+
+    let file = open_video_device(device_num, true)?;
+
+    let mut parm: videodev2::v4l2_streamparm = unsafe { std::mem::zeroed() };
+    parm.type_ = videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT;
+    parm.parm.output.timeperframe = videodev2::v4l2_fract {
+        numerator: interval.numerator,
+        denominator: interval.denominator,
+    };
+    unsafe { vidioc_s_parm(file.as_raw_fd(), &mut parm) }?;
+
+    let interval = unsafe { parm.parm.output.timeperframe };
+    Ok(FrameInterval::new(interval.numerator, interval.denominator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_distance, set_format_with};
+    use crate::{
+        add_device, delete_device, get_format, get_frame_interval, negotiate_format, set_format,
+        set_formats, set_frame_interval, used_device_numbers, Colorspace, DeviceConfig, Error,
+        Format, FrameInterval, PixelFormat, Quantization, Resolution, Settings, TransferFunction,
+    };
+
+    #[test]
+    fn format_and_interval_round_trip() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(320, 240));
+        set_format(device_num, &format).expect("Error when setting the format");
+        assert_eq!(
+            get_format(device_num).expect("Error when getting the format"),
+            format
+        );
+
+        set_frame_interval(device_num, FrameInterval::new(1, 15))
+            .expect("Error when setting the frame interval");
+        let interval = get_frame_interval(device_num).expect("Error when getting the interval");
+        assert_eq!(interval.fps(), 15.0);
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn dry_run_format() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let initial = get_format(device_num).expect("Error when getting the format");
+
+        let settings = Settings {
+            dry_run: true,
+            ..Default::default()
+        };
+        let format = Format::new(PixelFormat::Rgb24, Resolution::new(64, 48));
+        assert_ne!(initial, format);
+        assert_eq!(
+            set_format_with(&settings, device_num, &format).expect("Error in dry run"),
+            format
+        );
+        assert_eq!(
+            get_format(device_num).expect("Error when getting the format"),
+            initial
+        );
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn colorimetry_round_trip() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+
+        let format = Format {
+            colorspace: Colorspace::Srgb,
+            quantization: Quantization::FullRange,
+            transfer_function: TransferFunction::Srgb,
+            ..Format::new(PixelFormat::Rgb24, Resolution::new(320, 240))
+        };
+        set_format(device_num, &format).expect("Error when setting the format");
+        assert_eq!(
+            get_format(device_num).expect("Error when getting the format"),
+            format
+        );
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn negotiate_second_candidate() {
+        let config = DeviceConfig {
+            max_width: 640,
+            max_height: 480,
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+
+        let preferred = [
+            Format::new(PixelFormat::Yuyv, Resolution::new(1920, 1080)),
+            Format::new(PixelFormat::Yuyv, Resolution::new(320, 240)),
+        ];
+        let format =
+            negotiate_format(device_num, &preferred).expect("Error when negotiating the format");
+        assert_eq!(format, preferred[1]);
+        assert_eq!(
+            get_format(device_num).expect("Error when getting the format"),
+            preferred[1]
+        );
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn formats_of_several_devices() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let missing = used_device_numbers().unwrap().last().unwrap() + 1;
+
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(320, 240));
+        let results = set_formats(&[(missing, format), (device_num, format)]);
+        assert!(matches!(results[0], Err(Error::DeviceNotFound(n)) if n == missing));
+        assert!(results[1].is_ok());
+        assert_eq!(
+            get_format(device_num).expect("Error when getting the format"),
+            format
+        );
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn closest_format() {
+        let requested = Format::new(PixelFormat::Yuyv, Resolution::new(1280, 720));
+        let clamped = Format::new(PixelFormat::Yuyv, Resolution::new(640, 480));
+        let converted = Format::new(PixelFormat::Nv12, Resolution::new(1280, 720));
+
+        assert_eq!(format_distance(&requested, &requested), (false, 0));
+        assert!(format_distance(&requested, &clamped) < format_distance(&requested, &converted));
+    }
+}
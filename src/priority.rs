@@ -0,0 +1,154 @@
+use std::{fs::File, os::fd::AsRawFd};
+
+use crate::{
+    sys::{ioctl_read, ioctl_write_ptr},
+    video::open_video_device,
+    videodev2, Errno, Error, Result,
+};
+
+ioctl_read!(vidioc_g_priority, b'V', 67, u32);
+ioctl_write_ptr!(vidioc_s_priority, b'V', 68, u32);
+
+/// Priority of an application using a device.
+///
+/// While an application holds a priority, applications with a lower priority can't change the
+/// device settings (like the format).
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Priority {
+    /// Lowest priority, for applications running in the background.
+    Background,
+    /// Default priority of every application.
+    #[default]
+    Interactive,
+    /// Highest priority, for applications which must not be disturbed, like a recording.
+    Record,
+}
+
+impl From<Priority> for u32 {
+    fn from(priority: Priority) -> Self {
+        match priority {
+            Priority::Background => videodev2::v4l2_priority_V4L2_PRIORITY_BACKGROUND,
+            Priority::Interactive => videodev2::v4l2_priority_V4L2_PRIORITY_INTERACTIVE,
+            Priority::Record => videodev2::v4l2_priority_V4L2_PRIORITY_RECORD,
+        }
+    }
+}
+
+impl From<u32> for Priority {
+    fn from(value: u32) -> Self {
+        match value {
+            videodev2::v4l2_priority_V4L2_PRIORITY_BACKGROUND => Priority::Background,
+            videodev2::v4l2_priority_V4L2_PRIORITY_RECORD => Priority::Record,
+            // Unset means nobody asked for a particular priority
+            _ => Priority::Interactive,
+        }
+    }
+}
+
+fn unsupported(e: Errno) -> Error {
+    match e {
+        Errno::ENOTTY => Error::Unsupported("priorities"),
+        e => e.into(),
+    }
+}
+
+/// Fetch the highest priority held by the applications using a device.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Unsupported`] if the v4l2loopback version doesn't handle priorities.
+/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Unsupported`]: Error::Unsupported
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+pub fn get_priority(device_num: u32) -> Result<Priority> {
+    let file = open_video_device(device_num, false)?;
+
+    let mut priority = 0;
+    unsafe { vidioc_g_priority(file.as_raw_fd(), &mut priority) }.map_err(unsupported)?;
+
+    Ok(Priority::from(priority))
+}
+
+/// Open a device with the given priority, as a producer.
+///
+/// In v4l2, priorities are held by opened files, so the priority is released when the returned
+/// file is closed. The file is opened for writing, so it can be used to send frames.
+///
+/// # Errors
+///
+/// See [`get_priority`]. The ioctl fails with [`EBUSY`] if another application holds a higher
+/// priority.
+///
+/// [`EBUSY`]: Errno::EBUSY
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, get_priority, set_priority, DeviceConfig, Priority};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let output = set_priority(device_num, Priority::Record).expect("Error when setting the priority");
+/// assert_eq!(get_priority(device_num).expect("Error when getting the priority"), Priority::Record);
+///
+/// drop(output);
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn set_priority(device_num: u32, priority: Priority) -> Result<File> {
+    let file = open_video_device(device_num, true)?;
+
+    let priority = u32::from(priority);
+    unsafe { vidioc_s_priority(file.as_raw_fd(), &priority) }.map_err(unsupported)?;
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{add_device, delete_device, get_priority, set_priority, Priority};
+
+    #[test]
+    fn priority_round_trip() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        assert_eq!(
+            get_priority(device_num).expect("Error when getting the priority"),
+            Priority::Interactive
+        );
+
+        let output =
+            set_priority(device_num, Priority::Record).expect("Error when setting the priority");
+        assert_eq!(
+            get_priority(device_num).expect("Error when getting the priority"),
+            Priority::Record
+        );
+
+        // The priority is released along with the file
+        drop(output);
+        assert_eq!(
+            get_priority(device_num).expect("Error when getting the priority"),
+            Priority::Interactive
+        );
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn priority_values() {
+        for priority in [
+            Priority::Background,
+            Priority::Interactive,
+            Priority::Record,
+        ] {
+            assert_eq!(Priority::from(u32::from(priority)), priority);
+        }
+        assert_eq!(Priority::from(0), Priority::Interactive);
+        assert!(Priority::Record > Priority::Interactive);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+use crate::{Error, Result};
+
+/// Line of the registry for a device.
+///
+/// The registry has one `N<TAB>label` line per device. Backslashes, tabs and line breaks in
+/// labels are escaped like in Rust strings, so any label fits on its line.
+fn line(device_num: u32, label: &str) -> String {
+    let mut line = format!("{}\t", device_num);
+    for c in label.chars() {
+        match c {
+            '\\' => line.push_str("\\\\"),
+            '\t' => line.push_str("\\t"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            c => line.push(c),
+        }
+    }
+    line.push('\n');
+
+    line
+}
+
+/// Label of a registry line, reverting the escapes of [`line`].
+///
+/// Returns [`None`] for unknown escapes.
+fn unescape(label: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(label.len());
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            't' => unescaped.push('\t'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some(unescaped)
+}
+
+/// Record a persisted device in the registry at `path`, creating it if needed.
+pub(crate) fn record(path: &Path, device_num: u32, label: &str) -> Result<()> {
+    let mut file = match OpenOptions::new().create(true).append(true).open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    match file.write_all(line(device_num, label).as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(Error::Other(Box::new(e))),
+    }
+}
+
+/// Read every device recorded in the registry at `path`, keeping the registry as is.
+///
+/// A missing registry is considered empty, and malformed lines are ignored.
+pub(crate) fn read_all(path: &Path) -> Result<Vec<(u32, String)>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let (num, label) = line.split_once('\t')?;
+            Some((num.parse().ok()?, unescape(label)?))
+        })
+        .collect())
+}
+
+/// Replace the content of the registry at `path` with `records`.
+///
+/// The registry is removed when there is nothing left to record.
+pub(crate) fn write_all(path: &Path, records: &[(u32, String)]) -> Result<()> {
+    let res = if records.is_empty() {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    } else {
+        let content: String = records
+            .iter()
+            .map(|(num, label)| line(*num, label))
+            .collect();
+        fs::write(path, content)
+    };
+
+    res.map_err(|e| Error::Other(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{read_all, record, write_all};
+
+    #[test]
+    fn registry_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "v4l2loopback-registry-{}-round_trip",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        assert!(read_all(&path).unwrap().is_empty());
+
+        record(&path, 3, "First Camera").unwrap();
+        record(&path, 7, "Second\tCamera").unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "garbage\n").unwrap();
+
+        let records = read_all(&path).unwrap();
+        assert_eq!(
+            records,
+            [
+                (3, "First Camera".to_string()),
+                (7, "Second\tCamera".to_string())
+            ]
+        );
+
+        write_all(&path, &records[1..]).unwrap();
+        assert_eq!(read_all(&path).unwrap(), &records[1..]);
+
+        // Labels are free-form, even line breaks and escapes stay on their line
+        let records = [
+            (4, "Multi\nLine\r\n".to_string()),
+            (5, "Back\\slash \\t".to_string()),
+        ];
+        write_all(&path, &records).unwrap();
+        record(&path, 6, "Last\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        let read = read_all(&path).unwrap();
+        assert_eq!(read[..2], records);
+        assert_eq!(read[2], (6, "Last\n".to_string()));
+
+        write_all(&path, &records[1..]).unwrap();
+        assert_eq!(read_all(&path).unwrap(), &records[1..]);
+        write_all(&path, &[]).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    path::PathBuf,
+    sync::{PoisonError, RwLock},
+    time::Duration,
+};
+
+/// Settings changing the behavior of every function in this crate.
+///
+/// They are shared by the whole process, see [`set_settings`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Settings {
+    /// Don't perform mutating operations, only log what they would do.
+    ///
+    /// Functions like [`add_device`](crate::add_device),
+    /// [`delete_device`](crate::delete_device) or [`set_format`](crate::set_format) then return
+    /// successfully without touching the system, with the values they were asked to apply.
+    /// The operations are logged with the [`log`] crate, at the `info` level, and are prefixed
+    /// with `[dry run]`.
+    ///
+    /// Queries are not affected and still return the real state of the system.
+    pub dry_run: bool,
+
+    /// Open the control device with the `O_CLOEXEC` flag, `true` by default.
+    ///
+    /// This prevents the control device from being inherited by child processes, which
+    /// matters for daemons spawning other programs.
+    pub control_cloexec: bool,
+
+    /// Path of the control device, `/dev/v4l2loopback` when [`None`].
+    ///
+    /// This is useful when the control device is exposed somewhere else, like in a container.
+    pub control_path: Option<PathBuf>,
+
+    /// Maximal time to wait for the control device operations, like creating a device.
+    ///
+    /// A wedged driver can block an ioctl forever, so with a timeout the operation runs on a
+    /// separate thread and fails with [`Timeout`](crate::Error::Timeout) if it takes longer.
+    /// The thread is then abandoned, and the operation may still complete afterwards (for
+    /// example, the device may be created anyway). [`None`] by default, waiting as long as
+    /// needed.
+    pub ioctl_timeout: Option<Duration>,
+}
+
+const DEFAULT_SETTINGS: Settings = Settings {
+    dry_run: false,
+    control_cloexec: true,
+    control_path: None,
+    ioctl_timeout: None,
+};
+
+impl Default for Settings {
+    fn default() -> Self {
+        DEFAULT_SETTINGS
+    }
+}
+
+static SETTINGS: RwLock<Settings> = RwLock::new(DEFAULT_SETTINGS);
+
+/// Current settings of the crate.
+pub fn settings() -> Settings {
+    SETTINGS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Replace the settings of the crate.
+///
+/// The new settings apply to every thread, starting from the next call to a function of this
+/// crate.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+/// use v4l2loopback::{add_device, set_settings, settings, DeviceConfig, Settings};
+///
+/// let mut dry_run = settings();
+/// dry_run.dry_run = true;
+/// set_settings(dry_run);
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// assert!(!Path::new(&format!("/dev/video{}", device_num)).exists());
+///
+/// set_settings(Settings::default());
+/// ```
+pub fn set_settings(settings: Settings) {
+    *SETTINGS.write().unwrap_or_else(PoisonError::into_inner) = settings;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{add_device_with, delete_device_with, open_control_device, Settings};
+
+    #[test]
+    fn dry_run_add() {
+        let settings = Settings {
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let device_num = add_device_with(&settings, open_control_device, None, Default::default())
+            .expect("Error in dry run");
+        assert!(!Path::new(&format!("/dev/video{}", device_num)).exists());
+
+        delete_device_with(&settings, open_control_device, device_num).expect("Error in dry run");
+    }
+}
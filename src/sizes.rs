@@ -0,0 +1,483 @@
+use std::{fs::File, os::fd::AsRawFd};
+
+use crate::{
+    effective_caps, get_format, params::read_format, query_capabilities, sys::ioctl_readwrite,
+    video::open_video_device, videodev2, DeviceCaps, DeviceConfig, Errno, Error, Format,
+    FrameInterval, PixelFormat, Resolution, Result,
+};
+
+/// Resolution picked by [`Format::default_for_device`] when the device supports it.
+const PREFERRED_RESOLUTION: Resolution = Resolution::new(1280, 720);
+
+/// Frame sizes supported by a device for a pixel format.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FrameSizes {
+    /// Only the listed sizes are supported.
+    Discrete(Vec<Resolution>),
+    /// Every size between `min` and `max` is supported, in increments of `step`.
+    ///
+    /// Devices accepting any size in the range have a `step` of 1x1.
+    Stepwise {
+        /// Smallest supported size.
+        min: Resolution,
+        /// Biggest supported size.
+        max: Resolution,
+        /// Increment between two supported sizes.
+        step: Resolution,
+    },
+}
+
+impl FrameSizes {
+    /// Smallest and biggest supported sizes, compared separately on each dimension.
+    ///
+    /// Returns [`None`] if no size is supported.
+    pub fn bounds(&self) -> Option<(Resolution, Resolution)> {
+        match self {
+            FrameSizes::Discrete(sizes) => Some((
+                Resolution::new(
+                    sizes.iter().map(|size| size.width).min()?,
+                    sizes.iter().map(|size| size.height).min()?,
+                ),
+                Resolution::new(
+                    sizes.iter().map(|size| size.width).max()?,
+                    sizes.iter().map(|size| size.height).max()?,
+                ),
+            )),
+            FrameSizes::Stepwise { min, max, .. } => Some((*min, *max)),
+        }
+    }
+
+    /// Resolution picked by [`Format::default_for_device`] among these sizes.
+    pub(crate) fn default_resolution(&self) -> Option<Resolution> {
+        match self {
+            FrameSizes::Discrete(sizes) if sizes.contains(&PREFERRED_RESOLUTION) => {
+                Some(PREFERRED_RESOLUTION)
+            }
+            FrameSizes::Discrete(sizes) => sizes
+                .iter()
+                .copied()
+                .max_by_key(|size| size.width as u64 * size.height as u64),
+            FrameSizes::Stepwise { min, max, step } => {
+                let fits = |value: u32, min: u32, max: u32, step: u32| {
+                    (min..=max).contains(&value) && (value - min) % step.max(1) == 0
+                };
+                let preferred = PREFERRED_RESOLUTION;
+                if fits(preferred.width, min.width, max.width, step.width)
+                    && fits(preferred.height, min.height, max.height, step.height)
+                {
+                    Some(preferred)
+                } else {
+                    Some(*max)
+                }
+            }
+        }
+    }
+}
+
+/// Frame intervals supported by a device for a pixel format and resolution.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FrameIntervals {
+    /// Only the listed intervals are supported.
+    Discrete(Vec<FrameInterval>),
+    /// Every interval between `min` and `max` is supported, in increments of `step`.
+    ///
+    /// Devices accepting any interval in the range have a `step` of `1/1`.
+    Stepwise {
+        /// Shortest supported interval, which is the highest frame rate.
+        min: FrameInterval,
+        /// Longest supported interval, which is the lowest frame rate.
+        max: FrameInterval,
+        /// Increment between two supported intervals.
+        step: FrameInterval,
+    },
+}
+
+ioctl_readwrite!(
+    vidioc_enum_framesizes,
+    b'V',
+    74,
+    videodev2::v4l2_frmsizeenum
+);
+
+ioctl_readwrite!(
+    vidioc_enum_frameintervals,
+    b'V',
+    75,
+    videodev2::v4l2_frmivalenum
+);
+
+pub(crate) fn frame_sizes_of(file: &File, format: PixelFormat) -> Result<FrameSizes> {
+    let mut sizes = Vec::new();
+
+    for index in 0.. {
+        let mut size: videodev2::v4l2_frmsizeenum = unsafe { std::mem::zeroed() };
+        size.index = index;
+        size.pixel_format = format.fourcc();
+
+        match unsafe { vidioc_enum_framesizes(file.as_raw_fd(), &mut size) } {
+            Ok(_) => {}
+            // We went past the last size
+            Err(Errno::EINVAL) if index > 0 => break,
+            Err(e) => return Err(Error::Ioctl(e)),
+        }
+
+        if size.type_ != videodev2::v4l2_frmsizetypes_V4L2_FRMSIZE_TYPE_DISCRETE {
+            let stepwise = unsafe { size.__bindgen_anon_1.stepwise };
+            let continuous = videodev2::v4l2_frmsizetypes_V4L2_FRMSIZE_TYPE_CONTINUOUS;
+            let step = if size.type_ == continuous {
+                Resolution::new(1, 1)
+            } else {
+                Resolution::new(stepwise.step_width, stepwise.step_height)
+            };
+
+            return Ok(FrameSizes::Stepwise {
+                min: Resolution::new(stepwise.min_width, stepwise.min_height),
+                max: Resolution::new(stepwise.max_width, stepwise.max_height),
+                step,
+            });
+        }
+
+        let discrete = unsafe { size.__bindgen_anon_1.discrete };
+        sizes.push(Resolution::new(discrete.width, discrete.height));
+    }
+
+    Ok(FrameSizes::Discrete(sizes))
+}
+
+/// List the frame sizes a device supports for a pixel format, using
+/// `VIDIOC_ENUM_FRAMESIZES`.
+///
+/// This works with any video device, not only v4l2loopback ones.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the underlying ioctl call fails, for example with [`EINVAL`] if the device
+///   doesn't support `format`.
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`EINVAL`]: Errno::EINVAL
+/// [`Other`]: Error::Other
+pub fn enum_frame_sizes(device_num: u32, format: PixelFormat) -> Result<FrameSizes> {
+    let file = open_video_device(device_num, false)?;
+    frame_sizes_of(&file, format)
+}
+
+/// List the frame intervals a device supports for a pixel format and resolution, using
+/// `VIDIOC_ENUM_FRAMEINTERVALS`.
+///
+/// This works with any video device, not only v4l2loopback ones.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the underlying ioctl call fails, for example with [`EINVAL`] if the device
+///   doesn't support `format` at this `resolution`.
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`EINVAL`]: Errno::EINVAL
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, delete_device, enum_frame_intervals, DeviceConfig, FrameIntervals, PixelFormat,
+///     Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let intervals = enum_frame_intervals(device_num, PixelFormat::Yuyv, Resolution::new(640, 480))
+///     .expect("Error when listing frame intervals");
+/// match intervals {
+///     FrameIntervals::Discrete(intervals) => {
+///         for interval in intervals {
+///             println!("{} fps", interval.fps());
+///         }
+///     }
+///     FrameIntervals::Stepwise { min, max, .. } => {
+///         println!("From {} to {} fps", max.fps(), min.fps());
+///     }
+/// }
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn enum_frame_intervals(
+    device_num: u32,
+    format: PixelFormat,
+    resolution: Resolution,
+) -> Result<FrameIntervals> {
+    let file = open_video_device(device_num, false)?;
+    let mut intervals = Vec::new();
+
+    for index in 0.. {
+        let mut interval: videodev2::v4l2_frmivalenum = unsafe { std::mem::zeroed() };
+        interval.index = index;
+        interval.pixel_format = format.fourcc();
+        interval.width = resolution.width;
+        interval.height = resolution.height;
+
+        match unsafe { vidioc_enum_frameintervals(file.as_raw_fd(), &mut interval) } {
+            Ok(_) => {}
+            // We went past the last interval
+            Err(Errno::EINVAL) if index > 0 => break,
+            Err(e) => return Err(Error::Ioctl(e)),
+        }
+
+        let fraction =
+            |fract: videodev2::v4l2_fract| FrameInterval::new(fract.numerator, fract.denominator);
+
+        if interval.type_ != videodev2::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_DISCRETE {
+            let stepwise = unsafe { interval.__bindgen_anon_1.stepwise };
+            let continuous = videodev2::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_CONTINUOUS;
+            let step = if interval.type_ == continuous {
+                FrameInterval::new(1, 1)
+            } else {
+                fraction(stepwise.step)
+            };
+
+            return Ok(FrameIntervals::Stepwise {
+                min: fraction(stepwise.min),
+                max: fraction(stepwise.max),
+                step,
+            });
+        }
+
+        intervals.push(fraction(unsafe { interval.__bindgen_anon_1.discrete }));
+    }
+
+    Ok(FrameIntervals::Discrete(intervals))
+}
+
+impl Format {
+    /// Pick a format the device accepts, for producers which don't care about the format.
+    ///
+    /// The format is [`Yuyv`](PixelFormat::Yuyv), which every consumer reads, at the first of
+    /// these resolutions the device supports for it:
+    /// 1. 1280x720, which most consumers handle without scaling.
+    /// 2. The biggest supported resolution: the maximal one for devices accepting a range of
+    ///    resolutions, or the one with the most pixels for devices listing their resolutions.
+    ///
+    /// If the device doesn't support YUYV, which happens when v4l2loopback keeps the format of
+    /// a producer, the current format of the device is returned instead.
+    ///
+    /// The format isn't applied, see [`set_format`](crate::set_format).
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`Unsupported`] if the device supports YUYV but lists no resolution for it.
+    /// - Any error from [`enum_frame_sizes`] other than [`EINVAL`], or from [`get_format`].
+    ///
+    /// [`Unsupported`]: Error::Unsupported
+    /// [`EINVAL`]: Errno::EINVAL
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{add_device, delete_device, set_format, DeviceConfig, Format};
+    ///
+    /// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+    ///
+    /// let format = Format::default_for_device(device_num).expect("Error when picking a format");
+    /// set_format(device_num, &format).expect("Error when setting the format");
+    ///
+    /// delete_device(device_num).expect("Error when removing device");
+    /// ```
+    pub fn default_for_device(device_num: u32) -> Result<Format> {
+        let sizes = match enum_frame_sizes(device_num, PixelFormat::Yuyv) {
+            Ok(sizes) => sizes,
+            Err(Error::Ioctl(Errno::EINVAL)) => return get_format(device_num),
+            Err(e) => return Err(e),
+        };
+
+        match sizes.default_resolution() {
+            Some(resolution) => Ok(Format::new(PixelFormat::Yuyv, resolution)),
+            None => Err(Error::Unsupported("devices without any YUYV resolution")),
+        }
+    }
+}
+
+impl DeviceConfig {
+    /// Build a configuration mirroring a capture device, like a webcam.
+    ///
+    /// The label is the name of the capture device, and the width and height bounds are the
+    /// ones of the frame sizes it supports in its current pixel format. If the device doesn't
+    /// list its frame sizes, its current resolution is used for both bounds.
+    ///
+    /// Other fields keep their default value.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`NotACaptureDevice`] if the device is a v4l2loopback device or can't capture video.
+    /// - Any error from [`query_capabilities`] or [`enum_frame_sizes`].
+    ///
+    /// [`NotACaptureDevice`]: Error::NotACaptureDevice
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l2loopback::{add_device, DeviceConfig};
+    ///
+    /// // Assuming /dev/video0 is a webcam
+    /// let config = DeviceConfig::from_capture_device(0).expect("Error when reading the webcam");
+    /// let mirror = add_device(None, config).expect("Error when creating the device");
+    /// ```
+    pub fn from_capture_device(device_num: u32) -> Result<DeviceConfig> {
+        let caps = query_capabilities(device_num)?;
+        if caps.is_loopback() || !effective_caps(device_num)?.contains(DeviceCaps::VIDEO_CAPTURE) {
+            return Err(Error::NotACaptureDevice(device_num));
+        }
+
+        let file = open_video_device(device_num, false)?;
+        let format = read_format(&file, videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_CAPTURE)?;
+        let resolution = format.resolution;
+        let (min, max) = match frame_sizes_of(&file, format.pixel_format) {
+            Ok(sizes) => sizes.bounds().unwrap_or((resolution, resolution)),
+            Err(Error::Ioctl(Errno::EINVAL | Errno::ENOTTY)) => (resolution, resolution),
+            Err(e) => return Err(e),
+        };
+
+        Ok(DeviceConfig {
+            label: caps.card,
+            min_width: min.width,
+            max_width: max.width,
+            min_height: min.height,
+            max_height: max.height,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        add_device, delete_device, enum_frame_intervals, query_capabilities, used_device_numbers,
+        DeviceConfig, Error, Format, FrameIntervals, FrameSizes, PixelFormat, Resolution,
+    };
+
+    #[test]
+    fn discrete_bounds() {
+        let sizes = FrameSizes::Discrete(vec![
+            Resolution::new(1280, 720),
+            Resolution::new(640, 480),
+            Resolution::new(1920, 1080),
+        ]);
+        assert_eq!(
+            sizes.bounds(),
+            Some((Resolution::new(640, 480), Resolution::new(1920, 1080)))
+        );
+        assert_eq!(FrameSizes::Discrete(Vec::new()).bounds(), None);
+    }
+
+    #[test]
+    fn default_resolution() {
+        let stepwise = |max, step| FrameSizes::Stepwise {
+            min: Resolution::new(48, 32),
+            max,
+            step,
+        };
+        let one = Resolution::new(1, 1);
+        assert_eq!(
+            stepwise(Resolution::new(8192, 8192), one).default_resolution(),
+            Some(Resolution::new(1280, 720))
+        );
+        assert_eq!(
+            stepwise(Resolution::new(640, 480), one).default_resolution(),
+            Some(Resolution::new(640, 480))
+        );
+        // 1280 is not reachable from 48 by steps of 100
+        let max = Resolution::new(1948, 1032);
+        assert_eq!(
+            stepwise(max, Resolution::new(100, 1)).default_resolution(),
+            Some(max)
+        );
+
+        let discrete =
+            FrameSizes::Discrete(vec![Resolution::new(1920, 1080), Resolution::new(640, 480)]);
+        assert_eq!(
+            discrete.default_resolution(),
+            Some(Resolution::new(1920, 1080))
+        );
+        assert_eq!(FrameSizes::Discrete(Vec::new()).default_resolution(), None);
+    }
+
+    #[test]
+    fn default_format_within_bounds() {
+        let config = DeviceConfig {
+            max_width: 640,
+            max_height: 480,
+            ..Default::default()
+        };
+        let device_num = add_device(None, config.clone()).expect("Error when creating the device");
+
+        let format = Format::default_for_device(device_num).expect("Error when picking a format");
+        assert_eq!(format.pixel_format, PixelFormat::Yuyv);
+        assert!(format.resolution.fits_in(&config));
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn loopback_is_not_capture_device() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+
+        assert!(matches!(
+            DeviceConfig::from_capture_device(device_num),
+            Err(Error::NotACaptureDevice(n)) if n == device_num
+        ));
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn mirror_capture_device() {
+        let numbers = used_device_numbers().expect("Error when listing devices");
+        let capture = numbers.into_iter().find_map(|num| {
+            let config = DeviceConfig::from_capture_device(num).ok()?;
+            Some((num, config))
+        });
+        // Only possible with a real camera plugged in
+        let Some((num, config)) = capture else {
+            return;
+        };
+
+        let caps = query_capabilities(num).expect("Error when querying capabilities");
+        assert_eq!(config.label, caps.card);
+        assert!(config.min_width <= config.max_width);
+        assert!(config.min_height <= config.max_height);
+        assert!(config.max_width > 0 && config.max_height > 0);
+    }
+
+    #[test]
+    fn created_device_intervals() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+
+        let intervals =
+            enum_frame_intervals(device_num, PixelFormat::Yuyv, Resolution::new(640, 480))
+                .expect("Error when listing frame intervals");
+        let sensible = |fps: f64| fps > 0.0 && fps <= 1000.0;
+        match intervals {
+            FrameIntervals::Discrete(intervals) => {
+                assert!(!intervals.is_empty());
+                assert!(intervals.iter().all(|interval| sensible(interval.fps())));
+            }
+            FrameIntervals::Stepwise { min, max, .. } => {
+                assert!(sensible(min.fps()) && sensible(max.fps()));
+                assert!(min.fps() >= max.fps());
+            }
+        }
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+}
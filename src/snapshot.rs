@@ -0,0 +1,188 @@
+use std::io::Read;
+
+use image::{DynamicImage, GrayImage, RgbImage};
+
+use crate::{
+    params::read_format, video::open_video_device, videodev2, Error, Format, PixelFormat,
+    Quantization, Result,
+};
+
+/// Convert a YUV sample to RGB, with the BT.601 coefficients.
+fn yuv_to_rgb(y: u8, u: u8, v: u8, full_range: bool) -> [u8; 3] {
+    let (y, d, e) = (y as i32, u as i32 - 128, v as i32 - 128);
+    let (r, g, b) = if full_range {
+        (
+            y + ((359 * e + 128) >> 8),
+            y - ((88 * d + 183 * e + 128) >> 8),
+            y + ((454 * d + 128) >> 8),
+        )
+    } else {
+        let c = 298 * (y - 16);
+        (
+            (c + 409 * e + 128) >> 8,
+            (c - 100 * d - 208 * e + 128) >> 8,
+            (c + 516 * d + 128) >> 8,
+        )
+    };
+
+    [r, g, b].map(|channel| channel.clamp(0, 255) as u8)
+}
+
+/// Decode a frame in `format` into an image.
+///
+/// Returns [`None`] if the pixel format can't be decoded, or if the frame is too short.
+fn decode_frame(format: &Format, frame: &[u8]) -> Option<DynamicImage> {
+    let Format { resolution, .. } = format;
+    let frame = frame.get(..format.image_size()?)?;
+
+    match format.pixel_format {
+        PixelFormat::Yuyv => {
+            let full_range = format.quantization == Quantization::FullRange;
+            let row_len = resolution.width.div_ceil(2) as usize * 4;
+            let mut rgb =
+                Vec::with_capacity(resolution.width as usize * resolution.height as usize * 3);
+            for row in frame.chunks_exact(row_len) {
+                for (x, macropixel) in row.chunks_exact(4).enumerate() {
+                    let [y0, u, y1, v] =
+                        [macropixel[0], macropixel[1], macropixel[2], macropixel[3]];
+                    rgb.extend(yuv_to_rgb(y0, u, v, full_range));
+                    // The last macropixel of an odd width row only has one pixel
+                    if 2 * x + 1 < resolution.width as usize {
+                        rgb.extend(yuv_to_rgb(y1, u, v, full_range));
+                    }
+                }
+            }
+            RgbImage::from_raw(resolution.width, resolution.height, rgb)
+                .map(DynamicImage::ImageRgb8)
+        }
+        PixelFormat::Rgb24 => {
+            RgbImage::from_raw(resolution.width, resolution.height, frame.to_vec())
+                .map(DynamicImage::ImageRgb8)
+        }
+        PixelFormat::Grey => {
+            GrayImage::from_raw(resolution.width, resolution.height, frame.to_vec())
+                .map(DynamicImage::ImageLuma8)
+        }
+        _ => None,
+    }
+}
+
+/// Grab the next frame consumers of a device would see, as an image.
+///
+/// The frame is read from the capture side, so this blocks until a producer writes a frame.
+/// It is decoded according to the format negotiated on the device, which has to be
+/// [`Yuyv`](PixelFormat::Yuyv), [`Rgb24`](PixelFormat::Rgb24) or [`Grey`](PixelFormat::Grey).
+///
+/// This is only available with the `image` feature.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the format of the device couldn't be read.
+/// - [`Other`] if the device couldn't be read, or if its pixel format can't be decoded.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l2loopback::capture_snapshot;
+///
+/// let thumbnail = capture_snapshot(0)
+///     .expect("Error when capturing a frame")
+///     .to_rgb8();
+/// println!("Captured a {}x{} frame", thumbnail.width(), thumbnail.height());
+/// ```
+pub fn capture_snapshot(device_num: u32) -> Result<DynamicImage> {
+    let mut file = open_video_device(device_num, false)?;
+    let format = read_format(&file, videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_CAPTURE)?;
+
+    let mut frame = vec![0; format.image_size().unwrap_or(0)];
+    if let Err(e) = file.read_exact(&mut frame) {
+        return Err(Error::Other(Box::new(e)));
+    }
+
+    match decode_frame(&format, &frame) {
+        Some(image) => Ok(image),
+        None => Err(Error::Other(
+            format!("Can't decode {:?} frames", format.pixel_format).into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use image::Rgb;
+
+    use super::decode_frame;
+    use crate::{
+        capture_snapshot, set_format, Device, DeviceConfig, Format, PixelFormat, Quantization,
+        Resolution,
+    };
+
+    #[test]
+    fn decode_yuyv() {
+        let format = Format::new(PixelFormat::Yuyv, Resolution::new(3, 1));
+        // Black and white, then red alone in the last macropixel
+        let frame = [16, 128, 235, 128, 81, 90, 0, 240];
+        let image = decode_frame(&format, &frame).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (3, 1));
+        assert_eq!(*image.get_pixel(0, 0), Rgb([0, 0, 0]));
+        assert_eq!(*image.get_pixel(1, 0), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(2, 0), Rgb([255, 0, 0]));
+
+        let full_range = Format {
+            quantization: Quantization::FullRange,
+            ..format
+        };
+        let image = decode_frame(&full_range, &[0, 128, 255, 128, 0, 128, 0, 128]).unwrap();
+        assert_eq!(*image.to_rgb8().get_pixel(1, 0), Rgb([255, 255, 255]));
+
+        assert!(decode_frame(&format, &frame[..4]).is_none());
+        let nv12 = Format::new(PixelFormat::Nv12, Resolution::new(2, 2));
+        assert!(decode_frame(&nv12, &[0; 6]).is_none());
+    }
+
+    #[test]
+    fn snapshot_written_frame() {
+        let device =
+            Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Rgb24, Resolution::new(64, 48));
+        set_format(device.number().as_u32(), &format).expect("Error when setting the format");
+
+        let frame: Vec<u8> = (0..64 * 48 * 3).map(|i| (i % 251) as u8).collect();
+        let mut output = device.open_output().expect("Error when opening the device");
+        output
+            .write_all(&frame)
+            .expect("Error when writing the frame");
+
+        // Keep producing the same frame until it is captured
+        let captured = Arc::new(AtomicBool::new(false));
+        let producer = {
+            let (frame, captured) = (frame.clone(), captured.clone());
+            thread::spawn(move || {
+                while !captured.load(Ordering::Relaxed) && output.write_all(&frame).is_ok() {}
+            })
+        };
+
+        let image = capture_snapshot(device.number().as_u32())
+            .expect("Error when capturing a frame")
+            .to_rgb8();
+        assert_eq!(image.dimensions(), (64, 48));
+        captured.store(true, Ordering::Relaxed);
+        producer.join().unwrap();
+        assert_eq!(image.into_raw(), frame);
+    }
+}
@@ -0,0 +1,219 @@
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::{DeviceConfig, Resolution};
+
+/// Error returned when a spec string is malformed, see [`DeviceConfig::try_from`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::DeviceConfig;
+///
+/// let err = DeviceConfig::try_from("label=Camera,max=720").unwrap_err();
+/// assert_eq!(err.span, 17..20);
+/// assert_eq!(
+///     err.to_string(),
+///     "expected 'WIDTHxHEIGHT' at position 17, found '720'"
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Error)]
+#[error("expected {expected} at position {}, found '{found}'", span.start)]
+pub struct SpecParseError {
+    /// Position of the offending token in the spec, in bytes.
+    pub span: Range<usize>,
+    /// The offending token.
+    pub found: String,
+    /// What was expected instead.
+    pub expected: &'static str,
+}
+
+impl SpecParseError {
+    fn new(position: usize, found: &str, expected: &'static str) -> Self {
+        Self {
+            span: position..position + found.len(),
+            found: found.to_string(),
+            expected,
+        }
+    }
+}
+
+/// Parse a number starting at `position` in the spec.
+fn parse_number(value: &str, position: usize) -> Result<u32, SpecParseError> {
+    value
+        .parse()
+        .map_err(|_| SpecParseError::new(position, value, "a number"))
+}
+
+/// Parse a resolution starting at `position` in the spec.
+fn parse_resolution(value: &str, position: usize) -> Result<Resolution, SpecParseError> {
+    let Some((width, height)) = value.split_once('x') else {
+        return Err(SpecParseError::new(position, value, "'WIDTHxHEIGHT'"));
+    };
+
+    Ok(Resolution::new(
+        parse_number(width, position)?,
+        parse_number(height, position + width.len() + 1)?,
+    ))
+}
+
+/// Parse the configuration part of a spec, starting at `offset` in the whole spec.
+fn parse_config_at(spec: &str, offset: usize) -> Result<DeviceConfig, SpecParseError> {
+    let mut config = DeviceConfig::default();
+    if spec.is_empty() {
+        return Ok(config);
+    }
+
+    let mut position = offset;
+    for field in spec.split(',') {
+        let (key, value) = field.split_once('=').unwrap_or((field, ""));
+        let value_position = position + key.len() + 1;
+        match (key, field.contains('=')) {
+            ("label", true) => config.label = value.to_string(),
+            ("min", true) => {
+                let min = parse_resolution(value, value_position)?;
+                (config.min_width, config.min_height) = (min.width, min.height);
+            }
+            ("max", true) => {
+                let max = parse_resolution(value, value_position)?;
+                (config.max_width, config.max_height) = (max.width, max.height);
+            }
+            ("buffers", true) => config.max_buffers = parse_number(value, value_position)?,
+            ("openers", true) => config.max_openers = parse_number(value, value_position)?,
+            ("all_caps", false) => config.announce_all_caps = true,
+            _ => {
+                return Err(SpecParseError::new(
+                    position,
+                    key,
+                    "one of label=, min=, max=, buffers=, openers= or all_caps",
+                ))
+            }
+        }
+        position += field.len() + 1;
+    }
+
+    Ok(config)
+}
+
+/// Parse the configuration part of a spec, see [`DeviceConfig::try_from`].
+pub(crate) fn parse_config(spec: &str) -> Result<DeviceConfig, SpecParseError> {
+    parse_config_at(spec, 0)
+}
+
+/// Parse a full device spec, see [`Device::from_spec`](crate::Device::from_spec).
+pub(crate) fn parse_device(spec: &str) -> Result<(Option<u32>, DeviceConfig), SpecParseError> {
+    // The label can contain ':', so only a leading number is taken as the device number
+    match spec.split_once(':') {
+        Some((num, config)) if !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()) => Ok((
+            Some(parse_number(num, 0)?),
+            parse_config_at(config, num.len() + 1)?,
+        )),
+        _ => Ok((None, parse_config(spec)?)),
+    }
+}
+
+impl TryFrom<&str> for DeviceConfig {
+    type Error = SpecParseError;
+
+    /// Parse a configuration from a spec string.
+    ///
+    /// A spec is a comma separated list of fields, in any order:
+    /// - `label=TEXT`: [`label`](DeviceConfig::label), which can't contain a comma.
+    /// - `min=WIDTHxHEIGHT`: [`min_width`](DeviceConfig::min_width) and
+    ///   [`min_height`](DeviceConfig::min_height).
+    /// - `max=WIDTHxHEIGHT`: [`max_width`](DeviceConfig::max_width) and
+    ///   [`max_height`](DeviceConfig::max_height).
+    /// - `buffers=N`: [`max_buffers`](DeviceConfig::max_buffers).
+    /// - `openers=N`: [`max_openers`](DeviceConfig::max_openers).
+    /// - `all_caps`: enables [`announce_all_caps`](DeviceConfig::announce_all_caps).
+    ///
+    /// Missing fields keep their default value, so an empty spec is the default configuration.
+    /// Errors point at the part of the spec which couldn't be parsed, see [`SpecParseError`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::DeviceConfig;
+    ///
+    /// let config = DeviceConfig::try_from("label=My Camera,max=1920x1080,buffers=4").unwrap();
+    /// assert_eq!(config.label, "My Camera");
+    /// assert_eq!((config.max_width, config.max_height), (1920, 1080));
+    /// assert_eq!(config.max_buffers, 4);
+    /// ```
+    fn try_from(spec: &str) -> Result<Self, Self::Error> {
+        parse_config(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_device, SpecParseError};
+    use crate::DeviceConfig;
+
+    #[test]
+    fn full_spec() {
+        let config = DeviceConfig::try_from(
+            "label=Cam: 1,min=320x240,max=1920x1080,buffers=4,openers=2,all_caps",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            DeviceConfig {
+                label: "Cam: 1".to_string(),
+                min_width: 320,
+                max_width: 1920,
+                min_height: 240,
+                max_height: 1080,
+                max_buffers: 4,
+                max_openers: 2,
+                announce_all_caps: true,
+                ..Default::default()
+            }
+        );
+
+        let (num, device_config) = parse_device("7:label=Cam: 1").unwrap();
+        assert_eq!(num, Some(7));
+        assert_eq!(device_config.label, "Cam: 1");
+    }
+
+    #[test]
+    fn minimal_spec() {
+        assert_eq!(DeviceConfig::try_from("").unwrap(), DeviceConfig::default());
+        assert_eq!(
+            DeviceConfig::try_from("label=Camera").unwrap(),
+            DeviceConfig {
+                label: "Camera".to_string(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(parse_device("label=Cam: 1").unwrap().0, None);
+    }
+
+    #[test]
+    fn error_positions() {
+        let error = |spec: &str| DeviceConfig::try_from(spec).unwrap_err();
+
+        assert_eq!(
+            error("max=1920"),
+            SpecParseError {
+                span: 4..8,
+                found: "1920".to_string(),
+                expected: "'WIDTHxHEIGHT'",
+            }
+        );
+        assert_eq!(error("label=Camera,min=640xabc").span, 21..24);
+        assert_eq!(error("label=Camera,min=640xabc").found, "abc");
+        assert_eq!(error("buffers=-1").span, 8..10);
+        assert_eq!(error("label=Camera,fps=30").span, 13..16);
+        assert_eq!(error("label=Camera,,all_caps").span, 13..13);
+        assert_eq!(error("all_caps=yes").found, "all_caps");
+
+        // Positions count the device number
+        assert_eq!(parse_device("12:openers=x").unwrap_err().span, 11..12);
+        assert_eq!(
+            error("max=720").to_string(),
+            "expected 'WIDTHxHEIGHT' at position 4, found '720'"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    add_device_with, current_format_sysfs, free_numbers, list_devices, open_control_device,
+    query_device, set_format, used_device_numbers, DeviceConfig, Error, Format, Result, Settings,
+};
+
+/// A v4l2loopback device recorded in a [`StateDocument`].
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct DeviceState {
+    /// Number of the device, `N` in `/dev/videoN`.
+    pub number: u32,
+    /// Configuration of the device, as returned by [`query_device`].
+    pub config: DeviceConfig,
+    /// Format negotiated on the device, if any, see [`current_format_sysfs`].
+    pub format: Option<Format>,
+}
+
+/// Every v4l2loopback device of a system, see [`export_state`].
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct StateDocument {
+    /// The devices, in ascending order of numbers.
+    pub devices: Vec<DeviceState>,
+}
+
+/// What [`import_state`] does with a device whose number is already used.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum NumberConflict {
+    /// Create the device with the first number neither used nor recorded in the document
+    /// instead.
+    #[default]
+    Remap,
+    /// Don't create the device.
+    Skip,
+}
+
+/// Record every v4l2loopback device of the system, to recreate them with [`import_state`].
+///
+/// The document can be serialized with any serde format, for example to back up a setup or
+/// to move it to another machine. Formats are read from sysfs, so they are only recorded if a
+/// format was negotiated and the v4l2loopback version exposes it. Only the pixel format and
+/// the resolution of the formats are recorded.
+///
+/// This is only available with the `serde` feature.
+///
+/// # Errors
+///
+/// This function will return any error from [`list_devices`] or [`query_device`], other than
+/// [`DeviceNotFound`] for devices removed in the meantime.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::export_state;
+///
+/// let state = export_state().expect("Error when exporting the devices");
+/// for device in &state.devices {
+///     println!("/dev/video{}: {:?}", device.number, device.config.label);
+/// }
+/// ```
+pub fn export_state() -> Result<StateDocument> {
+    let mut devices = Vec::new();
+    for number in list_devices()? {
+        let config = match query_device(number) {
+            Ok(config) => config,
+            Err(Error::DeviceNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let format = match current_format_sysfs(number) {
+            Ok(format) => format,
+            Err(Error::DeviceNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+
+        devices.push(DeviceState {
+            number,
+            config,
+            format,
+        });
+    }
+
+    Ok(StateDocument { devices })
+}
+
+/// Number to request for each device of `doc`.
+///
+/// Remapped devices get the lowest numbers which are neither in `used` nor recorded in `doc`,
+/// so they can't take the number of a device coming later in the document. Skipped devices
+/// are not returned.
+pub(crate) fn plan_import<'a>(
+    doc: &'a StateDocument,
+    used: &[u32],
+    on_conflict: NumberConflict,
+) -> Vec<(&'a DeviceState, u32)> {
+    let mut taken = used.to_vec();
+    let mut conflicts = 0;
+    let mut plan = Vec::new();
+    for device in &doc.devices {
+        if !taken.contains(&device.number) {
+            taken.push(device.number);
+            plan.push((device, Some(device.number)));
+        } else if on_conflict == NumberConflict::Remap {
+            conflicts += 1;
+            plan.push((device, None));
+        }
+    }
+
+    let mut reserved: Vec<u32> = taken
+        .into_iter()
+        .chain(doc.devices.iter().map(|device| device.number))
+        .collect();
+    reserved.sort_unstable();
+    reserved.dedup();
+    let mut remapped = free_numbers(&reserved, conflicts).into_iter();
+
+    plan.into_iter()
+        .filter_map(|(device, number)| Some((device, number.or_else(|| remapped.next())?)))
+        .collect()
+}
+
+/// Recreate the devices recorded by [`export_state`].
+///
+/// Devices are created with their recorded number when it is free, otherwise according to
+/// `on_conflict`. Their format is set again when one was recorded. Devices which already exist
+/// are not compared with the document, so importing the same document twice creates the
+/// devices twice with [`NumberConflict::Remap`].
+///
+/// Returns the number each created device got, by recorded number. Skipped devices are
+/// missing.
+///
+/// With [`dry_run`](Settings::dry_run) enabled, nothing is created and the numbers devices
+/// would get are returned.
+///
+/// This is only available with the `serde` feature.
+///
+/// # Errors
+///
+/// This function will return any error from [`used_device_numbers`],
+/// [`add_device`](crate::add_device) or [`set_format`], for example [`NumberTaken`] if another
+/// application created a device with a planned number in the meantime. Devices created before
+/// the error are kept.
+///
+/// [`NumberTaken`]: Error::NumberTaken
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{export_state, import_state, settings, NumberConflict};
+///
+/// let state = export_state().expect("Error when exporting the devices");
+///
+/// // On another machine
+/// let numbers = import_state(&state, &settings(), NumberConflict::Remap)
+///     .expect("Error when importing the devices");
+/// for (recorded, created) in numbers {
+///     println!("/dev/video{} was recreated as /dev/video{}", recorded, created);
+/// }
+/// ```
+pub fn import_state(
+    doc: &StateDocument,
+    settings: &Settings,
+    on_conflict: NumberConflict,
+) -> Result<BTreeMap<u32, u32>> {
+    let used = used_device_numbers()?;
+
+    let mut numbers = BTreeMap::new();
+    for (device, number) in plan_import(doc, &used, on_conflict) {
+        let created = add_device_with(
+            settings,
+            open_control_device,
+            Some(number),
+            device.config.clone(),
+        )?;
+        if let (Some(format), false) = (&device.format, settings.dry_run) {
+            set_format(created, format)?;
+        }
+        numbers.insert(device.number, created);
+    }
+
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan_import, DeviceState, NumberConflict, StateDocument};
+    use crate::{DeviceConfig, Format, PixelFormat, Resolution};
+
+    fn document() -> StateDocument {
+        let device = |number, label: &str, format| DeviceState {
+            number,
+            config: DeviceConfig {
+                label: label.to_string(),
+                ..Default::default()
+            },
+            format,
+        };
+
+        StateDocument {
+            devices: vec![
+                device(
+                    10,
+                    "Camera",
+                    Some(Format::new(PixelFormat::Yuyv, Resolution::new(640, 480))),
+                ),
+                device(11, "Screen", None),
+            ],
+        }
+    }
+
+    #[test]
+    fn import_plan() {
+        let doc = document();
+        let numbers = |used: &[u32], on_conflict| {
+            plan_import(&doc, used, on_conflict)
+                .into_iter()
+                .map(|(device, number)| (device.number, number))
+                .collect::<Vec<_>>()
+        };
+
+        // Clean system
+        assert_eq!(numbers(&[], NumberConflict::Skip), [(10, 10), (11, 11)]);
+        assert_eq!(
+            numbers(&[0, 11], NumberConflict::Remap),
+            [(10, 10), (11, 1)]
+        );
+        assert_eq!(numbers(&[0, 11], NumberConflict::Skip), [(10, 10)]);
+    }
+
+    #[test]
+    fn remap_collisions() {
+        let device = |number| DeviceState {
+            number,
+            config: DeviceConfig::default(),
+            format: None,
+        };
+        let doc = StateDocument {
+            devices: vec![device(0), device(1), device(2), device(2)],
+        };
+        let numbers = plan_import(&doc, &[0, 1, 4], NumberConflict::Remap)
+            .into_iter()
+            .map(|(device, number)| (device.number, number))
+            .collect::<Vec<_>>();
+
+        // Remapped devices don't take 2, recorded later, nor each other's numbers
+        assert_eq!(numbers, [(0, 3), (1, 5), (2, 2), (2, 6)]);
+    }
+}
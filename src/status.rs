@@ -0,0 +1,403 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    query_device,
+    sys::{ioctl_readwrite, libc},
+    video::open_video_device,
+    videodev2, Errno, Error, Result,
+};
+
+/// How many times a frame is written by [`verify_roundtrip`] before giving up.
+const ROUNDTRIP_ATTEMPTS: usize = 10;
+
+ioctl_readwrite!(vidioc_querybuf, b'V', 9, videodev2::v4l2_buffer);
+
+/// Runtime status of a v4l2loopback device.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct DeviceStatus {
+    /// Number of file descriptors currently opened on `/dev/videoN`.
+    ///
+    /// This includes both producers and consumers.
+    pub openers: usize,
+}
+
+/// Fetch the runtime status of a device.
+///
+/// Openers are counted by looking for file descriptors pointing to `/dev/videoN` in `/proc`.
+/// Processes you are not allowed to inspect are skipped, so without root permissions this
+/// can only be considered a lower bound.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Other`] if `/proc` couldn't be read.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use std::fs::File;
+/// use v4l2loopback::{add_device, delete_device, device_status, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let file = File::open(format!("/dev/video{}", device_num)).unwrap();
+/// let status = device_status(device_num).expect("Error when fetching the status");
+/// assert_eq!(status.openers, 1);
+///
+/// drop(file);
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn device_status(device_num: u32) -> Result<DeviceStatus> {
+    let node = PathBuf::from(format!("/dev/video{}", device_num));
+    if !node.exists() {
+        return Err(Error::DeviceNotFound(device_num));
+    }
+
+    let processes = match fs::read_dir("/proc") {
+        Ok(dir) => dir,
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    let mut openers = 0;
+    for process in processes.flatten() {
+        let is_pid = process
+            .file_name()
+            .to_str()
+            .map(|name| name.bytes().all(|b| b.is_ascii_digit()))
+            .unwrap_or(false);
+        if !is_pid {
+            continue;
+        }
+
+        // We may not be allowed to look at other users' processes
+        let fds = match fs::read_dir(process.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        openers += fds
+            .flatten()
+            .filter(|fd| fs::read_link(fd.path()).is_ok_and(|target| target == node))
+            .count();
+    }
+
+    Ok(DeviceStatus { openers })
+}
+
+/// Wait until something opens a device, checking its status every `poll_interval`.
+///
+/// This lets a producer defer generating frames until a consumer actually watches the device.
+/// Returns `true` once the device has an opener, or `false` if there is still none after
+/// `timeout`. A timeout too long to be represented, like [`Duration::MAX`], waits forever.
+///
+/// Every opener is counted, see [`device_status`], so the producer should open the device
+/// only once this returns.
+///
+/// # Errors
+///
+/// See [`device_status`].
+///
+/// # Example
+///
+/// ```
+/// use std::{fs::File, thread, time::Duration};
+/// use v4l2loopback::{add_device, delete_device, wait_for_consumer, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let consumer = thread::spawn(move || File::open(format!("/dev/video{}", device_num)));
+/// let connected = wait_for_consumer(device_num, Duration::from_secs(5), Duration::from_millis(50))
+///     .expect("Error when fetching the status");
+/// assert!(connected);
+///
+/// drop(consumer.join());
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn wait_for_consumer(
+    device_num: u32,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<bool> {
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        if device_status(device_num)?.openers > 0 {
+            return Ok(true);
+        }
+
+        let now = Instant::now();
+        match deadline {
+            Some(deadline) if now >= deadline => return Ok(false),
+            Some(deadline) => thread::sleep(poll_interval.min(deadline - now)),
+            None => thread::sleep(poll_interval),
+        }
+    }
+}
+
+/// Check that a frame written to a device can be read back by consumers.
+///
+/// The frame is written on the output side and read on the capture side, and this returns
+/// whether the frame read is identical to the one written. This is a sanity check a producer
+/// can run when it starts, before relying on the device.
+///
+/// The frame must match the format of the device (see [`set_format`](crate::set_format)),
+/// since v4l2loopback passes frames as is. As it is read by this process, a consumer reading
+/// the device at the same time may see the frame too.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Other`] if the device couldn't be opened, written to or read from.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, delete_device, set_format, verify_roundtrip, DeviceConfig, Format, PixelFormat,
+///     Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+/// set_format(device_num, &format).expect("Error when setting the format");
+///
+/// let frame = vec![128; format.image_size().unwrap()];
+/// assert!(verify_roundtrip(device_num, &frame).expect("Error when checking the device"));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn verify_roundtrip(device_num: u32, frame: &[u8]) -> Result<bool> {
+    // Frames are only available to consumers once one was written
+    let mut output = open_video_device(device_num, true)?;
+    if let Err(e) = output.write_all(frame) {
+        return Err(Error::Other(Box::new(e)));
+    }
+    let mut capture = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(format!("/dev/video{}", device_num))
+    {
+        Ok(capture) => capture,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(Error::DeviceNotFound(device_num)),
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    // The frame written before opening the capture side may not be readable, so it is written
+    // again until a frame is read
+    let mut read = vec![0; frame.len()];
+    for _ in 0..ROUNDTRIP_ATTEMPTS {
+        match capture.read(&mut read) {
+            Ok(len) => return Ok(read[..len] == *frame),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(Error::Other(Box::new(e))),
+        }
+        if let Err(e) = output.write_all(frame) {
+            return Err(Error::Other(Box::new(e)));
+        }
+    }
+
+    Ok(false)
+}
+
+/// Informations about a frame written to a device.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FrameInfo {
+    /// Size of the frame, in bytes.
+    pub bytes_used: u32,
+    /// Sequence number of the frame, counting the frames written to the device.
+    pub sequence: u32,
+    /// When the frame was written, as set by v4l2loopback.
+    pub timestamp: Duration,
+}
+
+/// The most recent of the frames held in the buffers of a device.
+fn latest_frame(frames: impl IntoIterator<Item = FrameInfo>) -> Option<FrameInfo> {
+    frames
+        .into_iter()
+        .filter(|frame| frame.bytes_used > 0)
+        .max_by_key(|frame| frame.sequence)
+}
+
+/// Fetch informations about the last frame written to a device.
+///
+/// This looks at the buffers v4l2loopback keeps for the device, so a producer can confirm its
+/// frames actually land in the device. Returns [`None`] if no frame was written yet.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Other`] if the device couldn't be opened.
+/// - Any error from [`query_device`].
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, last_frame_info, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let info = last_frame_info(device_num).expect("Error when querying the buffers");
+/// assert_eq!(info, None);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn last_frame_info(device_num: u32) -> Result<Option<FrameInfo>> {
+    let buffers = query_device(device_num)?.max_buffers;
+    let file = open_video_device(device_num, false)?;
+
+    let mut frames = Vec::new();
+    for index in 0..buffers {
+        let mut buffer: videodev2::v4l2_buffer = unsafe { std::mem::zeroed() };
+        buffer.index = index;
+        buffer.type_ = videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT;
+        match unsafe { vidioc_querybuf(file.as_raw_fd(), &mut buffer) } {
+            Ok(_) => {}
+            // Fewer buffers are allocated than the device allows
+            Err(Errno::EINVAL) => break,
+            Err(e) => return Err(e.into()),
+        }
+
+        frames.push(FrameInfo {
+            bytes_used: buffer.bytesused,
+            sequence: buffer.sequence,
+            timestamp: Duration::new(
+                buffer.timestamp.tv_sec.try_into().unwrap_or(0),
+                (buffer.timestamp.tv_usec * 1000).try_into().unwrap_or(0),
+            ),
+        });
+    }
+
+    Ok(latest_frame(frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Write, thread, time::Duration};
+
+    use super::latest_frame;
+    use crate::{
+        add_device, delete_device, device_status, last_frame_info, set_format, verify_roundtrip,
+        wait_for_consumer, Error, Format, FrameInfo, PixelFormat, Resolution,
+    };
+
+    #[test]
+    fn openers_count() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let path = format!("/dev/video{}", device_num);
+
+        let status = device_status(device_num).expect("Error when fetching the status");
+        assert_eq!(status.openers, 0);
+
+        let first = File::open(&path).unwrap();
+        let second = File::open(&path).unwrap();
+        let status = device_status(device_num).expect("Error when fetching the status");
+        assert_eq!(status.openers, 2);
+
+        drop(first);
+        drop(second);
+        delete_device(device_num).expect("Error when removing device");
+        assert!(matches!(
+            device_status(device_num),
+            Err(Error::DeviceNotFound(n)) if n == device_num
+        ));
+    }
+
+    #[test]
+    fn wait_for_opener() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let interval = Duration::from_millis(10);
+        assert!(!wait_for_consumer(device_num, Duration::from_millis(50), interval).unwrap());
+
+        let path = format!("/dev/video{}", device_num);
+        let consumer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let file = File::open(path).unwrap();
+            thread::sleep(Duration::from_millis(200));
+            drop(file);
+        });
+        // Waiting forever doesn't overflow the deadline
+        assert!(wait_for_consumer(device_num, Duration::MAX, interval).unwrap());
+
+        consumer.join().unwrap();
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Rgb24, Resolution::new(32, 16));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let pattern: Vec<u8> = (0..format.image_size().unwrap())
+            .map(|i| (i % 256) as u8)
+            .collect();
+        assert!(verify_roundtrip(device_num, &pattern).expect("Error when checking the device"));
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn latest_of_buffers() {
+        let frame = |bytes_used, sequence| FrameInfo {
+            bytes_used,
+            sequence,
+            timestamp: Duration::ZERO,
+        };
+
+        assert_eq!(latest_frame([frame(0, 0), frame(0, 0)]), None);
+        assert_eq!(
+            latest_frame([frame(100, 4), frame(100, 5), frame(0, 9), frame(100, 3)]),
+            Some(frame(100, 5))
+        );
+    }
+
+    #[test]
+    fn sequence_advances() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+        let frame = vec![0; format.image_size().unwrap()];
+
+        let mut output = File::options()
+            .write(true)
+            .open(format!("/dev/video{}", device_num))
+            .unwrap();
+        output.write_all(&frame).unwrap();
+        let first = last_frame_info(device_num)
+            .expect("Error when querying the buffers")
+            .expect("A frame was written");
+        assert_eq!(first.bytes_used as usize, frame.len());
+
+        output.write_all(&frame).unwrap();
+        let second = last_frame_info(device_num)
+            .expect("Error when querying the buffers")
+            .expect("A frame was written");
+        assert!(second.sequence > first.sequence);
+
+        drop(output);
+        delete_device(device_num).expect("Error when removing device");
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::{video::open_video_device, Error, Format, Result};
+
+/// What [`write_frames_from`] does when the reader ends in the middle of a frame.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TrailingFrame {
+    /// Fail with [`Error::Other`], the frames before were written anyway.
+    #[default]
+    Error,
+    /// Drop the partial frame.
+    Ignore,
+}
+
+/// Read as much of `buf` as possible, returning how many bytes were read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(len) => filled += len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Copy frames of `frame_len` bytes from `reader` to `writer`, returning how many were copied.
+pub(crate) fn copy_frames(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    frame_len: usize,
+    trailing: TrailingFrame,
+) -> Result<u64> {
+    let mut frame = vec![0; frame_len];
+    let mut count = 0;
+    loop {
+        let len = match read_full(&mut reader, &mut frame) {
+            Ok(len) => len,
+            Err(e) => return Err(Error::Other(Box::new(e))),
+        };
+        if len == 0 {
+            return Ok(count);
+        }
+        if len < frame_len {
+            return match trailing {
+                TrailingFrame::Error => Err(Error::Other(
+                    format!(
+                        "The last frame is truncated: {} bytes instead of {}",
+                        len, frame_len
+                    )
+                    .into(),
+                )),
+                TrailingFrame::Ignore => Ok(count),
+            };
+        }
+
+        if let Err(e) = writer.write_all(&frame) {
+            return Err(Error::Other(Box::new(e)));
+        }
+        count += 1;
+    }
+}
+
+/// Write raw frames read from `reader` to a device, until the reader ends.
+///
+/// Frames are read one after the other, each one being [`Format::image_size`] bytes long, so
+/// a raw video file can be piped straight to a device. `format` must be the format of the
+/// device (see [`set_format`](crate::set_format)), since v4l2loopback passes frames as is.
+///
+/// Returns how many frames were written. If the reader ends in the middle of a frame, the
+/// partial frame is handled according to `trailing`.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Unsupported`] if frames in `format` don't have a fixed size, like MJPEG.
+/// - [`Other`] if the device couldn't be opened or written to, if the reader fails, or if the
+///   last frame is truncated and `trailing` is [`TrailingFrame::Error`].
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Unsupported`]: Error::Unsupported
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use std::io::Cursor;
+/// use v4l2loopback::{
+///     add_device, delete_device, set_format, write_frames_from, DeviceConfig, Format,
+///     PixelFormat, Resolution, TrailingFrame,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+/// set_format(device_num, &format).expect("Error when setting the format");
+///
+/// let video = Cursor::new(vec![128; 64 * 48 * 10]);
+/// let count = write_frames_from(device_num, video, &format, TrailingFrame::Error)
+///     .expect("Error when writing the frames");
+/// assert_eq!(count, 10);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn write_frames_from<R: Read>(
+    device_num: u32,
+    reader: R,
+    format: &Format,
+    trailing: TrailingFrame,
+) -> Result<u64> {
+    let Some(frame_len) = format.image_size() else {
+        return Err(Error::Unsupported("frames without a fixed size"));
+    };
+    let output = open_video_device(device_num, true)?;
+
+    copy_frames(reader, output, frame_len, trailing)
+}
+
+/// Write a frame held in a [`Bytes`](bytes::Bytes) buffer to a device, as a producer.
+///
+/// The frame is written straight from the buffer, so producers already holding their frames
+/// in `Bytes` don't need to copy them to a `Vec` first. It must have the size of the format of
+/// the device, see [`set_format`](crate::set_format).
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`FrameSize`] if the frame doesn't have the size of the format of the device.
+/// - [`Ioctl`] if the format of the device couldn't be read.
+/// - [`Other`] if the device couldn't be opened or written to.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`FrameSize`]: Error::FrameSize
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use bytes::Bytes;
+/// use v4l2loopback::{
+///     add_device, delete_device, set_format, write_bytes, DeviceConfig, Format, PixelFormat,
+///     Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+/// set_format(device_num, &format).expect("Error when setting the format");
+///
+/// let frame = Bytes::from(vec![128; 64 * 48]);
+/// write_bytes(device_num, &frame).expect("Error when writing the frame");
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+#[cfg(feature = "bytes")]
+pub fn write_bytes(device_num: u32, frame: &bytes::Bytes) -> Result<()> {
+    let mut output = open_video_device(device_num, true)?;
+    let format = crate::params::read_format(
+        &output,
+        crate::videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT,
+    )?;
+    if let Some(expected) = format.image_size() {
+        if frame.len() != expected {
+            return Err(Error::FrameSize {
+                expected,
+                actual: frame.len(),
+            });
+        }
+    }
+
+    output
+        .write_all(frame)
+        .map_err(|e| Error::Other(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{copy_frames, TrailingFrame};
+    use crate::Error;
+
+    #[test]
+    fn concatenated_frames() {
+        let frames: Vec<u8> = (0..3).flat_map(|i| [i; 4]).collect();
+
+        let mut written = Vec::new();
+        let count = copy_frames(Cursor::new(&frames), &mut written, 4, TrailingFrame::Error)
+            .expect("Error when copying the frames");
+        assert_eq!(count, 3);
+        assert_eq!(written, frames);
+
+        let truncated = &frames[..10];
+        let mut written = Vec::new();
+        let count = copy_frames(
+            Cursor::new(truncated),
+            &mut written,
+            4,
+            TrailingFrame::Ignore,
+        )
+        .expect("Error when copying the frames");
+        assert_eq!(count, 2);
+        assert_eq!(written, &frames[..8]);
+        assert!(matches!(
+            copy_frames(Cursor::new(truncated), Vec::new(), 4, TrailingFrame::Error),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_frame() {
+        use std::{fs::File, io::Read};
+
+        use bytes::Bytes;
+
+        use crate::{
+            add_device, delete_device, set_format, write_bytes, Format, PixelFormat, Resolution,
+        };
+
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        assert!(matches!(
+            write_bytes(device_num, &Bytes::from_static(&[0; 16])),
+            Err(Error::FrameSize {
+                expected: 3072,
+                actual: 16
+            })
+        ));
+        let frame = Bytes::from((0..64 * 48).map(|i| i as u8).collect::<Vec<u8>>());
+        let mut capture = File::open(format!("/dev/video{}", device_num)).unwrap();
+        write_bytes(device_num, &frame).expect("Error when writing the frame");
+
+        let mut captured = vec![0; 64 * 48];
+        capture
+            .read_exact(&mut captured)
+            .expect("Error when reading the frame");
+        assert_eq!(captured, &frame[..]);
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+}
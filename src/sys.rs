@@ -0,0 +1,318 @@
+//! System calls used by the crate, made through `nix` by default, or directly through `libc`
+//! with the `libc-backend` feature.
+
+#[cfg(not(any(feature = "nix", feature = "libc-backend")))]
+compile_error!("Either the `nix` or the `libc-backend` feature must be enabled");
+
+use std::{fmt, io};
+
+#[cfg(not(feature = "libc-backend"))]
+pub(crate) use nix::libc;
+#[cfg(not(feature = "libc-backend"))]
+pub(crate) use nix_backend::{
+    ioctl_read, ioctl_read_bad, ioctl_readwrite, ioctl_readwrite_bad, ioctl_write_int_bad,
+    ioctl_write_ptr,
+};
+
+#[cfg(feature = "libc-backend")]
+pub(crate) use ::libc;
+#[cfg(feature = "libc-backend")]
+pub(crate) use libc_backend::{
+    ioctl, ioctl_read, ioctl_read_bad, ioctl_readwrite, ioctl_readwrite_bad, ioctl_write_int_bad,
+    ioctl_write_ptr, request_code, READ, WRITE,
+};
+
+/// Error code returned by a system call, like `errno` in C.
+///
+/// This is the same type whichever backend makes the system calls. Only the codes this crate
+/// handles explicitly are named, any other code can be made with [`Errno::from_i32`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Errno(i32);
+
+impl Errno {
+    /// Operation not permitted.
+    pub const EPERM: Errno = Errno(libc::EPERM);
+    /// No such file or directory.
+    pub const ENOENT: Errno = Errno(libc::ENOENT);
+    /// Interrupted system call.
+    pub const EINTR: Errno = Errno(libc::EINTR);
+    /// Input/output error.
+    pub const EIO: Errno = Errno(libc::EIO);
+    /// Bad file descriptor.
+    pub const EBADF: Errno = Errno(libc::EBADF);
+    /// Resource temporarily unavailable.
+    pub const EAGAIN: Errno = Errno(libc::EAGAIN);
+    /// Out of memory.
+    pub const ENOMEM: Errno = Errno(libc::ENOMEM);
+    /// Permission denied.
+    pub const EACCES: Errno = Errno(libc::EACCES);
+    /// Device or resource busy.
+    pub const EBUSY: Errno = Errno(libc::EBUSY);
+    /// File exists.
+    pub const EEXIST: Errno = Errno(libc::EEXIST);
+    /// No such device.
+    pub const ENODEV: Errno = Errno(libc::ENODEV);
+    /// Invalid argument.
+    pub const EINVAL: Errno = Errno(libc::EINVAL);
+    /// Inappropriate ioctl for device.
+    pub const ENOTTY: Errno = Errno(libc::ENOTTY);
+    /// No space left on device.
+    pub const ENOSPC: Errno = Errno(libc::ENOSPC);
+    /// Function not implemented.
+    pub const ENOSYS: Errno = Errno(libc::ENOSYS);
+
+    /// The error code of the last failed system call of the current thread.
+    pub fn last() -> Errno {
+        Errno(io::Error::last_os_error().raw_os_error().unwrap_or(0))
+    }
+
+    /// The error matching a raw error code.
+    pub const fn from_i32(code: i32) -> Errno {
+        Errno(code)
+    }
+
+    /// The raw error code.
+    pub const fn as_i32(self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", io::Error::from_raw_os_error(self.0))
+    }
+}
+
+impl std::error::Error for Errno {}
+
+impl From<Errno> for io::Error {
+    fn from(e: Errno) -> Self {
+        io::Error::from_raw_os_error(e.0)
+    }
+}
+
+#[cfg(not(feature = "libc-backend"))]
+mod nix_backend {
+    // The macros below wrap their `nix` counterparts, converting the errors to the crate's
+    // [`Errno`](crate::Errno)
+
+    macro_rules! ioctl_read {
+        ($name:ident, $ty:expr, $nr:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                ::nix::ioctl_read!(inner, $ty, $nr, $data);
+                inner(fd, data).map_err(|e| $crate::Errno::from_i32(e as i32))
+            }
+        };
+    }
+
+    macro_rules! ioctl_readwrite {
+        ($name:ident, $ty:expr, $nr:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                ::nix::ioctl_readwrite!(inner, $ty, $nr, $data);
+                inner(fd, data).map_err(|e| $crate::Errno::from_i32(e as i32))
+            }
+        };
+    }
+
+    macro_rules! ioctl_write_ptr {
+        ($name:ident, $ty:expr, $nr:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *const $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                ::nix::ioctl_write_ptr!(inner, $ty, $nr, $data);
+                inner(fd, data).map_err(|e| $crate::Errno::from_i32(e as i32))
+            }
+        };
+    }
+
+    macro_rules! ioctl_read_bad {
+        ($name:ident, $request:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                ::nix::ioctl_read_bad!(inner, $request, $data);
+                inner(fd, data).map_err(|e| $crate::Errno::from_i32(e as i32))
+            }
+        };
+    }
+
+    macro_rules! ioctl_readwrite_bad {
+        ($name:ident, $request:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                ::nix::ioctl_readwrite_bad!(inner, $request, $data);
+                inner(fd, data).map_err(|e| $crate::Errno::from_i32(e as i32))
+            }
+        };
+    }
+
+    macro_rules! ioctl_write_int_bad {
+        ($name:ident, $request:expr) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: $crate::sys::libc::c_int,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                ::nix::ioctl_write_int_bad!(inner, $request);
+                inner(fd, data).map_err(|e| $crate::Errno::from_i32(e as i32))
+            }
+        };
+    }
+
+    pub(crate) use {
+        ioctl_read, ioctl_read_bad, ioctl_readwrite, ioctl_readwrite_bad, ioctl_write_int_bad,
+        ioctl_write_ptr,
+    };
+}
+
+#[cfg(feature = "libc-backend")]
+mod libc_backend {
+    use libc::c_int;
+
+    use crate::Errno;
+
+    /// Direction bits of an ioctl request, the same way as the `_IOC` macro from the kernel.
+    pub(crate) const WRITE: u32 = 1;
+    pub(crate) const READ: u32 = 2;
+
+    /// Build an ioctl request number, the same way as the `_IOC` macro from the kernel.
+    pub(crate) const fn request_code(dir: u32, ty: u8, nr: u8, size: usize) -> u32 {
+        (dir << 30) | ((size as u32) << 16) | ((ty as u32) << 8) | nr as u32
+    }
+
+    /// Call `ioctl`, turning a failure into the matching [`Errno`].
+    pub(crate) unsafe fn ioctl<T>(fd: c_int, request: u32, arg: T) -> Result<c_int, Errno> {
+        match libc::ioctl(fd, request as _, arg) {
+            -1 => Err(Errno::last()),
+            res => Ok(res),
+        }
+    }
+
+    // The macros below generate the same functions as their `nix` counterparts
+
+    macro_rules! ioctl_read {
+        ($name:ident, $ty:expr, $nr:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                let request = $crate::sys::request_code(
+                    $crate::sys::READ,
+                    $ty,
+                    $nr,
+                    ::std::mem::size_of::<$data>(),
+                );
+                $crate::sys::ioctl(fd, request, data)
+            }
+        };
+    }
+
+    macro_rules! ioctl_readwrite {
+        ($name:ident, $ty:expr, $nr:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                let request = $crate::sys::request_code(
+                    $crate::sys::READ | $crate::sys::WRITE,
+                    $ty,
+                    $nr,
+                    ::std::mem::size_of::<$data>(),
+                );
+                $crate::sys::ioctl(fd, request, data)
+            }
+        };
+    }
+
+    macro_rules! ioctl_write_ptr {
+        ($name:ident, $ty:expr, $nr:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *const $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                let request = $crate::sys::request_code(
+                    $crate::sys::WRITE,
+                    $ty,
+                    $nr,
+                    ::std::mem::size_of::<$data>(),
+                );
+                $crate::sys::ioctl(fd, request, data)
+            }
+        };
+    }
+
+    macro_rules! ioctl_read_bad {
+        ($name:ident, $request:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                $crate::sys::ioctl(fd, $request as u32, data)
+            }
+        };
+    }
+
+    macro_rules! ioctl_readwrite_bad {
+        ($name:ident, $request:expr, $data:ty) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: *mut $data,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                $crate::sys::ioctl(fd, $request as u32, data)
+            }
+        };
+    }
+
+    macro_rules! ioctl_write_int_bad {
+        ($name:ident, $request:expr) => {
+            pub unsafe fn $name(
+                fd: $crate::sys::libc::c_int,
+                data: $crate::sys::libc::c_int,
+            ) -> Result<$crate::sys::libc::c_int, $crate::Errno> {
+                $crate::sys::ioctl(fd, $request as u32, data)
+            }
+        };
+    }
+
+    pub(crate) use {
+        ioctl_read, ioctl_read_bad, ioctl_readwrite, ioctl_readwrite_bad, ioctl_write_int_bad,
+        ioctl_write_ptr,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ioctl_read, libc};
+    use crate::{videodev2, Errno};
+
+    #[test]
+    fn ioctl_errors() {
+        ioctl_read!(vidioc_querycap, b'V', 0, videodev2::v4l2_capability);
+
+        let mut caps: videodev2::v4l2_capability = unsafe { std::mem::zeroed() };
+        let res = unsafe { vidioc_querycap(-1, &mut caps) };
+        assert_eq!(res, Err(Errno::EBADF));
+        assert_eq!(Errno::from_i32(libc::EINVAL), Errno::EINVAL);
+        assert_eq!(Errno::EBADF.as_i32(), libc::EBADF);
+    }
+
+    #[cfg(feature = "libc-backend")]
+    #[test]
+    fn request_codes() {
+        use super::{request_code, READ, WRITE};
+
+        // VIDIOC_QUERYCAP, VIDIOC_S_PRIORITY and VIDIOC_S_FMT
+        assert_eq!(request_code(READ, b'V', 0, 104), 0x8068_5600);
+        assert_eq!(request_code(WRITE, b'V', 68, 4), 0x4004_5644);
+        assert_eq!(request_code(READ | WRITE, b'V', 5, 208), 0xc0d0_5605);
+    }
+}
@@ -0,0 +1,786 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{query_capabilities, query_device, Error, Format, PixelFormat, Resolution, Result};
+
+/// Where the kernel exposes video devices in sysfs.
+pub(crate) const VIDEO4LINUX_ROOT: &str = "/sys/class/video4linux";
+
+/// Informations the kernel exposes about a video device in `/sys/class/video4linux/videoN/`.
+///
+/// Those come from the kernel's side and not from the control device, so they can differ from
+/// what [`query_device`](crate::query_device) returns (e.g. if the device was created by
+/// `modprobe`).
+///
+/// Every attribute is optional, since their availability depends on the kernel version.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SysfsInfo {
+    /// Name of the device, from the `name` attribute.
+    pub name: Option<String>,
+    /// Major and minor numbers of the device node, from the `dev` attribute.
+    pub dev: Option<(u32, u32)>,
+    /// Index of the node for the underlying device, from the `index` attribute.
+    pub index: Option<u32>,
+}
+
+/// Runtime metrics v4l2loopback exposes about a device in `/sys/class/video4linux/videoN/`.
+///
+/// Every attribute is optional, since their availability depends on the v4l2loopback version.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct DeviceMetrics {
+    /// Name of the device, from the `name` attribute.
+    pub name: Option<String>,
+    /// Current format of the device (e.g. `YUYV:640x480@30`), from the `format` attribute.
+    pub format: Option<String>,
+    /// Number of buffers allocated, from the `buffers` attribute.
+    pub buffers: Option<u32>,
+    /// Limit of simultaneous openers, from the `max_openers` attribute.
+    pub max_openers: Option<u32>,
+}
+
+/// Path of the sysfs directory for `/dev/videoN`.
+pub(crate) fn device_dir(root: &Path, device_num: u32) -> PathBuf {
+    root.join(format!("video{}", device_num))
+}
+
+/// Numbers of all the video devices known to the kernel, in ascending order.
+///
+/// This includes devices which are not managed by v4l2loopback.
+pub(crate) fn video_numbers(root: &Path) -> Result<Vec<u32>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    let mut numbers: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("video")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    numbers.sort_unstable();
+
+    Ok(numbers)
+}
+
+/// Read an attribute file, without the trailing newline.
+///
+/// Returns [`None`] if the attribute doesn't exist or can't be read.
+pub(crate) fn read_attribute(dir: &Path, attribute: &str) -> Option<String> {
+    fs::read_to_string(dir.join(attribute))
+        .ok()
+        .map(|value| value.trim_end().to_string())
+}
+
+pub(crate) fn sysfs_info_in(root: &Path, device_num: u32) -> Result<SysfsInfo> {
+    let dir = device_dir(root, device_num);
+    if !dir.is_dir() {
+        return Err(Error::DeviceNotFound(device_num));
+    }
+
+    let dev = read_attribute(&dir, "dev").and_then(|dev| {
+        let (major, minor) = dev.split_once(':')?;
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    });
+
+    Ok(SysfsInfo {
+        name: read_attribute(&dir, "name"),
+        dev,
+        index: read_attribute(&dir, "index").and_then(|index| index.parse().ok()),
+    })
+}
+
+/// Read the metrics of every v4l2loopback device, scanning `root` only once.
+///
+/// Devices without any v4l2loopback attribute are not managed by v4l2loopback and are skipped,
+/// without reading their name.
+pub(crate) fn metrics_in(root: &Path) -> Result<BTreeMap<u32, DeviceMetrics>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+
+    let mut metrics = BTreeMap::new();
+    for entry in entries.flatten() {
+        let Some(device_num) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix("video")?.parse().ok())
+        else {
+            continue;
+        };
+
+        let dir = entry.path();
+        let mut device_metrics = DeviceMetrics {
+            name: None,
+            format: read_attribute(&dir, "format"),
+            buffers: read_attribute(&dir, "buffers").and_then(|value| value.parse().ok()),
+            max_openers: read_attribute(&dir, "max_openers").and_then(|value| value.parse().ok()),
+        };
+        let is_loopback = device_metrics.format.is_some()
+            || device_metrics.buffers.is_some()
+            || device_metrics.max_openers.is_some();
+        if is_loopback {
+            device_metrics.name = read_attribute(&dir, "name");
+            metrics.insert(device_num, device_metrics);
+        }
+    }
+
+    Ok(metrics)
+}
+
+/// Read the metrics of every v4l2loopback device from sysfs, by device number.
+///
+/// This is meant for polling many devices, like in a dashboard. The sysfs directory is listed
+/// once, then each video device costs one read per attribute (an `open`, a `read` and a
+/// `close`): 3 reads for devices not managed by v4l2loopback, which are recognized by their
+/// missing v4l2loopback attributes, and 4 for v4l2loopback devices. Reading the same values
+/// device by device costs the same attribute reads plus a lookup of each device directory, and
+/// finding the v4l2loopback devices with [`list_devices`](crate::list_devices) adds an open of
+/// the control device and an ioctl for each video device.
+///
+/// This doesn't need to open the control device, so it doesn't require any particular
+/// permissions.
+///
+/// # Errors
+///
+/// This function will return [`Other`] if the sysfs directory couldn't be read.
+///
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, metrics_all, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// let metrics = metrics_all().expect("Error when reading the metrics");
+/// assert!(metrics.contains_key(&device_num));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn metrics_all() -> Result<BTreeMap<u32, DeviceMetrics>> {
+    metrics_in(Path::new(VIDEO4LINUX_ROOT))
+}
+
+/// Read what the kernel exposes about a device in sysfs.
+///
+/// This doesn't need to open the control device, so it doesn't require any particular
+/// permission.
+///
+/// # Errors
+///
+/// This function will return [`DeviceNotFound`] if the kernel has no video device with this
+/// number.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, device_sysfs_info, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     label: "Test Device".to_string(),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// let info = device_sysfs_info(device_num).expect("Error when reading sysfs");
+/// assert_eq!(info.name.as_deref(), Some("Test Device"));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn device_sysfs_info(device_num: u32) -> Result<SysfsInfo> {
+    sysfs_info_in(Path::new(VIDEO4LINUX_ROOT), device_num)
+}
+
+pub(crate) fn device_index_in(root: &Path, device_num: u32) -> Result<u32> {
+    Ok(sysfs_info_in(root, device_num)?.index.unwrap_or(0))
+}
+
+/// Index of `/dev/videoN` among the video nodes of the same device, from the sysfs `index`
+/// attribute.
+///
+/// When a device has several nodes (for example for metadata), the primary node has the
+/// index 0. Kernels without this attribute create a single node per device, so 0 is
+/// returned in that case.
+///
+/// # Errors
+///
+/// See [`device_sysfs_info`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, device_index, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// assert_eq!(device_index(device_num).expect("Error when reading sysfs"), 0);
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn device_index(device_num: u32) -> Result<u32> {
+    device_index_in(Path::new(VIDEO4LINUX_ROOT), device_num)
+}
+
+/// Parse a `format` attribute like `YUYV:640x480@30`.
+fn parse_format(format: &str) -> Option<Format> {
+    let (fourcc, frame) = format.split_once(':')?;
+    let fourcc: [u8; 4] = fourcc.as_bytes().try_into().ok()?;
+    let size = frame.split_once('@').map_or(frame, |(size, _)| size);
+    let (width, height) = size.split_once('x')?;
+
+    Some(Format::new(
+        PixelFormat::from(u32::from_le_bytes(fourcc)),
+        Resolution::new(width.parse().ok()?, height.parse().ok()?),
+    ))
+}
+
+pub(crate) fn format_in(root: &Path, device_num: u32) -> Result<Option<Format>> {
+    let dir = device_dir(root, device_num);
+    if !dir.is_dir() {
+        return Err(Error::DeviceNotFound(device_num));
+    }
+
+    Ok(read_attribute(&dir, "format").and_then(|format| parse_format(&format)))
+}
+
+/// Read the format of a device from sysfs, without opening it.
+///
+/// Unlike [`get_format`](crate::get_format), this doesn't need the permission to open
+/// `/dev/videoN`, so unprivileged monitors can show it. Only the pixel format and the
+/// resolution are exposed, the colorimetry is the default one of [`Format::new`].
+///
+/// Returns [`None`] if no format was negotiated yet, or if the v4l2loopback version doesn't
+/// expose the format.
+///
+/// # Errors
+///
+/// This function will return [`DeviceNotFound`] if the kernel has no video device with this
+/// number.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, current_format_sysfs, delete_device, set_format, DeviceConfig, Format,
+///     PixelFormat, Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let format = Format::new(PixelFormat::Yuyv, Resolution::new(640, 480));
+/// set_format(device_num, &format).expect("Error when setting the format");
+///
+/// if let Some(current) = current_format_sysfs(device_num).expect("Error when reading sysfs") {
+///     println!("Streaming {:?} at {:?}", current.pixel_format, current.resolution);
+/// }
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn current_format_sysfs(device_num: u32) -> Result<Option<Format>> {
+    format_in(Path::new(VIDEO4LINUX_ROOT), device_num)
+}
+
+/// Whether sysfs shows a device is managed by v4l2loopback, [`None`] if it can't tell.
+pub(crate) fn is_loopback_in(root: &Path, device_num: u32) -> Result<Option<bool>> {
+    let dir = device_dir(root, device_num);
+    if !dir.is_dir() {
+        return Err(Error::DeviceNotFound(device_num));
+    }
+
+    // Only v4l2loopback exposes those attributes
+    if ["format", "buffers", "max_openers"]
+        .iter()
+        .any(|attribute| dir.join(attribute).exists())
+    {
+        return Ok(Some(true));
+    }
+    // v4l2loopback devices have no parent device, so a bound driver is another one
+    if let Ok(driver) = fs::read_link(dir.join("device/driver")) {
+        return Ok(Some(
+            driver
+                .file_name()
+                .is_some_and(|name| name == "v4l2loopback"),
+        ));
+    }
+
+    Ok(None)
+}
+
+/// Whether a video device is managed by v4l2loopback, checked from sysfs.
+///
+/// Unlike [`query_device`], this doesn't need to open the control device, so it doesn't
+/// require any particular permission. Only when sysfs doesn't tell (for example with old
+/// v4l2loopback versions), the device is queried through the control device instead.
+///
+/// # Errors
+///
+/// This function will return [`DeviceNotFound`] if the kernel has no video device with this
+/// number, or the errors of [`query_device`] when sysfs is inconclusive.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, is_loopback_device_sysfs, DeviceConfig};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// assert!(is_loopback_device_sysfs(device_num).expect("Error when reading sysfs"));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn is_loopback_device_sysfs(device_num: u32) -> Result<bool> {
+    match is_loopback_in(Path::new(VIDEO4LINUX_ROOT), device_num)? {
+        Some(is_loopback) => Ok(is_loopback),
+        None => match query_device(device_num) {
+            Ok(_) => Ok(true),
+            Err(Error::DeviceNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// What manages a video node, see [`enumerate_video_nodes`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum NodeKind {
+    /// A virtual device managed by v4l2loopback.
+    Loopback,
+    /// A device managed by another driver, usually an actual camera.
+    Hardware,
+    /// The node couldn't be identified, for example because it can't be opened.
+    Unknown,
+}
+
+/// A video node, see [`enumerate_video_nodes`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VideoNode {
+    /// Number of the node, `N` in `/dev/videoN`.
+    pub number: u32,
+    /// What manages the node.
+    pub kind: NodeKind,
+    /// Name of the device, which is the label for v4l2loopback devices.
+    pub name: Option<String>,
+}
+
+/// List the video nodes in `root`, classifying those sysfs can't tell with `fallback`.
+pub(crate) fn video_nodes_in(
+    root: &Path,
+    fallback: impl Fn(u32) -> NodeKind,
+) -> Result<Vec<VideoNode>> {
+    let mut nodes = Vec::new();
+    for number in video_numbers(root)? {
+        let kind = match is_loopback_in(root, number) {
+            Ok(Some(true)) => NodeKind::Loopback,
+            Ok(Some(false)) => NodeKind::Hardware,
+            Ok(None) => fallback(number),
+            // Removed while listing the nodes
+            Err(Error::DeviceNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        nodes.push(VideoNode {
+            number,
+            kind,
+            name: read_attribute(&device_dir(root, number), "name"),
+        });
+    }
+
+    Ok(nodes)
+}
+
+/// List every video node of the system, marking which ones are managed by v4l2loopback.
+///
+/// Nodes are classified from sysfs, like [`is_loopback_device_sysfs`], and from the driver
+/// name reported by [`query_capabilities`] when sysfs doesn't tell. Nodes which can't be
+/// queried either are [`Unknown`](NodeKind::Unknown). Nodes are sorted by number.
+///
+/// # Errors
+///
+/// This function will return [`Other`] if the sysfs directory couldn't be read.
+///
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{enumerate_video_nodes, NodeKind};
+///
+/// for node in enumerate_video_nodes().expect("Error when listing the video nodes") {
+///     let kind = match node.kind {
+///         NodeKind::Loopback => "virtual",
+///         NodeKind::Hardware => "camera",
+///         NodeKind::Unknown => "unknown",
+///     };
+///     println!("/dev/video{} ({}): {:?}", node.number, kind, node.name);
+/// }
+/// ```
+pub fn enumerate_video_nodes() -> Result<Vec<VideoNode>> {
+    video_nodes_in(
+        Path::new(VIDEO4LINUX_ROOT),
+        |number| match query_capabilities(number) {
+            Ok(caps) if caps.is_loopback() => NodeKind::Loopback,
+            Ok(_) => NodeKind::Hardware,
+            Err(_) => NodeKind::Unknown,
+        },
+    )
+}
+
+/// Stable identifiers of the v4l2loopback devices among `nodes`, with their numbers.
+///
+/// The identifier is the label, followed by the position of the device among the devices with
+/// the same label, in ascending order of numbers.
+pub(crate) fn stable_ids(nodes: &[VideoNode]) -> Vec<(u32, String)> {
+    let mut seen: BTreeMap<&str, u32> = BTreeMap::new();
+    nodes
+        .iter()
+        .filter(|node| node.kind == NodeKind::Loopback)
+        .map(|node| {
+            let label = node.name.as_deref().unwrap_or_default();
+            let position = seen.entry(label).or_default();
+            *position += 1;
+            (node.number, format!("v4l2loopback:{}#{}", label, position))
+        })
+        .collect()
+}
+
+/// Identifier of a v4l2loopback device which doesn't depend on its number.
+///
+/// Device numbers change across reboots, or when devices are created in another order, so
+/// this can be stored instead to find the device again with [`find_by_stable_id`].
+///
+/// The identifier is derived from the label of the device and, when several devices share
+/// it, from its position among them. It stays the same as long as the device is recreated
+/// with the same label, whatever its number, but it changes with the label, and devices with
+/// the same label can swap identifiers if they are recreated in a different order. Giving
+/// distinct labels to the devices makes the identifier reliable.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if there is no v4l2loopback device with this number.
+/// - [`Other`] if the sysfs directory couldn't be read.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Other`]: Error::Other
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, device_stable_id, find_by_stable_id, DeviceConfig};
+///
+/// let device_config = DeviceConfig {
+///     label: "Stable Device".to_string(),
+///     ..DeviceConfig::default()
+/// };
+/// let device_num = add_device(None, device_config).expect("Error when creating the device");
+///
+/// let id = device_stable_id(device_num).expect("Error when identifying the device");
+/// assert_eq!(find_by_stable_id(&id).unwrap(), Some(device_num));
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn device_stable_id(device_num: u32) -> Result<String> {
+    stable_ids(&enumerate_video_nodes()?)
+        .into_iter()
+        .find(|(number, _)| *number == device_num)
+        .map(|(_, id)| id)
+        .ok_or(Error::DeviceNotFound(device_num))
+}
+
+/// Number of the v4l2loopback device with a [stable identifier](device_stable_id), [`None`] if
+/// there is none.
+///
+/// # Errors
+///
+/// This function will return [`Other`] if the sysfs directory couldn't be read.
+///
+/// [`Other`]: Error::Other
+pub fn find_by_stable_id(id: &str) -> Result<Option<u32>> {
+    Ok(stable_ids(&enumerate_video_nodes()?)
+        .into_iter()
+        .find(|(_, stable_id)| stable_id == id)
+        .map(|(number, _)| number))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use super::{
+        device_dir, device_index_in, format_in, is_loopback_in, metrics_in, stable_ids,
+        sysfs_info_in, video_nodes_in,
+    };
+    use crate::{
+        add_device, delete_device, device_index, device_stable_id, device_sysfs_info,
+        find_by_stable_id, DeviceConfig, DeviceMetrics, Error, Format, NodeKind, PixelFormat,
+        Resolution, VideoNode,
+    };
+
+    /// A fake sysfs tree, removed when dropped.
+    pub(crate) struct FakeSysfs(PathBuf);
+
+    impl FakeSysfs {
+        pub(crate) fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "v4l2loopback-sysfs-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        pub(crate) fn root(&self) -> &Path {
+            &self.0
+        }
+
+        /// Create `videoN` with the given attributes.
+        pub(crate) fn add_device(&self, device_num: u32, attributes: &[(&str, &str)]) {
+            let dir = device_dir(&self.0, device_num);
+            fs::create_dir_all(&dir).unwrap();
+            for (attribute, value) in attributes {
+                let path = dir.join(attribute);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, format!("{}\n", value)).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn info_of_created_device() {
+        let config = DeviceConfig {
+            label: "Sysfs Device".to_string(),
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+
+        let info = device_sysfs_info(device_num).expect("Error when reading sysfs");
+        assert_eq!(info.name.as_deref(), Some("Sysfs Device"));
+        assert!(info.dev.is_some());
+        assert_eq!(info.index, Some(0));
+
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn missing_attributes() {
+        let sysfs = FakeSysfs::new("missing_attributes");
+        sysfs.add_device(3, &[("name", "Old Kernel"), ("dev", "81:3")]);
+
+        let info = sysfs_info_in(sysfs.root(), 3).expect("Error when reading sysfs");
+        assert_eq!(info.name.as_deref(), Some("Old Kernel"));
+        assert_eq!(info.dev, Some((81, 3)));
+        assert_eq!(info.index, None);
+
+        assert!(matches!(
+            sysfs_info_in(sysfs.root(), 4),
+            Err(Error::DeviceNotFound(4))
+        ));
+    }
+
+    #[test]
+    fn node_index() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        assert_eq!(
+            device_index(device_num).expect("Error when reading sysfs"),
+            0
+        );
+        delete_device(device_num).expect("Error when removing device");
+
+        let sysfs = FakeSysfs::new("node_index");
+        sysfs.add_device(2, &[("index", "1")]);
+        sysfs.add_device(3, &[("name", "Old Kernel")]);
+        assert_eq!(device_index_in(sysfs.root(), 2).unwrap(), 1);
+        assert_eq!(device_index_in(sysfs.root(), 3).unwrap(), 0);
+    }
+
+    #[test]
+    fn format_attribute() {
+        let sysfs = FakeSysfs::new("format_attribute");
+        sysfs.add_device(0, &[("format", "YUYV:640x480@30")]);
+        sysfs.add_device(1, &[("format", "")]);
+        sysfs.add_device(2, &[("name", "Old Loopback")]);
+        sysfs.add_device(3, &[("format", "NV12:1280x720")]);
+
+        assert_eq!(
+            format_in(sysfs.root(), 0).unwrap(),
+            Some(Format::new(PixelFormat::Yuyv, Resolution::new(640, 480)))
+        );
+        assert_eq!(format_in(sysfs.root(), 1).unwrap(), None);
+        assert_eq!(format_in(sysfs.root(), 2).unwrap(), None);
+        assert_eq!(
+            format_in(sysfs.root(), 3).unwrap(),
+            Some(Format::new(PixelFormat::Nv12, Resolution::new(1280, 720)))
+        );
+        assert!(matches!(
+            format_in(sysfs.root(), 4),
+            Err(Error::DeviceNotFound(4))
+        ));
+    }
+
+    #[test]
+    fn loopback_from_sysfs() {
+        let sysfs = FakeSysfs::new("loopback_from_sysfs");
+        sysfs.add_device(0, &[("name", "Loopback"), ("buffers", "2")]);
+        sysfs.add_device(1, &[("name", "Integrated Webcam")]);
+        let driver = sysfs.root().join("drivers/uvcvideo");
+        fs::create_dir_all(&driver).unwrap();
+        fs::create_dir_all(device_dir(sysfs.root(), 1).join("device")).unwrap();
+        std::os::unix::fs::symlink(&driver, device_dir(sysfs.root(), 1).join("device/driver"))
+            .unwrap();
+        sysfs.add_device(2, &[("name", "Old Loopback")]);
+
+        assert_eq!(is_loopback_in(sysfs.root(), 0).unwrap(), Some(true));
+        assert_eq!(is_loopback_in(sysfs.root(), 1).unwrap(), Some(false));
+        // Inconclusive, the control device has to be queried
+        assert_eq!(is_loopback_in(sysfs.root(), 2).unwrap(), None);
+        assert!(matches!(
+            is_loopback_in(sysfs.root(), 3),
+            Err(Error::DeviceNotFound(3))
+        ));
+    }
+
+    #[test]
+    fn classified_nodes() {
+        let sysfs = FakeSysfs::new("classified_nodes");
+        sysfs.add_device(0, &[("name", "Integrated Webcam")]);
+        let driver = sysfs.root().join("drivers/uvcvideo");
+        fs::create_dir_all(&driver).unwrap();
+        fs::create_dir_all(device_dir(sysfs.root(), 0).join("device")).unwrap();
+        std::os::unix::fs::symlink(&driver, device_dir(sysfs.root(), 0).join("device/driver"))
+            .unwrap();
+        sysfs.add_device(2, &[("name", "Virtual Camera"), ("max_openers", "10")]);
+        sysfs.add_device(3, &[("name", "Old Loopback")]);
+        sysfs.add_device(4, &[]);
+
+        // Only the nodes sysfs can't tell are queried
+        let nodes = video_nodes_in(sysfs.root(), |number| match number {
+            3 => NodeKind::Loopback,
+            4 => NodeKind::Unknown,
+            _ => panic!("/dev/video{} shouldn't be queried", number),
+        })
+        .expect("Error when reading sysfs");
+
+        let node = |number, kind, name: Option<&str>| VideoNode {
+            number,
+            kind,
+            name: name.map(str::to_string),
+        };
+        assert_eq!(
+            nodes,
+            [
+                node(0, NodeKind::Hardware, Some("Integrated Webcam")),
+                node(2, NodeKind::Loopback, Some("Virtual Camera")),
+                node(3, NodeKind::Loopback, Some("Old Loopback")),
+                node(4, NodeKind::Unknown, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn metrics_of_all_devices() {
+        let sysfs = FakeSysfs::new("metrics_of_all_devices");
+        sysfs.add_device(
+            0,
+            &[
+                ("name", "Loopback"),
+                ("format", "YUYV:640x480@30"),
+                ("buffers", "2"),
+                ("max_openers", "10"),
+            ],
+        );
+        sysfs.add_device(2, &[("name", "Old Loopback"), ("buffers", "8")]);
+        sysfs.add_device(4, &[("name", "Integrated Webcam"), ("index", "0")]);
+
+        let metrics = metrics_in(sysfs.root()).expect("Error when reading sysfs");
+        assert_eq!(metrics.keys().copied().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(
+            metrics[&0],
+            DeviceMetrics {
+                name: Some("Loopback".to_string()),
+                format: Some("YUYV:640x480@30".to_string()),
+                buffers: Some(2),
+                max_openers: Some(10),
+            }
+        );
+        assert_eq!(
+            metrics[&2],
+            DeviceMetrics {
+                name: Some("Old Loopback".to_string()),
+                buffers: Some(8),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn stable_id_after_renumbering() {
+        let node = |number, kind, name: &str| VideoNode {
+            number,
+            kind,
+            name: Some(name.to_string()),
+        };
+        let before = stable_ids(&[
+            node(0, NodeKind::Hardware, "Integrated Webcam"),
+            node(2, NodeKind::Loopback, "Camera"),
+            node(3, NodeKind::Loopback, "Camera"),
+            node(4, NodeKind::Loopback, "Screen"),
+        ]);
+        assert_eq!(
+            before,
+            [
+                (2, "v4l2loopback:Camera#1".to_string()),
+                (3, "v4l2loopback:Camera#2".to_string()),
+                (4, "v4l2loopback:Screen#1".to_string()),
+            ]
+        );
+
+        let after = stable_ids(&[
+            node(10, NodeKind::Loopback, "Screen"),
+            node(11, NodeKind::Loopback, "Camera"),
+        ]);
+        assert_eq!(after[0], (10, "v4l2loopback:Screen#1".to_string()));
+    }
+
+    #[test]
+    fn stable_id_of_recreated_device() {
+        let config = DeviceConfig {
+            label: "Stable Id Device".to_string(),
+            ..Default::default()
+        };
+        let device_num = add_device(None, config.clone()).expect("Error when creating the device");
+        let id = device_stable_id(device_num).expect("Error when identifying the device");
+        delete_device(device_num).expect("Error when removing device");
+        assert_eq!(find_by_stable_id(&id).unwrap(), None);
+
+        let device_num = add_device(None, config).expect("Error when creating the device");
+        assert_eq!(device_stable_id(device_num).unwrap(), id);
+        assert_eq!(find_by_stable_id(&id).unwrap(), Some(device_num));
+        delete_device(device_num).expect("Error when removing device");
+    }
+}
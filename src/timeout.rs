@@ -0,0 +1,67 @@
+use std::{
+    os::fd::{AsRawFd, BorrowedFd, RawFd},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use crate::{Error, Result};
+
+/// Run a blocking system call on `fd`, giving up after `timeout` if there is one.
+///
+/// With a timeout, the call runs on a watchdog thread with a duplicate of `fd`, and the thread
+/// is abandoned if the call doesn't complete in time. The call may still complete later, so
+/// its effect (like a created device) can show up after the timeout was reported.
+pub(crate) fn with_timeout<T: Send + 'static>(
+    timeout: Option<Duration>,
+    fd: &impl AsRawFd,
+    call: impl FnOnce(RawFd) -> T + Send + 'static,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return Ok(call(fd.as_raw_fd()));
+    };
+
+    // The caller may close its file descriptor while the call is still running
+    let fd = match unsafe { BorrowedFd::borrow_raw(fd.as_raw_fd()) }.try_clone_to_owned() {
+        Ok(fd) => fd,
+        Err(e) => return Err(Error::Other(Box::new(e))),
+    };
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // Nobody is listening anymore if the call timed out
+        let _ = sender.send(call(fd.as_raw_fd()));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => Ok(result),
+        Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(timeout)),
+        Err(RecvTimeoutError::Disconnected) => Err(Error::Other("The system call panicked".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, thread, time::Duration};
+
+    use super::with_timeout;
+    use crate::Error;
+
+    #[test]
+    fn slow_call_times_out() {
+        let file = File::open("/dev/null").unwrap();
+        let slow = |_| {
+            thread::sleep(Duration::from_millis(200));
+            1
+        };
+
+        assert!(matches!(
+            with_timeout(Some(Duration::from_millis(20)), &file, slow),
+            Err(Error::Timeout(timeout)) if timeout == Duration::from_millis(20)
+        ));
+        assert_eq!(
+            with_timeout(Some(Duration::from_secs(5)), &file, slow).unwrap(),
+            1
+        );
+        assert_eq!(with_timeout(None, &file, slow).unwrap(), 1);
+    }
+}
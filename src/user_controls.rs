@@ -0,0 +1,242 @@
+use std::{fs::File, os::fd::AsRawFd};
+
+use crate::{
+    settings, sys::ioctl_readwrite, video::open_video_device, videodev2, Errno, Error, Result,
+};
+
+ioctl_readwrite!(vidioc_g_ctrl, b'V', 27, videodev2::v4l2_control);
+ioctl_readwrite!(vidioc_s_ctrl, b'V', 28, videodev2::v4l2_control);
+ioctl_readwrite!(vidioc_queryctrl, b'V', 36, videodev2::v4l2_queryctrl);
+
+/// Range of values accepted by an integer control.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct ControlRange {
+    minimum: i32,
+    maximum: i32,
+    step: i32,
+}
+
+impl ControlRange {
+    /// Map a value of the control to `0.0..=1.0`.
+    fn normalize(&self, value: i32) -> f64 {
+        if self.maximum <= self.minimum {
+            return 0.0;
+        }
+
+        let value = value.clamp(self.minimum, self.maximum);
+        f64::from(value - self.minimum) / f64::from(self.maximum - self.minimum)
+    }
+
+    /// Map a value in `0.0..=1.0` to the closest value accepted by the control.
+    fn denormalize(&self, value: f64) -> i32 {
+        let span = f64::from(self.maximum) - f64::from(self.minimum);
+        let step = f64::from(self.step.max(1));
+        let steps = (value.clamp(0.0, 1.0) * span / step).round();
+
+        (f64::from(self.minimum) + steps * step).min(f64::from(self.maximum)) as i32
+    }
+}
+
+/// Fetch the range of control `id`, named `name` in errors.
+fn query_range(file: &File, id: u32, name: &'static str) -> Result<ControlRange> {
+    let mut query: videodev2::v4l2_queryctrl = unsafe { std::mem::zeroed() };
+    query.id = id;
+    match unsafe { vidioc_queryctrl(file.as_raw_fd(), &mut query) } {
+        Ok(_) if query.flags & videodev2::V4L2_CTRL_FLAG_DISABLED == 0 => {}
+        // The control doesn't exist, or the device has no controls at all
+        Ok(_) | Err(Errno::EINVAL | Errno::ENOTTY) => return Err(Error::Unsupported(name)),
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(ControlRange {
+        minimum: query.minimum,
+        maximum: query.maximum,
+        step: query.step,
+    })
+}
+
+fn get_normalized(device_num: u32, id: u32, name: &'static str) -> Result<f64> {
+    let file = open_video_device(device_num, false)?;
+    let range = query_range(&file, id, name)?;
+
+    let mut control = videodev2::v4l2_control { id, value: 0 };
+    unsafe { vidioc_g_ctrl(file.as_raw_fd(), &mut control) }?;
+
+    Ok(range.normalize(control.value))
+}
+
+fn set_normalized(device_num: u32, id: u32, name: &'static str, value: f64) -> Result<()> {
+    if settings().dry_run {
+        log::info!(
+            "[dry run] Would set the {} of /dev/video{} to {}",
+            name,
+            device_num,
+            value.clamp(0.0, 1.0)
+        );
+        return Ok(());
+    }
+
+    let file = open_video_device(device_num, false)?;
+    let range = query_range(&file, id, name)?;
+
+    let mut control = videodev2::v4l2_control {
+        id,
+        value: range.denormalize(value),
+    };
+    unsafe { vidioc_s_ctrl(file.as_raw_fd(), &mut control) }?;
+
+    Ok(())
+}
+
+/// `keep_format` control of v4l2loopback, which keeps the format and the buffers of a device
+/// once its producer closes it.
+const CID_KEEP_FORMAT: u32 = videodev2::V4L2_CID_USER_BASE | 0xf000;
+
+/// Enable or disable the `keep_format` control on the device opened as `file`.
+pub(crate) fn set_keep_format(file: &File, keep: bool) -> Result<()> {
+    query_range(file, CID_KEEP_FORMAT, "keep_format control")?;
+
+    let mut control = videodev2::v4l2_control {
+        id: CID_KEEP_FORMAT,
+        value: keep.into(),
+    };
+    unsafe { vidioc_s_ctrl(file.as_raw_fd(), &mut control) }?;
+
+    Ok(())
+}
+
+/// Fetch the brightness of a device, between 0.0 and 1.0.
+///
+/// The value is mapped from the range the device reports for the `V4L2_CID_BRIGHTNESS`
+/// control, so it can be shown as a slider whatever the range.
+///
+/// # Errors
+///
+/// This function will return the following errors:
+/// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+/// - [`Unsupported`] if the device doesn't have the control.
+/// - [`Ioctl`] if the underlying ioctl call fails
+/// - [`Other`] if the device couldn't be opened.
+///
+/// [`DeviceNotFound`]: Error::DeviceNotFound
+/// [`Unsupported`]: Error::Unsupported
+/// [`Ioctl`]: Error::Ioctl
+/// [`Other`]: Error::Other
+pub fn get_brightness(device_num: u32) -> Result<f64> {
+    get_normalized(
+        device_num,
+        videodev2::V4L2_CID_BRIGHTNESS,
+        "brightness control",
+    )
+}
+
+/// Change the brightness of a device, between 0.0 and 1.0.
+///
+/// The value is clamped to `0.0..=1.0`, and rounded to the closest value accepted by the
+/// control.
+///
+/// # Errors
+///
+/// See [`get_brightness`].
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{add_device, delete_device, get_brightness, set_brightness, DeviceConfig, Error};
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+///
+/// match set_brightness(device_num, 0.5) {
+///     Ok(()) => println!("Brightness: {}", get_brightness(device_num).unwrap()),
+///     Err(Error::Unsupported(_)) => println!("The device has no brightness control"),
+///     Err(e) => panic!("Error when setting the brightness: {}", e),
+/// }
+///
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub fn set_brightness(device_num: u32, value: f64) -> Result<()> {
+    set_normalized(
+        device_num,
+        videodev2::V4L2_CID_BRIGHTNESS,
+        "brightness control",
+        value,
+    )
+}
+
+/// Fetch the contrast of a device, between 0.0 and 1.0, see [`get_brightness`].
+pub fn get_contrast(device_num: u32) -> Result<f64> {
+    get_normalized(device_num, videodev2::V4L2_CID_CONTRAST, "contrast control")
+}
+
+/// Change the contrast of a device, between 0.0 and 1.0, see [`set_brightness`].
+pub fn set_contrast(device_num: u32, value: f64) -> Result<()> {
+    set_normalized(
+        device_num,
+        videodev2::V4L2_CID_CONTRAST,
+        "contrast control",
+        value,
+    )
+}
+
+/// Fetch the saturation of a device, between 0.0 and 1.0, see [`get_brightness`].
+pub fn get_saturation(device_num: u32) -> Result<f64> {
+    get_normalized(
+        device_num,
+        videodev2::V4L2_CID_SATURATION,
+        "saturation control",
+    )
+}
+
+/// Change the saturation of a device, between 0.0 and 1.0, see [`set_brightness`].
+pub fn set_saturation(device_num: u32, value: f64) -> Result<()> {
+    set_normalized(
+        device_num,
+        videodev2::V4L2_CID_SATURATION,
+        "saturation control",
+        value,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ControlRange;
+    use crate::{get_brightness, set_brightness, Device, DeviceConfig, Error};
+
+    #[test]
+    fn normalized_values() {
+        let range = ControlRange {
+            minimum: -64,
+            maximum: 64,
+            step: 1,
+        };
+        assert_eq!(range.normalize(-64), 0.0);
+        assert_eq!(range.normalize(0), 0.5);
+        assert_eq!(range.normalize(100), 1.0);
+        assert_eq!(range.denormalize(0.5), 0);
+        assert_eq!(range.denormalize(1.5), 64);
+
+        let stepped = ControlRange {
+            minimum: 0,
+            maximum: 255,
+            step: 10,
+        };
+        assert_eq!(stepped.denormalize(0.5), 130);
+        assert_eq!(stepped.denormalize(1.0), 255);
+    }
+
+    #[test]
+    fn brightness_round_trip() {
+        let device =
+            Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+
+        match set_brightness(device.number().as_u32(), 0.25) {
+            Ok(()) => {
+                let brightness = get_brightness(device.number().as_u32())
+                    .expect("Error when reading the brightness");
+                assert!((brightness - 0.25).abs() < 0.05);
+            }
+            Err(Error::Unsupported(_)) => {}
+            Err(e) => panic!("Error when setting the brightness: {}", e),
+        }
+    }
+}
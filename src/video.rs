@@ -0,0 +1,247 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read, Write},
+    ops::Deref,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+};
+
+use crate::{params::read_format, sys::libc, videodev2, DeviceNumber, Error, Result};
+
+/// Open the video device `/dev/videoN`.
+///
+/// The device is always opened for reading, and also for writing if `write` is `true`.
+pub(crate) fn open_video_device(device_num: u32, write: bool) -> Result<File> {
+    match OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(format!("/dev/video{}", device_num))
+    {
+        Ok(f) => Ok(f),
+        Err(e) => match e.kind() {
+            ErrorKind::NotFound => Err(Error::DeviceNotFound(device_num)),
+            _ => Err(Error::Other(Box::new(e))),
+        },
+    }
+}
+
+/// Side of a device a [`DeviceFile`] was opened for.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Direction {
+    /// Opened by a producer, writing frames.
+    Output,
+    /// Opened by a consumer, reading frames.
+    Capture,
+}
+
+/// Video device node opened through a [`Device`](crate::Device), see
+/// [`Device::open_output`](crate::Device::open_output).
+///
+/// It reads and writes frames like the underlying [`File`], and remembers which device it
+/// belongs to and which side it was opened for.
+#[derive(Debug)]
+pub struct DeviceFile {
+    file: File,
+    num: u32,
+    direction: Direction,
+}
+
+impl DeviceFile {
+    /// Open `/dev/videoN` for `direction`.
+    pub(crate) fn open(device_num: u32, direction: Direction) -> Result<Self> {
+        let file = open_video_device(device_num, direction == Direction::Output)?;
+
+        Ok(Self {
+            file,
+            num: device_num,
+            direction,
+        })
+    }
+
+    /// The number of the device, `N` in `/dev/videoN`.
+    pub fn number(&self) -> DeviceNumber {
+        self.num.into()
+    }
+
+    /// The side of the device the node was opened for.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The underlying file.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Take the underlying file.
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// Write a black frame to the device, for example to hide the picture without deleting
+    /// the device.
+    ///
+    /// The frame has the format negotiated with the device (see
+    /// [`get_format`](crate::get_format)), and is black in its pixel format: YUV formats aren't
+    /// black when all zeros, since their chroma is centered on 128 and their luma starts at 16
+    /// in limited range. Like any frame written by a producer, consumers keep getting it until
+    /// the next frame, or until the output is closed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`Unsupported`] if the file was opened as a capture, or if the negotiated pixel
+    ///   format isn't known by this crate.
+    /// - [`Ioctl`] if the format couldn't be read.
+    /// - [`Other`] if the frame couldn't be written.
+    ///
+    /// [`Unsupported`]: Error::Unsupported
+    /// [`Ioctl`]: Error::Ioctl
+    /// [`Other`]: Error::Other
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{set_format, Device, DeviceConfig, Format, PixelFormat, Resolution};
+    ///
+    /// let device = Device::new(None, DeviceConfig::default()).expect("Error when creating the device");
+    /// let format = Format::new(PixelFormat::Yuyv, Resolution::new(1280, 720));
+    /// set_format(device.number().as_u32(), &format).expect("Error when setting the format");
+    ///
+    /// // Pause the camera
+    /// let output = device.open_output().expect("Error when opening the output");
+    /// output.write_black_frame().expect("Error when writing the frame");
+    /// ```
+    pub fn write_black_frame(&self) -> Result<()> {
+        if self.direction != Direction::Output {
+            return Err(Error::Unsupported("writing frames to a capture"));
+        }
+
+        let format = read_format(
+            &self.file,
+            videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT,
+        )?;
+        let Some(frame) = format.solid_frame((0, 0, 0)) else {
+            return Err(Error::Unsupported("filling frames in this pixel format"));
+        };
+
+        if let Err(e) = (&self.file).write_all(&frame) {
+            return Err(Error::Other(Box::new(e)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for DeviceFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Read for &DeviceFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.file).read(buf)
+    }
+}
+
+impl Write for DeviceFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Write for &DeviceFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.file).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&self.file).flush()
+    }
+}
+
+impl AsFd for DeviceFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsRawFd for DeviceFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl From<DeviceFile> for File {
+    fn from(file: DeviceFile) -> Self {
+        file.file
+    }
+}
+
+/// Output of a device holding an exclusive lock on the device node, see
+/// [`Device::open_output_exclusive`](crate::Device::open_output_exclusive).
+///
+/// The lock is released when the guard is dropped. Methods of [`DeviceFile`] are available
+/// through [`Deref`].
+#[derive(Debug)]
+pub struct ExclusiveOutput {
+    file: DeviceFile,
+}
+
+impl ExclusiveOutput {
+    /// Open the output of `/dev/videoN` and lock it.
+    pub(crate) fn open(device_num: u32) -> Result<Self> {
+        let file = DeviceFile::open(device_num, Direction::Output)?;
+
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } < 0 {
+            let e = io::Error::last_os_error();
+            return match e.kind() {
+                ErrorKind::WouldBlock => Err(Error::DeviceBusy(device_num)),
+                _ => Err(Error::Other(Box::new(e))),
+            };
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl Deref for ExclusiveOutput {
+    type Target = DeviceFile;
+
+    fn deref(&self) -> &DeviceFile {
+        &self.file
+    }
+}
+
+impl Write for ExclusiveOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl AsFd for ExclusiveOutput {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsRawFd for ExclusiveOutput {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Drop for ExclusiveOutput {
+    fn drop(&mut self) {
+        // Closing the file releases the lock too, unless its descriptor was duplicated
+        unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_UN) };
+    }
+}
@@ -0,0 +1,821 @@
+use std::{fmt, fs::File, os::fd::AsRawFd, ptr, slice, time::Duration};
+
+use crate::{
+    params::read_format,
+    sys::{ioctl_readwrite, ioctl_write_ptr, libc},
+    video::open_video_device,
+    videodev2, Errno, Error, Result,
+};
+
+ioctl_readwrite!(vidioc_reqbufs, b'V', 8, videodev2::v4l2_requestbuffers);
+ioctl_readwrite!(vidioc_querybuf, b'V', 9, videodev2::v4l2_buffer);
+ioctl_readwrite!(vidioc_qbuf, b'V', 15, videodev2::v4l2_buffer);
+ioctl_readwrite!(vidioc_dqbuf, b'V', 17, videodev2::v4l2_buffer);
+ioctl_write_ptr!(vidioc_streamon, b'V', 18, libc::c_int);
+ioctl_write_ptr!(vidioc_streamoff, b'V', 19, libc::c_int);
+
+const OUTPUT: u32 = videodev2::v4l2_buf_type_V4L2_BUF_TYPE_VIDEO_OUTPUT;
+
+/// Alignment of the frames passed by pointer, the size of the largest pixels of the formats
+/// known by this crate.
+const USERPTR_ALIGN: usize = 4;
+
+/// Memory holding the buffers of a [`FrameWriter`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum MemoryType {
+    /// Buffers allocated by the driver and mapped in the process, frames are copied into them.
+    #[default]
+    Mmap,
+    /// Buffers allocated by the process, passed to the driver by pointer.
+    UserPtr,
+    /// Buffers shared between devices as DMABUF file descriptors.
+    DmaBuf,
+}
+
+impl MemoryType {
+    /// The matching `v4l2_memory` value.
+    fn raw(self) -> u32 {
+        match self {
+            MemoryType::Mmap => videodev2::v4l2_memory_V4L2_MEMORY_MMAP,
+            MemoryType::UserPtr => videodev2::v4l2_memory_V4L2_MEMORY_USERPTR,
+            MemoryType::DmaBuf => videodev2::v4l2_memory_V4L2_MEMORY_DMABUF,
+        }
+    }
+}
+
+/// What a [`FrameWriter`] does when queueing a frame fails, see
+/// [`FrameWriter::set_error_handler`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FrameErrorAction {
+    /// Queue the frame again.
+    Retry,
+    /// Drop the frame, as if it was queued.
+    Skip,
+    /// Return the error.
+    #[default]
+    Abort,
+}
+
+/// Callback choosing what to do with a frame which failed to be queued.
+type ErrorHandler<'a> = dyn FnMut(&Error, u32) -> FrameErrorAction + 'a;
+
+/// Run `attempt` until it succeeds, or until `on_error` tells to stop for one of its errors.
+///
+/// Without `on_error`, the first error is returned.
+fn handle_errors(
+    mut on_error: Option<&mut ErrorHandler<'_>>,
+    mut attempt: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    let mut failures = 0;
+    loop {
+        let Err(e) = attempt() else {
+            return Ok(());
+        };
+        failures += 1;
+
+        let action = match &mut on_error {
+            Some(on_error) => on_error(&e, failures),
+            None => FrameErrorAction::default(),
+        };
+        match action {
+            FrameErrorAction::Retry => {}
+            FrameErrorAction::Skip => return Ok(()),
+            FrameErrorAction::Abort => return Err(e),
+        }
+    }
+}
+
+/// Buffer of the driver mapped in the process.
+#[derive(Debug)]
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    /// Map the buffer at `offset` in the memory of the device opened as `file`.
+    fn new(file: &File, offset: u32, len: usize) -> Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                offset.into(),
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Errno::last().into());
+        }
+
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+/// Streams frames to a device through buffers queued to the driver, as a producer.
+///
+/// Unlike writing to the device, the frames don't go through an extra copy in the driver, and
+/// up to [`FrameWriter::buffer_count`] frames can be pending at once. The device has to have
+/// its format set before creating the writer (see [`set_format`](crate::set_format)), and
+/// the format can't be changed while the writer exists.
+///
+/// The buffers are released when the writer is dropped.
+///
+/// # Example
+///
+/// ```
+/// use v4l2loopback::{
+///     add_device, delete_device, set_format, DeviceConfig, Format, FrameWriter, MemoryType,
+///     PixelFormat, Resolution,
+/// };
+///
+/// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+/// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+/// set_format(device_num, &format).expect("Error when setting the format");
+///
+/// let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 4)
+///     .expect("Error when creating the writer");
+/// for _ in 0..10 {
+///     writer.queue(&[128; 64 * 48]).expect("Error when queueing the frame");
+/// }
+///
+/// drop(writer);
+/// delete_device(device_num).expect("Error when removing device");
+/// ```
+pub struct FrameWriter {
+    file: File,
+    memory: MemoryType,
+    frame_len: Option<usize>,
+    count: u32,
+    mappings: Vec<Mapping>,
+    /// Buffers which were never queued, the next one being `count - unused`.
+    unused: u32,
+    streaming: bool,
+    on_error: Option<Box<ErrorHandler<'static>>>,
+}
+
+impl fmt::Debug for FrameWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameWriter")
+            .field("file", &self.file)
+            .field("memory", &self.memory)
+            .field("frame_len", &self.frame_len)
+            .field("count", &self.count)
+            .field("mappings", &self.mappings)
+            .field("unused", &self.unused)
+            .field("streaming", &self.streaming)
+            .field("on_error", &self.on_error.is_some())
+            .finish()
+    }
+}
+
+impl FrameWriter {
+    /// Request `count` buffers of the `memory` type on the output of `/dev/videoN`.
+    ///
+    /// The driver can allocate fewer buffers than requested, see [`FrameWriter::buffer_count`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`DeviceNotFound`] if `/dev/videoN` doesn't exist.
+    /// - [`Unsupported`] if the device doesn't handle buffers of the `memory` type.
+    /// - [`Ioctl`] if the buffers couldn't be allocated or mapped, for example with
+    ///   [`EBUSY`] if another producer is streaming to the device.
+    /// - [`Other`] if the device couldn't be opened.
+    ///
+    /// [`DeviceNotFound`]: Error::DeviceNotFound
+    /// [`Unsupported`]: Error::Unsupported
+    /// [`Ioctl`]: Error::Ioctl
+    /// [`EBUSY`]: Errno::EBUSY
+    /// [`Other`]: Error::Other
+    pub fn new(device_num: u32, memory: MemoryType, count: u32) -> Result<Self> {
+        let file = open_video_device(device_num, true)?;
+        let frame_len = read_format(&file, OUTPUT)?.image_size();
+
+        let mut request: videodev2::v4l2_requestbuffers = unsafe { std::mem::zeroed() };
+        request.count = count;
+        request.type_ = OUTPUT;
+        request.memory = memory.raw();
+        match unsafe { vidioc_reqbufs(file.as_raw_fd(), &mut request) } {
+            Ok(_) => {}
+            Err(Errno::EINVAL) => return Err(Error::Unsupported("this memory type for buffers")),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut writer = Self {
+            file,
+            memory,
+            frame_len,
+            count: request.count,
+            mappings: Vec::new(),
+            unused: request.count,
+            streaming: false,
+            on_error: None,
+        };
+        if memory == MemoryType::Mmap {
+            for index in 0..writer.count {
+                let mut buffer = writer.buffer(index);
+                unsafe { vidioc_querybuf(writer.file.as_raw_fd(), &mut buffer) }?;
+                let offset = unsafe { buffer.m.offset };
+                let mapping = Mapping::new(&writer.file, offset, buffer.length as usize)?;
+                writer.mappings.push(mapping);
+            }
+        }
+
+        Ok(writer)
+    }
+
+    /// Number of buffers actually allocated by the driver.
+    ///
+    /// The driver clamps the count requested in [`FrameWriter::new`], for example to the
+    /// [`max_buffers`](crate::DeviceConfig::max_buffers) of the device.
+    pub fn buffer_count(&self) -> u32 {
+        self.count
+    }
+
+    /// The memory type of the buffers.
+    pub fn memory_type(&self) -> MemoryType {
+        self.memory
+    }
+
+    /// A buffer description of the writer, at `index`.
+    fn buffer(&self, index: u32) -> videodev2::v4l2_buffer {
+        let mut buffer: videodev2::v4l2_buffer = unsafe { std::mem::zeroed() };
+        buffer.index = index;
+        buffer.type_ = OUTPUT;
+        buffer.memory = self.memory.raw();
+        buffer.field = videodev2::v4l2_field_V4L2_FIELD_NONE;
+        buffer
+    }
+
+    /// Check that `frame` fits the format of the device.
+    fn check_len(&self, frame: &[u8]) -> Result<()> {
+        match self.frame_len {
+            Some(expected) if frame.len() != expected => Err(Error::FrameSize {
+                expected,
+                actual: frame.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Index of a buffer the driver is done with, dequeueing one if every buffer was queued.
+    fn free_buffer(&mut self) -> Result<u32> {
+        if self.unused > 0 {
+            self.unused -= 1;
+            return Ok(self.count - self.unused - 1);
+        }
+
+        let mut buffer = self.buffer(0);
+        unsafe { vidioc_dqbuf(self.file.as_raw_fd(), &mut buffer) }?;
+        Ok(buffer.index)
+    }
+
+    /// Queue `buffer`, starting the stream with the first frame.
+    fn submit(&mut self, mut buffer: videodev2::v4l2_buffer) -> Result<()> {
+        unsafe { vidioc_qbuf(self.file.as_raw_fd(), &mut buffer) }?;
+        if !self.streaming {
+            unsafe { vidioc_streamon(self.file.as_raw_fd(), &(OUTPUT as libc::c_int)) }?;
+            self.streaming = true;
+        }
+
+        Ok(())
+    }
+
+    /// Queue a whole frame, to be shown to consumers.
+    ///
+    /// The driver stamps the frame when it is queued, see [`FrameWriter::queue_with`] to
+    /// choose its timestamp.
+    ///
+    /// With [`MemoryType::Mmap`] buffers, the frame is copied to a free buffer. Once every
+    /// buffer is queued, this waits for the driver to release one.
+    ///
+    /// With [`MemoryType::UserPtr`] buffers, the driver reads the frame where it is, without
+    /// copying it to a buffer first. The frame must start on a 4 bytes boundary. It is given
+    /// back by the driver before returning, so it can be reused right away.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the following errors:
+    /// - [`FrameSize`] if the frame doesn't have the size of the format of the device.
+    /// - [`Unsupported`] with [`MemoryType::DmaBuf`] buffers, which can't hold a frame from
+    ///   memory.
+    /// - [`Ioctl`] if the frame couldn't be queued.
+    /// - [`Other`] if the frame is passed by pointer and isn't aligned.
+    ///
+    /// Errors are first passed to the handler set with [`FrameWriter::set_error_handler`], if
+    /// any.
+    ///
+    /// [`FrameSize`]: Error::FrameSize
+    /// [`Unsupported`]: Error::Unsupported
+    /// [`Ioctl`]: Error::Ioctl
+    /// [`Other`]: Error::Other
+    pub fn queue(&mut self, frame: &[u8]) -> Result<()> {
+        self.queue_with(frame, None, None)
+    }
+
+    /// Queue a whole frame like [`FrameWriter::queue`], with its timestamp and sequence number.
+    ///
+    /// A `timestamp` replaces the one the driver would give to the frame, so consumers can
+    /// align it with the clock of the producer, for example to keep audio in sync. Without
+    /// `sequence`, the sequence number is left to the driver. v4l2loopback numbers the frames
+    /// itself, so a given sequence number may not reach consumers.
+    ///
+    /// # Errors
+    ///
+    /// See [`FrameWriter::queue`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use v4l2loopback::{
+    ///     add_device, delete_device, last_frame_info, set_format, DeviceConfig, Format,
+    ///     FrameWriter, MemoryType, PixelFormat, Resolution,
+    /// };
+    ///
+    /// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+    /// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+    /// set_format(device_num, &format).expect("Error when setting the format");
+    ///
+    /// let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 4)
+    ///     .expect("Error when creating the writer");
+    /// let timestamp = Duration::from_millis(40);
+    /// writer
+    ///     .queue_with(&[128; 64 * 48], Some(timestamp), Some(1))
+    ///     .expect("Error when queueing the frame");
+    ///
+    /// let info = last_frame_info(device_num).expect("Error when fetching the frame info");
+    /// assert_eq!(info.unwrap().timestamp, timestamp);
+    ///
+    /// drop(writer);
+    /// delete_device(device_num).expect("Error when removing device");
+    /// ```
+    pub fn queue_with(
+        &mut self,
+        frame: &[u8],
+        timestamp: Option<Duration>,
+        sequence: Option<u32>,
+    ) -> Result<()> {
+        let mut on_error = self.on_error.take();
+        let res = handle_errors(on_error.as_deref_mut(), || {
+            self.try_queue(frame, timestamp, sequence)
+        });
+        self.on_error = on_error;
+
+        res
+    }
+
+    /// Queue a whole frame held in a [`Bytes`](bytes::Bytes) buffer, see [`FrameWriter::queue`].
+    ///
+    /// The frame is copied to the buffers of the device straight from `frame`, or passed by
+    /// pointer with [`MemoryType::UserPtr`] buffers, without going through a `Vec`.
+    ///
+    /// # Errors
+    ///
+    /// See [`FrameWriter::queue`].
+    #[cfg(feature = "bytes")]
+    pub fn queue_bytes(&mut self, frame: &bytes::Bytes) -> Result<()> {
+        self.queue(frame)
+    }
+
+    /// Choose what happens when queueing a frame fails, instead of returning the error.
+    ///
+    /// `handler` is called with the error and how many times in a row the frame failed, and
+    /// tells whether to [retry](FrameErrorAction::Retry) the frame, to
+    /// [skip](FrameErrorAction::Skip) it, or to [abort](FrameErrorAction::Abort) by returning
+    /// the error, which is what happens without a handler. This lets long running streams
+    /// survive transient failures.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l2loopback::{
+    ///     add_device, delete_device, set_format, DeviceConfig, Errno, Error, Format,
+    ///     FrameErrorAction, FrameWriter, MemoryType, PixelFormat, Resolution,
+    /// };
+    ///
+    /// let device_num = add_device(None, DeviceConfig::default()).expect("Error when creating the device");
+    /// let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+    /// set_format(device_num, &format).expect("Error when setting the format");
+    ///
+    /// let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 4)
+    ///     .expect("Error when creating the writer");
+    /// writer.set_error_handler(|e, failures| match e {
+    ///     Error::Ioctl(Errno::EAGAIN | Errno::EINTR) if failures < 3 => FrameErrorAction::Retry,
+    ///     Error::FrameSize { .. } => FrameErrorAction::Skip,
+    ///     _ => FrameErrorAction::Abort,
+    /// });
+    /// writer.queue(&[0; 16]).expect("The truncated frame should be skipped");
+    ///
+    /// drop(writer);
+    /// delete_device(device_num).expect("Error when removing device");
+    /// ```
+    pub fn set_error_handler(
+        &mut self,
+        handler: impl FnMut(&Error, u32) -> FrameErrorAction + 'static,
+    ) {
+        self.on_error = Some(Box::new(handler));
+    }
+
+    /// Queue a frame once, see [`FrameWriter::queue_with`].
+    fn try_queue(
+        &mut self,
+        frame: &[u8],
+        timestamp: Option<Duration>,
+        sequence: Option<u32>,
+    ) -> Result<()> {
+        self.check_len(frame)?;
+        let mut buffer = match self.memory {
+            MemoryType::Mmap => {
+                let capacity = self.mappings.first().map_or(0, |mapping| mapping.len);
+                if frame.len() > capacity {
+                    return Err(Error::FrameSize {
+                        expected: capacity,
+                        actual: frame.len(),
+                    });
+                }
+
+                let index = self.free_buffer()?;
+                self.mappings[index as usize].as_mut_slice()[..frame.len()].copy_from_slice(frame);
+                self.buffer(index)
+            }
+            MemoryType::UserPtr => {
+                check_alignment(frame)?;
+
+                // Every frame is dequeued before returning, so the first buffer is always free
+                let mut buffer = self.buffer(0);
+                buffer.m.userptr = frame.as_ptr() as _;
+                buffer.length = frame.len() as u32;
+                buffer
+            }
+            MemoryType::DmaBuf => {
+                return Err(Error::Unsupported(
+                    "queueing frames from memory to DMABUF buffers",
+                ))
+            }
+        };
+        buffer.bytesused = frame.len() as u32;
+        stamp(&mut buffer, timestamp, sequence);
+
+        let res = self.submit(buffer).and_then(|()| {
+            // The driver must not keep a pointer to the frame once it is given back
+            if self.memory == MemoryType::UserPtr {
+                unsafe { vidioc_dqbuf(self.file.as_raw_fd(), &mut buffer) }?;
+            }
+            Ok(())
+        });
+        if res.is_err() {
+            // Get every buffer back, so none is left out of the count of free buffers
+            let _ = self.stop();
+        }
+        res
+    }
+
+    /// Discard the frames queued but not taken by the driver yet, so the next queued frame is
+    /// the next one consumers get.
+    ///
+    /// This is useful when resuming after a pause, to avoid showing frames from before the
+    /// pause. The stream is stopped until the next frame is queued, consumers don't get any
+    /// frame in between.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Ioctl`] if the stream couldn't be stopped.
+    ///
+    /// [`Ioctl`]: Error::Ioctl
+    pub fn flush(&mut self) -> Result<()> {
+        if !self.streaming {
+            return Ok(());
+        }
+
+        self.stop()
+    }
+
+    /// Stop the stream, which gives every queued buffer back.
+    fn stop(&mut self) -> Result<()> {
+        let res = unsafe { vidioc_streamoff(self.file.as_raw_fd(), &(OUTPUT as libc::c_int)) };
+        self.streaming = false;
+        self.unused = self.count;
+
+        res.map(|_| ()).map_err(Error::from)
+    }
+}
+
+/// Check that a frame passed by pointer is aligned on [`USERPTR_ALIGN`] bytes.
+fn check_alignment(frame: &[u8]) -> Result<()> {
+    if frame.as_ptr().align_offset(USERPTR_ALIGN) != 0 {
+        return Err(Error::Other(
+            format!("The frame isn't aligned on {} bytes", USERPTR_ALIGN).into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Set the `timestamp` and `sequence` of a buffer about to be queued, when they are given.
+fn stamp(buffer: &mut videodev2::v4l2_buffer, timestamp: Option<Duration>, sequence: Option<u32>) {
+    if let Some(timestamp) = timestamp {
+        buffer.timestamp.tv_sec = timestamp.as_secs() as _;
+        buffer.timestamp.tv_usec = timestamp.subsec_micros() as _;
+        buffer.flags |= videodev2::V4L2_BUF_FLAG_TIMESTAMP_COPY;
+    }
+    if let Some(sequence) = sequence {
+        buffer.sequence = sequence;
+    }
+}
+
+impl Drop for FrameWriter {
+    fn drop(&mut self) {
+        if self.streaming {
+            let _ = self.stop();
+        }
+        // Buffers can't be freed while they are mapped
+        self.mappings.clear();
+        let mut request: videodev2::v4l2_requestbuffers = unsafe { std::mem::zeroed() };
+        request.type_ = OUTPUT;
+        request.memory = self.memory.raw();
+        let _ = unsafe { vidioc_reqbufs(self.file.as_raw_fd(), &mut request) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Read, os::fd::AsRawFd, time::Duration};
+
+    use super::{
+        check_alignment, handle_errors, stamp, vidioc_querybuf, FrameErrorAction, FrameWriter,
+        MemoryType,
+    };
+    use crate::{
+        add_device, delete_device, last_frame_info, set_format, videodev2, DeviceConfig, Errno,
+        Error, Format, PixelFormat, Resolution,
+    };
+
+    #[test]
+    fn memory_types() {
+        assert_eq!(MemoryType::default(), MemoryType::Mmap);
+        let raw: Vec<u32> = [MemoryType::Mmap, MemoryType::UserPtr, MemoryType::DmaBuf]
+            .into_iter()
+            .map(MemoryType::raw)
+            .collect();
+        assert_eq!(raw, [1, 2, 4]);
+    }
+
+    #[test]
+    fn clamped_buffer_count() {
+        let config = DeviceConfig {
+            max_buffers: 2,
+            ..Default::default()
+        };
+        let device_num = add_device(None, config).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let writer = FrameWriter::new(device_num, MemoryType::Mmap, 32)
+            .expect("Error when creating the writer");
+        let count = writer.buffer_count();
+        assert!((1..=2).contains(&count));
+
+        // Every reported buffer exists, and no other
+        let fd = writer.file.as_raw_fd();
+        for index in 0..count {
+            let mut buffer = writer.buffer(index);
+            unsafe { vidioc_querybuf(fd, &mut buffer) }.expect("Missing buffer");
+        }
+        let mut buffer = writer.buffer(count);
+        assert_eq!(
+            unsafe { vidioc_querybuf(fd, &mut buffer) },
+            Err(Errno::EINVAL)
+        );
+
+        drop(writer);
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn stamped_buffers() {
+        let mut buffer: videodev2::v4l2_buffer = unsafe { std::mem::zeroed() };
+        stamp(&mut buffer, None, None);
+        assert_eq!((buffer.timestamp.tv_sec, buffer.timestamp.tv_usec), (0, 0));
+        assert_eq!((buffer.flags, buffer.sequence), (0, 0));
+
+        stamp(&mut buffer, Some(Duration::from_micros(2_500_001)), Some(7));
+        assert_eq!(
+            (buffer.timestamp.tv_sec, buffer.timestamp.tv_usec),
+            (2, 500_001)
+        );
+        assert_eq!(buffer.flags, videodev2::V4L2_BUF_FLAG_TIMESTAMP_COPY);
+        assert_eq!(buffer.sequence, 7);
+    }
+
+    #[test]
+    fn timestamp_read_back() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 2)
+            .expect("Error when creating the writer");
+        let timestamp = Duration::from_millis(1500);
+        writer
+            .queue_with(&[128; 64 * 48], Some(timestamp), Some(3))
+            .expect("Error when queueing the frame");
+
+        let mut capture = File::open(format!("/dev/video{}", device_num)).unwrap();
+        let mut frame = vec![0; 64 * 48];
+        capture
+            .read_exact(&mut frame)
+            .expect("Error when reading the frame");
+        assert_eq!(frame, [128; 64 * 48]);
+        let info = last_frame_info(device_num)
+            .expect("Error when fetching the frame info")
+            .expect("No frame was written");
+        assert_eq!(info.timestamp, timestamp);
+
+        drop(writer);
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn userptr_alignment() {
+        let frame = [0u32; 16];
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(frame.as_ptr().cast(), 64) };
+        assert!(check_alignment(bytes).is_ok());
+        assert!(matches!(check_alignment(&bytes[1..]), Err(Error::Other(_))));
+    }
+
+    #[test]
+    fn userptr_writer() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let mut writer = match FrameWriter::new(device_num, MemoryType::UserPtr, 2) {
+            Ok(writer) => writer,
+            Err(Error::Unsupported(_)) => {
+                delete_device(device_num).expect("Error when removing device");
+                return;
+            }
+            Err(e) => panic!("Error when creating the writer: {}", e),
+        };
+        let frame = [0x4040_4040u32; 64 * 48 / 4];
+        let frame: &[u8] = unsafe { std::slice::from_raw_parts(frame.as_ptr().cast(), 64 * 48) };
+        for _ in 0..5 {
+            writer.queue(frame).expect("Error when queueing the frame");
+        }
+
+        let mut captured = vec![0; 64 * 48];
+        File::open(format!("/dev/video{}", device_num))
+            .unwrap()
+            .read_exact(&mut captured)
+            .expect("Error when reading the frame");
+        assert_eq!(captured, frame);
+
+        drop(writer);
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn flushed_frames_discarded() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 2)
+            .expect("Error when creating the writer");
+        writer
+            .flush()
+            .expect("Error when flushing before streaming");
+        for _ in 0..3 {
+            writer
+                .queue(&[16; 64 * 48])
+                .expect("Error when queueing the frame");
+        }
+        writer.flush().expect("Error when flushing the writer");
+        writer
+            .queue(&[235; 64 * 48])
+            .expect("Error when queueing the frame");
+
+        let mut frame = vec![0; 64 * 48];
+        File::open(format!("/dev/video{}", device_num))
+            .unwrap()
+            .read_exact(&mut frame)
+            .expect("Error when reading the frame");
+        assert_eq!(frame, [235; 64 * 48]);
+
+        drop(writer);
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn frame_error_policies() {
+        let transient = || {
+            let mut calls = 0;
+            move || {
+                calls += 1;
+                match calls {
+                    1 => Err(Error::Ioctl(Errno::EAGAIN)),
+                    _ => Ok(()),
+                }
+            }
+        };
+
+        assert!(matches!(
+            handle_errors(None, transient()),
+            Err(Error::Ioctl(Errno::EAGAIN))
+        ));
+
+        let mut failures = Vec::new();
+        let mut skip = |_: &Error, count| {
+            failures.push(count);
+            FrameErrorAction::Skip
+        };
+        assert!(handle_errors(Some(&mut skip), transient()).is_ok());
+        assert_eq!(failures, [1]);
+
+        let mut attempts = 0;
+        let mut retry = |_: &Error, _| FrameErrorAction::Retry;
+        let res = handle_errors(Some(&mut retry), || {
+            attempts += 1;
+            match attempts {
+                1..=2 => Err(Error::Ioctl(Errno::EINTR)),
+                _ => Ok(()),
+            }
+        });
+        assert!(res.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn skipped_frame_keeps_stream() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 2)
+            .expect("Error when creating the writer");
+        writer.set_error_handler(|_, _| FrameErrorAction::Skip);
+        writer.queue(&[0; 16]).expect("The frame should be skipped");
+        writer
+            .queue(&[128; 64 * 48])
+            .expect("Error when queueing the frame");
+
+        let info = last_frame_info(device_num)
+            .expect("Error when fetching the frame info")
+            .expect("No frame was written");
+        assert_eq!(info.bytes_used, 64 * 48);
+
+        drop(writer);
+        delete_device(device_num).expect("Error when removing device");
+    }
+
+    #[test]
+    fn mmap_writer() {
+        let device_num =
+            add_device(None, Default::default()).expect("Error when creating the device");
+        let format = Format::new(PixelFormat::Grey, Resolution::new(64, 48));
+        set_format(device_num, &format).expect("Error when setting the format");
+
+        let mut writer = FrameWriter::new(device_num, MemoryType::Mmap, 2)
+            .expect("Error when creating the writer");
+        assert_eq!(writer.memory_type(), MemoryType::Mmap);
+        assert!(matches!(
+            writer.queue(&[0; 16]),
+            Err(Error::FrameSize {
+                expected: 3072,
+                actual: 16
+            })
+        ));
+        for _ in 0..5 {
+            writer
+                .queue(&[128; 64 * 48])
+                .expect("Error when queueing the frame");
+        }
+        let info = last_frame_info(device_num)
+            .expect("Error when fetching the frame info")
+            .expect("No frame was written");
+        assert_eq!(info.bytes_used, 64 * 48);
+
+        drop(writer);
+        delete_device(device_num).expect("Error when removing device");
+    }
+}